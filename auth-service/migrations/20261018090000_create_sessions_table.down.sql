@@ -0,0 +1,3 @@
+
+DROP TABLE if exists sessions;
+
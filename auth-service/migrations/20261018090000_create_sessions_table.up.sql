@@ -0,0 +1,13 @@
+
+-- Sessions table. One row per login, keyed by the refresh token family id.
+--
+CREATE TABLE IF NOT EXISTS sessions(
+   id             TEXT        NOT NULL PRIMARY KEY,
+   email          TEXT        NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   user_agent     TEXT,
+   ip             TEXT,
+   created_at     TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_seen      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sessions_email_idx ON sessions(email);
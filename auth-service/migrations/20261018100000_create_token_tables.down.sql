@@ -0,0 +1,4 @@
+
+DROP TABLE if exists two_fa_codes;
+DROP TABLE if exists token_not_before;
+DROP TABLE if exists banned_tokens;
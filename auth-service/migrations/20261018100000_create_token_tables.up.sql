@@ -0,0 +1,31 @@
+
+
+-- Revoked tokens and sessions, by their jti or sid. A row is only needed until the tokens it revokes expire.
+--
+CREATE TABLE IF NOT EXISTS banned_tokens(
+   jti            TEXT        NOT NULL PRIMARY KEY,
+   expires_at     TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS banned_tokens_expires_at_idx ON banned_tokens(expires_at);
+
+-- Per subject revocation epoch. Tokens issued at or before the epoch are revoked.
+--
+CREATE TABLE IF NOT EXISTS token_not_before(
+   subject        TEXT        NOT NULL PRIMARY KEY,
+   epoch          BIGINT      NOT NULL,
+   expires_at     TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS token_not_before_expires_at_idx ON token_not_before(expires_at);
+
+-- Pending 2FA codes. One per user; a new login attempt replaces the previous code.
+--
+CREATE TABLE IF NOT EXISTS two_fa_codes(
+   email            TEXT        NOT NULL PRIMARY KEY,
+   login_attempt_id TEXT        NOT NULL,
+   code             TEXT        NOT NULL,
+   expires_at       TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS two_fa_codes_expires_at_idx ON two_fa_codes(expires_at);
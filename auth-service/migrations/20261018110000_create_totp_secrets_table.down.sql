@@ -0,0 +1,2 @@
+
+DROP TABLE if exists totp_secrets;
@@ -0,0 +1,12 @@
+
+
+-- Authenticator app (TOTP) enrollments. The secret is AES-GCM encrypted by the service.
+-- last_used_step is the time step of the last accepted code, so a code cannot be replayed.
+--
+CREATE TABLE IF NOT EXISTS totp_secrets(
+   email            TEXT        NOT NULL PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE,
+   secret           BYTEA       NOT NULL,
+   confirmed        BOOLEAN     NOT NULL DEFAULT FALSE,
+   last_used_step   BIGINT,
+   created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1 @@
+DROP TABLE if exists recovery_codes;
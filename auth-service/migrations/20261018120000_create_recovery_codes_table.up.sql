@@ -0,0 +1,11 @@
+-- Single use 2FA recovery codes, stored as Argon2 hashes. A code is spent once used_at is set.
+--
+CREATE TABLE IF NOT EXISTS recovery_codes(
+   id          BIGSERIAL   PRIMARY KEY,
+   email       TEXT        NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   code_hash   TEXT        NOT NULL,
+   used_at     TIMESTAMPTZ,
+   created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS recovery_codes_email_idx ON recovery_codes(email);
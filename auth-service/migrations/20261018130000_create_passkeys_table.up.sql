@@ -0,0 +1,14 @@
+-- WebAuthn credentials (passkeys), keyed by their base64url credential id.
+-- passkey is the serialized credential, including its public key and signature counter.
+-- user_handle is the WebAuthn user id, the same for every passkey of a user.
+--
+CREATE TABLE IF NOT EXISTS passkeys(
+   credential_id  TEXT        NOT NULL PRIMARY KEY,
+   email          TEXT        NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   user_handle    TEXT        NOT NULL,
+   passkey        TEXT        NOT NULL,
+   created_at     TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_used_at   TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS passkeys_email_idx ON passkeys(email);
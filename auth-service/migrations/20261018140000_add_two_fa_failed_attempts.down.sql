@@ -0,0 +1 @@
+ALTER TABLE two_fa_codes DROP COLUMN IF EXISTS failed_attempts;
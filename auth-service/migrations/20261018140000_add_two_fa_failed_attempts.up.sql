@@ -0,0 +1,3 @@
+-- Wrong answers to the current login attempt. Reset whenever a new code is issued.
+--
+ALTER TABLE two_fa_codes ADD COLUMN IF NOT EXISTS failed_attempts INTEGER NOT NULL DEFAULT 0;
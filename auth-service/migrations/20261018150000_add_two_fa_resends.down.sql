@@ -0,0 +1,2 @@
+ALTER TABLE two_fa_codes DROP COLUMN IF EXISTS last_resent_at;
+ALTER TABLE two_fa_codes DROP COLUMN IF EXISTS resend_count;
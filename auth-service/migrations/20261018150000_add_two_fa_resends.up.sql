@@ -0,0 +1,5 @@
+-- How often the code for the current login attempt was resent, and when it last was.
+-- Both reset when a login starts a new attempt.
+--
+ALTER TABLE two_fa_codes ADD COLUMN IF NOT EXISTS resend_count   INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE two_fa_codes ADD COLUMN IF NOT EXISTS last_resent_at TIMESTAMPTZ;
@@ -0,0 +1,10 @@
+DROP INDEX IF EXISTS two_fa_codes_email_idx;
+
+-- Back to one code per user: keep each user's latest login attempt.
+--
+DELETE FROM two_fa_codes a
+USING two_fa_codes b
+WHERE a.email = b.email AND (a.expires_at, a.login_attempt_id) < (b.expires_at, b.login_attempt_id);
+
+ALTER TABLE two_fa_codes DROP CONSTRAINT IF EXISTS two_fa_codes_pkey;
+ALTER TABLE two_fa_codes ADD PRIMARY KEY (email);
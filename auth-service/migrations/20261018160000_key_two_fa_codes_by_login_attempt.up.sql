@@ -0,0 +1,6 @@
+-- Pending 2FA codes are keyed by login attempt, so a user can have several in flight.
+--
+ALTER TABLE two_fa_codes DROP CONSTRAINT IF EXISTS two_fa_codes_pkey;
+ALTER TABLE two_fa_codes ADD PRIMARY KEY (login_attempt_id);
+
+CREATE INDEX IF NOT EXISTS two_fa_codes_email_idx ON two_fa_codes(email);
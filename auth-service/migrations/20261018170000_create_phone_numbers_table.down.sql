@@ -0,0 +1,2 @@
+
+DROP TABLE if exists phone_numbers;
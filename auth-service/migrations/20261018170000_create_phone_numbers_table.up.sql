@@ -0,0 +1,14 @@
+
+-- A phone number per user, for 2FA codes sent by SMS. The number is verified with a code
+-- sent to it; two_fa_channel only says 'sms' once it is.
+--
+CREATE TABLE IF NOT EXISTS phone_numbers(
+   email             TEXT        NOT NULL PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE,
+   phone_number      TEXT        NOT NULL,
+   verified          BOOLEAN     NOT NULL DEFAULT FALSE,
+   two_fa_channel    TEXT        NOT NULL DEFAULT 'email' CHECK (two_fa_channel IN ('email', 'sms')),
+   verification_code TEXT,
+   code_expires_at   TIMESTAMPTZ,
+   failed_attempts   INTEGER     NOT NULL DEFAULT 0,
+   CHECK (two_fa_channel = 'email' OR verified)
+);
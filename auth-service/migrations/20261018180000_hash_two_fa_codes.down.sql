@@ -0,0 +1,7 @@
+-- Hashes cannot be turned back into codes, so pending ones are dropped.
+--
+DELETE FROM two_fa_codes;
+ALTER TABLE two_fa_codes RENAME COLUMN code_hash TO code;
+
+UPDATE phone_numbers SET verification_code_hash = NULL, code_expires_at = NULL WHERE verification_code_hash IS NOT NULL;
+ALTER TABLE phone_numbers RENAME COLUMN verification_code_hash TO verification_code;
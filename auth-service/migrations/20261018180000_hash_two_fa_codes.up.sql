@@ -0,0 +1,8 @@
+-- 2FA codes are stored as keyed hashes instead of in plain text. Codes already sent cannot be
+-- hashed after the fact, so pending ones are dropped; their users simply log in again.
+--
+DELETE FROM two_fa_codes;
+ALTER TABLE two_fa_codes RENAME COLUMN code TO code_hash;
+
+UPDATE phone_numbers SET verification_code = NULL, code_expires_at = NULL WHERE verification_code IS NOT NULL;
+ALTER TABLE phone_numbers RENAME COLUMN verification_code TO verification_code_hash;
@@ -0,0 +1,5 @@
+-- New users have to verify their email address before they can log in.
+-- Accounts that already exist predate verification and count as verified.
+--
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE users ALTER COLUMN email_verified SET DEFAULT FALSE;
@@ -0,0 +1,12 @@
+DROP TABLE IF EXISTS email_changes;
+
+ALTER TABLE sessions       DROP CONSTRAINT IF EXISTS sessions_email_fkey;
+ALTER TABLE sessions       ADD  CONSTRAINT sessions_email_fkey       FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE totp_secrets   DROP CONSTRAINT IF EXISTS totp_secrets_email_fkey;
+ALTER TABLE totp_secrets   ADD  CONSTRAINT totp_secrets_email_fkey   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE recovery_codes DROP CONSTRAINT IF EXISTS recovery_codes_email_fkey;
+ALTER TABLE recovery_codes ADD  CONSTRAINT recovery_codes_email_fkey FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE passkeys       DROP CONSTRAINT IF EXISTS passkeys_email_fkey;
+ALTER TABLE passkeys       ADD  CONSTRAINT passkeys_email_fkey       FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE phone_numbers  DROP CONSTRAINT IF EXISTS phone_numbers_email_fkey;
+ALTER TABLE phone_numbers  ADD  CONSTRAINT phone_numbers_email_fkey  FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
@@ -0,0 +1,26 @@
+-- Users can change their email address, the key every table uses for them.
+-- References to it follow a change instead of blocking it.
+--
+ALTER TABLE sessions       DROP CONSTRAINT IF EXISTS sessions_email_fkey;
+ALTER TABLE sessions       ADD  CONSTRAINT sessions_email_fkey       FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE totp_secrets   DROP CONSTRAINT IF EXISTS totp_secrets_email_fkey;
+ALTER TABLE totp_secrets   ADD  CONSTRAINT totp_secrets_email_fkey   FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE recovery_codes DROP CONSTRAINT IF EXISTS recovery_codes_email_fkey;
+ALTER TABLE recovery_codes ADD  CONSTRAINT recovery_codes_email_fkey FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE passkeys       DROP CONSTRAINT IF EXISTS passkeys_email_fkey;
+ALTER TABLE passkeys       ADD  CONSTRAINT passkeys_email_fkey       FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+ALTER TABLE phone_numbers  DROP CONSTRAINT IF EXISTS phone_numbers_email_fkey;
+ALTER TABLE phone_numbers  ADD  CONSTRAINT phone_numbers_email_fkey  FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- Pending email changes, one per user. The tokens that confirm or cancel a change are
+-- kept as SHA-256 digests.
+--
+CREATE TABLE IF NOT EXISTS email_changes(
+   email            TEXT        NOT NULL PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE,
+   new_email        TEXT        NOT NULL,
+   confirm_digest   TEXT        NOT NULL UNIQUE,
+   cancel_digest    TEXT        NOT NULL UNIQUE,
+   expires_at       TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS email_changes_expires_at_idx ON email_changes(expires_at);
@@ -0,0 +1,2 @@
+ALTER TABLE phone_numbers DROP COLUMN IF EXISTS last_sent_at;
+ALTER TABLE phone_numbers DROP COLUMN IF EXISTS resend_count;
@@ -0,0 +1,6 @@
+-- How often a code was texted to a number being verified, and when it last was. Within the
+-- lifetime of a code these and failed_attempts carry over when the number is registered again,
+-- so registering over and over neither sends unlimited texts nor resets the wrong answers.
+--
+ALTER TABLE phone_numbers ADD COLUMN IF NOT EXISTS resend_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE phone_numbers ADD COLUMN IF NOT EXISTS last_sent_at TIMESTAMPTZ;
@@ -0,0 +1,7 @@
+-- What is left of a deleted account. The id is random, so nothing here leads back to the
+-- user; it only shows that an account was erased, and when. Audit log lines carry the same id.
+--
+CREATE TABLE IF NOT EXISTS account_tombstones(
+   id         TEXT        NOT NULL PRIMARY KEY,
+   deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -1,34 +1,75 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{EmailClient, TokenStore};
+use crate::domain::{EmailChangeStore, EmailClient, PasskeyStore, PasswordResetTokenStore, PhoneStore, RecoveryCodeStore, RefreshTokenStore, SessionStore, SmsClient, TokenStore, TotpStore, WebAuthnChallengeStore};
 use crate::domain::TwoFACodeStore;
 use crate::domain::UserStore;
 
-type EmailClientTraitObject        = dyn EmailClient     + Send + Sync;
-type TokenStoreTraitObject         = dyn TokenStore      + Send + Sync;
-type TwoFactorCodeStoreTraitObject = dyn TwoFACodeStore  + Send + Sync;
-type UserStoreTraitObject          = dyn UserStore       + Send + Sync;
-pub type EmailClientType           = Arc<RwLock<  EmailClientTraitObject>>;
-pub type TokenStoreType            = Arc<RwLock<   TokenStoreTraitObject>>;
-pub type TwoFactorCodeStoreType    = Arc<RwLock<TwoFactorCodeStoreTraitObject>>;
-pub type UserStoreType             = Arc<RwLock<    UserStoreTraitObject>>;
+type EmailChangeStoreTraitObject        = dyn EmailChangeStore        + Send + Sync;
+type EmailClientTraitObject             = dyn EmailClient             + Send + Sync;
+type PasskeyStoreTraitObject            = dyn PasskeyStore            + Send + Sync;
+type PasswordResetTokenStoreTraitObject = dyn PasswordResetTokenStore + Send + Sync;
+type PhoneStoreTraitObject              = dyn PhoneStore              + Send + Sync;
+type RecoveryCodeStoreTraitObject       = dyn RecoveryCodeStore       + Send + Sync;
+type RefreshTokenStoreTraitObject       = dyn RefreshTokenStore       + Send + Sync;
+type SessionStoreTraitObject            = dyn SessionStore            + Send + Sync;
+type SmsClientTraitObject               = dyn SmsClient               + Send + Sync;
+type TokenStoreTraitObject              = dyn TokenStore              + Send + Sync;
+type TotpStoreTraitObject               = dyn TotpStore               + Send + Sync;
+type TwoFactorCodeStoreTraitObject      = dyn TwoFACodeStore          + Send + Sync;
+type UserStoreTraitObject               = dyn UserStore               + Send + Sync;
+type WebAuthnChallengeStoreTraitObject  = dyn WebAuthnChallengeStore  + Send + Sync;
+pub type EmailChangeStoreType           = Arc<RwLock<       EmailChangeStoreTraitObject>>;
+pub type EmailClientType                = Arc<RwLock<            EmailClientTraitObject>>;
+pub type PasskeyStoreType               = Arc<RwLock<           PasskeyStoreTraitObject>>;
+pub type PasswordResetTokenStoreType    = Arc<RwLock<PasswordResetTokenStoreTraitObject>>;
+pub type PhoneStoreType                 = Arc<RwLock<             PhoneStoreTraitObject>>;
+pub type RecoveryCodeStoreType          = Arc<RwLock<      RecoveryCodeStoreTraitObject>>;
+pub type RefreshTokenStoreType          = Arc<RwLock<      RefreshTokenStoreTraitObject>>;
+pub type SessionStoreType               = Arc<RwLock<           SessionStoreTraitObject>>;
+pub type SmsClientType                  = Arc<RwLock<              SmsClientTraitObject>>;
+pub type TokenStoreType                 = Arc<RwLock<             TokenStoreTraitObject>>;
+pub type TotpStoreType                  = Arc<RwLock<              TotpStoreTraitObject>>;
+pub type TwoFactorCodeStoreType         = Arc<RwLock<     TwoFactorCodeStoreTraitObject>>;
+pub type UserStoreType                  = Arc<RwLock<              UserStoreTraitObject>>;
+pub type WebAuthnChallengeStoreType     = Arc<RwLock< WebAuthnChallengeStoreTraitObject>>;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub user_store:        UserStoreType,
-    pub banned_tokens:     TokenStoreType,
-    pub two_fa_code_store: TwoFactorCodeStoreType,
-    pub email_client:      EmailClientType,
+    pub user_store:          UserStoreType,
+    pub banned_tokens:       TokenStoreType,
+    pub refresh_tokens:      RefreshTokenStoreType,
+    pub sessions:            SessionStoreType,
+    pub two_fa_code_store:   TwoFactorCodeStoreType,
+    pub totp_store:          TotpStoreType,
+    pub recovery_codes:      RecoveryCodeStoreType,
+    pub passkeys:            PasskeyStoreType,
+    pub webauthn_challenges: WebAuthnChallengeStoreType,
+    pub phones:              PhoneStoreType,
+    pub password_resets:     PasswordResetTokenStoreType,
+    pub email_changes:       EmailChangeStoreType,
+    pub email_client:        EmailClientType,
+    pub sms_client:          SmsClientType,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]          // One per store and client, all of them required
     pub fn new(
-        user_store:        UserStoreType,
-        banned_tokens:     TokenStoreType,
-        two_fa_code_store: TwoFactorCodeStoreType,
-        email_client:      EmailClientType,
+        user_store:          UserStoreType,
+        banned_tokens:       TokenStoreType,
+        refresh_tokens:      RefreshTokenStoreType,
+        sessions:            SessionStoreType,
+        two_fa_code_store:   TwoFactorCodeStoreType,
+        totp_store:          TotpStoreType,
+        recovery_codes:      RecoveryCodeStoreType,
+        passkeys:            PasskeyStoreType,
+        webauthn_challenges: WebAuthnChallengeStoreType,
+        phones:              PhoneStoreType,
+        password_resets:     PasswordResetTokenStoreType,
+        email_changes:       EmailChangeStoreType,
+        email_client:        EmailClientType,
+        sms_client:          SmsClientType,
         ) -> Self {
-        AppState{user_store, banned_tokens, two_fa_code_store, email_client}
+        AppState{user_store, banned_tokens, refresh_tokens, sessions, two_fa_code_store, totp_store, recovery_codes, passkeys, webauthn_challenges, phones, password_resets, email_changes, email_client, sms_client}
     }
 }
@@ -4,6 +4,8 @@ pub mod email;
 pub mod email_client;
 pub mod error;
 pub mod password;
+pub mod phone_number;
+pub mod sms_client;
 pub mod user;
 
 
@@ -12,9 +14,13 @@ pub use email::*;
 pub use email_client::*;
 pub use error::*;
 pub use password::*;
+pub use phone_number::*;
+pub use sms_client::*;
 pub use user::*;
 
 #[cfg(test)]
 mod email_tests;
 #[cfg(test)]
 mod password_tests;
+#[cfg(test)]
+mod phone_number_tests;
@@ -1,11 +1,15 @@
 use super::email::Email;
 use super::password::Password;
+use super::phone_number::PhoneNumber;
 use super::user::User;
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Context, Report, Result};
 use secrecy::{Secret};
 use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
 use thiserror::Error;
 use std::fmt;
+use webauthn_rs::prelude::{Passkey, Uuid};
 
 #[derive(Debug, Error)]
 pub enum UserStoreError
@@ -18,6 +22,8 @@ pub enum UserStoreError
     UserAlreadyExists,
     #[error("User not found")]
     UserNotFound,
+    #[error("Tombstone not found")]
+    TombstoneNotFound,
 }
 
 impl PartialEq for UserStoreError {
@@ -27,6 +33,7 @@ impl PartialEq for UserStoreError {
             (  Self::UserAlreadyExists,  Self::UserAlreadyExists )
             | (Self::UserNotFound,       Self::UserNotFound      )
             | (Self::InvalidCredentials, Self::InvalidCredentials)
+            | (Self::TombstoneNotFound,  Self::TombstoneNotFound )
             | (Self::UnexpectedError(_), Self::UnexpectedError(_))
             )
     }
@@ -35,7 +42,7 @@ impl PartialEq for UserStoreError {
 #[derive(Debug, Error)]
 pub enum TokenStoreError 
 {
-    #[error("Token is blank")]
+    #[error("Token id is blank")]
     BlankToken,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
@@ -51,10 +58,215 @@ impl PartialEq for TokenStoreError {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError
+{
+    #[error("Refresh token family has been revoked")]
+    FamilyRevoked,
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token has already been used")]
+    TokenReused,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (  Self::FamilyRevoked,      Self::FamilyRevoked     )
+            | (Self::TokenNotFound,      Self::TokenNotFound     )
+            | (Self::TokenReused,        Self::TokenReused       )
+            | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError
+{
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (  Self::SessionNotFound,    Self::SessionNotFound   )
+            | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TotpStoreError
+{
+    #[error("Authenticator is already enrolled")]
+    AlreadyEnrolled,
+    #[error("Code has already been used")]
+    CodeReused,
+    #[error("Authenticator is not enrolled")]
+    NotEnrolled,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for TotpStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (  Self::AlreadyEnrolled,    Self::AlreadyEnrolled   )
+            | (Self::CodeReused,         Self::CodeReused        )
+            | (Self::NotEnrolled,        Self::NotEnrolled       )
+            | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PasskeyStoreError
+{
+    #[error("Passkey is already registered")]
+    CredentialAlreadyRegistered,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasskeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (  Self::CredentialAlreadyRegistered, Self::CredentialAlreadyRegistered)
+            | (Self::UnexpectedError(_),          Self::UnexpectedError(_)         )
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WebAuthnChallengeStoreError
+{
+    #[error("Challenge not found")]
+    ChallengeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for WebAuthnChallengeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (  Self::ChallengeNotFound,  Self::ChallengeNotFound )
+            | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum EmailChangeStoreError
+{
+    #[error("Email change not found")]
+    ChangeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailChangeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (  Self::ChangeNotFound,     Self::ChangeNotFound    )
+            | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PasswordResetTokenStoreError
+{
+    #[error("Reset token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasswordResetTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (  Self::TokenNotFound,      Self::TokenNotFound     )
+            | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeStoreError
+{
+    #[error("Recovery code has already been used")]
+    CodeAlreadyUsed,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (  Self::CodeAlreadyUsed,    Self::CodeAlreadyUsed   )
+            | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PhoneStoreError
+{
+    #[error("No verification code is pending")]
+    CodeNotFound,
+    #[error("Verification code is incorrect")]
+    IncorrectCode,
+    #[error("Phone number is not registered")]
+    NotRegistered,
+    #[error("Phone number is not verified")]
+    NotVerified,
+    #[error("Too many wrong verification codes")]
+    TooManyAttempts,
+    #[error("A code was sent too recently")]
+    ResendTooSoon,
+    #[error("Too many codes sent")]
+    ResendLimitReached,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PhoneStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (  Self::CodeNotFound,       Self::CodeNotFound      )
+            | (Self::IncorrectCode,      Self::IncorrectCode     )
+            | (Self::NotRegistered,      Self::NotRegistered     )
+            | (Self::NotVerified,        Self::NotVerified       )
+            | (Self::TooManyAttempts,    Self::TooManyAttempts   )
+            | (Self::ResendTooSoon,      Self::ResendTooSoon     )
+            | (Self::ResendLimitReached, Self::ResendLimitReached)
+            | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum TwoFACodeStoreError {
     #[error("Invalid code")]
     LoginAttemptIdNotFound,
+    #[error("Code was resent too recently")]
+    ResendTooSoon,
+    #[error("Code was resent too many times")]
+    ResendLimitReached,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -64,30 +276,284 @@ impl PartialEq for TwoFACodeStoreError {
         matches!(
             (self, other),
             (  Self::LoginAttemptIdNotFound, Self::LoginAttemptIdNotFound)
+            | (Self::ResendTooSoon,          Self::ResendTooSoon         )
+            | (Self::ResendLimitReached,     Self::ResendLimitReached    )
             | (Self::UnexpectedError(_),     Self::UnexpectedError(_)    )
         )
     }
 }
 
+// What is left of a deleted account: a random id, unrelated to the email, and when the account
+// was deleted. It shows an account was erased without telling whose it was.
+//
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountTombstone {
+    pub id:         Uuid,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl AccountTombstone {
+    pub fn new() -> Self {
+        Self {id: Uuid::new_v4(), deleted_at: Utc::now()}
+    }
+}
+
+impl Default for AccountTombstone {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Deleting a user leaves a tombstone in its place, in the same step, so no account
+// disappears without one.
+//
 #[async_trait::async_trait]
 pub trait UserStore 
 {
     async fn add_user(&mut self, user: User)                          -> Result<(),   UserStoreError>;
     async fn get_user(&self, email: &Email)                           -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password) -> Result<(),   UserStoreError>;
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError>;
+    async fn set_email_verified(&mut self, email: &Email, verified: bool)   -> Result<(), UserStoreError>;
+    async fn set_password(&mut self, email: &Email, password: Password)     -> Result<(), UserStoreError>;
+    async fn change_email(&mut self, email: &Email, new_email: &Email)      -> Result<(), UserStoreError>;
+    async fn delete_user(&mut self, email: &Email)                          -> Result<AccountTombstone, UserStoreError>;
+    async fn get_tombstone(&self, id: &Uuid)                                -> Result<AccountTombstone, UserStoreError>;
 }
 
+// Revoked tokens, identified by their `jti` claim, or revoked sessions, identified by their `sid`.
+// An entry only has to outlive the tokens it revokes, so each one is forgotten once the given
+// `exp` (seconds since the epoch) passes.
+//
+// A subject can also have a "not valid before" epoch, in milliseconds since the Unix epoch.
+// Every token issued to that subject at or before the epoch is revoked, wherever it is held.
+//
 #[async_trait::async_trait]
 pub trait TokenStore
 {
-    async fn add_token(&mut self, token: &Secret<String>)    -> Result<(),   TokenStoreError>;
-    async fn clear(&mut self)                                -> Result<(),   TokenStoreError>;
-    async fn count(&self)                                    -> Result<u64,  TokenStoreError>;
-    async fn delete_token(&mut self, token: &Secret<String>) -> Result<(),   TokenStoreError>;
-    async fn contains_token(&self, token: &Secret<String>)   -> bool;
+    async fn add_token(&mut self, jti: &str, exp: usize)            -> Result<(),            TokenStoreError>;
+    async fn clear(&mut self)                                       -> Result<(),            TokenStoreError>;
+    async fn count(&self)                                           -> Result<u64,           TokenStoreError>;
+    async fn delete_token(&mut self, jti: &str)                     -> Result<(),            TokenStoreError>;
+    async fn contains_token(&self, jti: &str)                       -> Result<bool,          TokenStoreError>;
+    async fn set_not_before(&mut self, subject: &str, epoch: usize) -> Result<(),            TokenStoreError>;
+    async fn get_not_before(&self, subject: &str)                   -> Result<Option<usize>, TokenStoreError>;
+}
+
+// Every refresh token belongs to a family that starts at login. Rotating a token
+// keeps the family; presenting an already rotated token revokes the whole family.
+// Whether the login asked to be remembered is passed along, so rotated auth tokens keep their lifetime.
+//
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefreshTokenRecord {
+    pub email:       Email,
+    pub family_id:   String,
+    pub issued_at:   usize,
+    pub remember_me: bool,
+}
+
+impl RefreshTokenRecord {
+    pub fn new(email: Email, family_id: String, remember_me: bool) -> Self {
+        let issued_at = chrono::Utc::now().timestamp_millis() as usize;
+        Self {email, family_id, issued_at, remember_me}
+    }
+}
+
+#[async_trait::async_trait]
+pub trait RefreshTokenStore
+{
+    async fn add_token(&mut self, token: &Secret<String>, record: RefreshTokenRecord) -> Result<(),                 RefreshTokenStoreError>;
+    async fn consume_token(&mut self, token: &Secret<String>)                         -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    async fn revoke_family(&mut self, family_id: &str)                                -> Result<(),                 RefreshTokenStoreError>;
+}
+
+// A session starts at login and lasts as long as its refresh token family.
+// The session id is the family id, and every JWT auth token of the session carries it as `sid`.
+//
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id:         String,
+    pub email:      Email,
+    pub user_agent: Option<String>,
+    pub ip:         Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen:  DateTime<Utc>,
+}
+
+impl Session {
+    pub fn new(id: String, email: Email, user_agent: Option<String>, ip: Option<String>) -> Self {
+        let created_at = Utc::now();
+        let last_seen  = created_at;
+        Self {id, email, user_agent, ip, created_at, last_seen}
+    }
+}
+
+#[async_trait::async_trait]
+pub trait SessionStore
+{
+    async fn add_session(&mut self, session: Session)                -> Result<(),           SessionStoreError>;
+    async fn get_sessions(&self, email: &Email)                      -> Result<Vec<Session>, SessionStoreError>;
+    async fn touch_session(&mut self, email: &Email, id: &str)       -> Result<(),           SessionStoreError>;
+    async fn remove_session(&mut self, email: &Email, id: &str)      -> Result<(),           SessionStoreError>;
+    async fn remove_sessions(&mut self, email: &Email)               -> Result<(),           SessionStoreError>;
+}
+
+// An authenticator app enrollment. It only counts as a second factor once the user
+// has confirmed it with a first code.
+//
+pub struct TotpEnrollment {
+    pub secret:    Secret<Vec<u8>>,
+    pub confirmed: bool,
+}
+
+// Every accepted code is recorded by its time step, so a code can only be used once.
+// Enrolling again replaces an unconfirmed enrollment, but never a confirmed one.
+//
+#[async_trait::async_trait]
+pub trait TotpStore
+{
+    async fn start_enrollment(&mut self, email: &Email, secret: Secret<Vec<u8>>) -> Result<(),             TotpStoreError>;
+    async fn get_enrollment(&self, email: &Email)                                -> Result<TotpEnrollment, TotpStoreError>;
+    async fn confirm_enrollment(&mut self, email: &Email, step: u64)             -> Result<(),             TotpStoreError>;
+    async fn use_step(&mut self, email: &Email, step: u64)                       -> Result<(),             TotpStoreError>;
+    async fn remove_enrollment(&mut self, email: &Email)                         -> Result<(),             TotpStoreError>;
+}
+
+// A recovery code, only ever stored as its Argon2 hash.
+//
+#[derive(Clone, Debug)]
+pub struct RecoveryCodeRecord {
+    pub id:   i64,
+    pub hash: String,
+}
+
+// A user has one set of recovery codes at a time. Replacing the set drops every old code,
+// used or not, and a used code is never handed out by `get_unused_codes` again.
+//
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore
+{
+    async fn replace_codes(&mut self, email: &Email, hashes: Vec<String>) -> Result<(),                      RecoveryCodeStoreError>;
+    async fn get_unused_codes(&self, email: &Email)                       -> Result<Vec<RecoveryCodeRecord>, RecoveryCodeStoreError>;
+    async fn use_code(&mut self, email: &Email, id: i64)                  -> Result<(),                      RecoveryCodeStoreError>;
+}
+
+// Where the codes of emailed 2FA go: to the user's email address, or by SMS to their phone.
+//
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl TwoFAChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TwoFAChannel::Email => "email",
+            TwoFAChannel::Sms   => "sms",
+        }
+    }
+
+    pub fn parse(channel: &str) -> Result<Self> {
+        match channel {
+            "email" => Ok(TwoFAChannel::Email),
+            "sms"   => Ok(TwoFAChannel::Sms),
+            _       => Err(eyre!("Unknown 2FA channel: {}", channel)),
+        }
+    }
+}
+
+// A user's phone number, and the channel their 2FA codes are sent over.
+//
+pub struct PhoneEnrollment {
+    pub phone_number: PhoneNumber,
+    pub verified:     bool,
+    pub channel:      TwoFAChannel,
+}
+
+// A user has at most one phone number. It is verified with a code sent to it, which
+// expires like a 2FA code and is dropped after `max_attempts` wrong answers. Registering
+// a number again replaces the old one, and codes go back to email until it is verified.
+// Codes only go by SMS to a verified number.
+//
+// While a code is alive, registering again is limited like resending a 2FA code: a new code
+// once per `cooldown_seconds`, at most `max_resends` times, and the wrong answers given so far count on.
+//
+#[async_trait::async_trait]
+pub trait PhoneStore
+{
+    async fn start_verification(
+        &mut self,
+        email:            &Email,
+        phone_number:     PhoneNumber,
+        code_hash:        TwoFACodeHash,
+        cooldown_seconds: i64,
+        max_resends:      u32,
+        max_attempts:     u32,
+    )                                                                                                          -> Result<(),              PhoneStoreError>;
+    async fn verify_number(&mut self, email: &Email, code_hash: &TwoFACodeHash, max_attempts: u32)             -> Result<(),              PhoneStoreError>;
+    async fn get_phone(&self, email: &Email)                                                                   -> Result<PhoneEnrollment, PhoneStoreError>;
+    async fn set_channel(&mut self, email: &Email, channel: TwoFAChannel)                                      -> Result<(),              PhoneStoreError>;
+}
+
+// The passkeys of each user. A credential belongs to exactly one user, and all passkeys
+// of a user share the same WebAuthn user handle. Updating a passkey stores its new
+// signature counter after an authentication.
+//
+#[async_trait::async_trait]
+pub trait PasskeyStore
+{
+    async fn add_passkey(&mut self, email: &Email, user_handle: Uuid, passkey: &Passkey) -> Result<(),           PasskeyStoreError>;
+    async fn get_passkeys(&self, email: &Email)                                          -> Result<Vec<Passkey>, PasskeyStoreError>;
+    async fn get_user_handle(&self, email: &Email)                                       -> Result<Option<Uuid>, PasskeyStoreError>;
+    async fn update_passkey(&mut self, email: &Email, passkey: &Passkey)                 -> Result<(),           PasskeyStoreError>;
+}
+
+// The server side state of a WebAuthn ceremony between its start and finish, serialized.
+// A challenge can be taken once only, so a signed response cannot be replayed. Challenges
+// nobody took expire on their own.
+//
+#[async_trait::async_trait]
+pub trait WebAuthnChallengeStore
+{
+    async fn add_challenge(&mut self, id: &str, state: String) -> Result<(),     WebAuthnChallengeStoreError>;
+    async fn take_challenge(&mut self, id: &str)               -> Result<String, WebAuthnChallengeStoreError>;
+}
+
+// Tokens emailed to users who forgot their password. A user has at most one at a time:
+// asking again replaces the one sent before. A token can be taken once only, and tokens
+// nobody took expire on their own. Stores key tokens by a digest, never the token itself.
+//
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore
+{
+    async fn add_token(&mut self, token: &Secret<String>, email: Email) -> Result<(),    PasswordResetTokenStoreError>;
+    async fn take_token(&mut self, token: &Secret<String>)              -> Result<Email, PasswordResetTokenStoreError>;
+}
+
+// A user's request to move their account to another email address.
+//
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmailChange {
+    pub email:     Email,
+    pub new_email: Email,
+}
+
+// A user has at most one pending email change; starting another replaces it. It comes with
+// two single use tokens: one confirms the change, the other cancels it. Using either one
+// ends the change, and changes nobody confirmed or cancelled expire. Tokens are kept as digests.
+//
+#[async_trait::async_trait]
+pub trait EmailChangeStore
+{
+    async fn start_change(&mut self, change: EmailChange, confirm_token: &Secret<String>, cancel_token: &Secret<String>) -> Result<(),          EmailChangeStoreError>;
+    async fn confirm_change(&mut self, confirm_token: &Secret<String>)                                                  -> Result<EmailChange, EmailChangeStoreError>;
+    async fn cancel_change(&mut self, cancel_token: &Secret<String>)                                                    -> Result<EmailChange, EmailChangeStoreError>;
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct LoginAttemptId(String);
 
 impl LoginAttemptId {
@@ -103,11 +569,11 @@ impl LoginAttemptId {
     }
 
     pub fn is_match(&self, other: &LoginAttemptId) -> bool {
-        self.0 == other.0
+        self.is_match_str(&other.0)
     }
 
     pub fn is_match_str(&self, v: &str) -> bool {
-        self.0 == v
+        self.0.as_bytes().ct_eq(v.as_bytes()).into()
     }
 }
 
@@ -147,10 +613,10 @@ impl TwoFACode {
         Ok(Self(code))
     }
     pub fn is_match(&self, other: &TwoFACode) -> bool {
-        self.0 == other.0
+        self.is_match_str(&other.0)
     }
     pub fn is_match_str(&self, v: &str) -> bool {
-        self.0 == v
+        self.0.as_bytes().ct_eq(v.as_bytes()).into()
     }
 }
 
@@ -164,18 +630,59 @@ impl fmt::Display for TwoFACode {
     }
 }
 
+// What the stores keep instead of a 2FA code: a keyed hash of the code, hex encoded.
+// Without the key, a dumped hash does not give the code away, not even by trying all million codes.
+//
+#[derive(Clone, Debug, PartialEq)]
+pub struct TwoFACodeHash(String);
+
+impl AsRef<String> for TwoFACodeHash {
+    fn as_ref(&self) -> &String { &self.0 }
+}
+
+impl TwoFACodeHash {
+    pub const HEX_LENGTH: usize = 64;
+
+    pub fn parse(hash: String) -> Result<Self> {
+        if hash.len() != Self::HEX_LENGTH                            { return Err(eyre!("Code hash must be exactly {} characters", Self::HEX_LENGTH)); }
+        if !hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) { return Err(eyre!("Code hash must be lowercase hex")); }
+        Ok(Self(hash))
+    }
+    pub fn is_match(&self, other: &TwoFACodeHash) -> bool {
+        self.0.as_bytes().ct_eq(other.0.as_bytes()).into()
+    }
+}
+
 // The interface that concrete 2FA stores should implement.
+// Pending login attempts are keyed by their id, so a user can have several in flight,
+// e.g. one on a phone and one on a laptop. Wrong answers are counted per login attempt.
+// Stores only ever see the hash of a code, never the code itself.
 //
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
     async fn add_code(&mut self,
         email:            Email,
         login_attempt_id: LoginAttemptId,
-        code:             TwoFACode,
+        code_hash:        TwoFACodeHash,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError>;
+    // Drop every pending login attempt of the user, e.g. once one of them completes.
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError>;
+    async fn record_failure(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError>;
+    // Swap in a new code for the same login attempt. Resends must be at least `cooldown_seconds`
+    // apart and there can be at most `max_resends` of them. Wrong answers already counted stay counted.
+    async fn resend_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        code_hash:        TwoFACodeHash,
+        cooldown_seconds: i64,
+        max_resends:      u32,
+    ) -> Result<(), TwoFACodeStoreError>;
 }
@@ -1,4 +1,5 @@
-use axum::http::StatusCode;
+use axum::http::header::WWW_AUTHENTICATE;
+use axum::http::{HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use color_eyre::eyre::Report;
@@ -9,14 +10,36 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum AuthAPIError 
 {
+   #[error("Email address is not verified")]
+   EmailNotVerified,
    #[error("Credentials are incorrect")]
    IncorrectCredentials,
+   #[error("Client authentication failed")]
+   InvalidClient,
    #[error("Credentials are invalid")]
    InvalidCredentials,
    #[error("Token is invalid")]
    InvalidToken,
    #[error("Token is missing")]
    MissingToken,
+   #[error("Passkey is already registered")]
+   PasskeyAlreadyRegistered,
+   #[error("Phone number is not verified")]
+   PhoneNotVerified,
+   #[error("Code was resent too recently")]
+   ResendTooSoon,
+   #[error("Session not found")]
+   SessionNotFound,
+   #[error("Authenticator is already enrolled")]
+   TotpAlreadyEnrolled,
+   #[error("Authenticator is not enrolled")]
+   TotpNotEnrolled,
+   #[error("Too many attempts")]
+   TooManyAttempts,
+   #[error("Two-factor authentication is already enabled")]
+   TwoFAAlreadyEnabled,
+   #[error("Two-factor authentication is not enabled")]
+   TwoFANotEnabled,
    #[error("Unexpected error")]
    UnexpectedError(#[source] Report),
    #[error("User already exists")]
@@ -28,18 +51,35 @@ impl IntoResponse for AuthAPIError
    fn into_response(self) -> Response 
    {
       log_error_chain(&self);
+      let challenge = matches!(self, AuthAPIError::InvalidClient);
       let (status, error_message) = match self {
+         AuthAPIError::EmailNotVerified      => (StatusCode::FORBIDDEN,             "Email address not verified"),
          AuthAPIError::IncorrectCredentials  => (StatusCode::UNAUTHORIZED,          "Authorization failure"),
+         AuthAPIError::InvalidClient         => (StatusCode::UNAUTHORIZED,          "Invalid client"       ),
          AuthAPIError::InvalidCredentials    => (StatusCode::BAD_REQUEST,           "Invalid credentials"  ),
          AuthAPIError::InvalidToken          => (StatusCode::UNAUTHORIZED,          "Invalid token "       ),
          AuthAPIError::MissingToken          => (StatusCode::BAD_REQUEST,           "Missing token"        ),
+         AuthAPIError::PasskeyAlreadyRegistered => (StatusCode::CONFLICT,           "Passkey already registered"),
+         AuthAPIError::PhoneNotVerified      => (StatusCode::BAD_REQUEST,           "Phone number not verified"),
+         AuthAPIError::ResendTooSoon         => (StatusCode::TOO_MANY_REQUESTS,     "Please wait before requesting another code"),
+         AuthAPIError::SessionNotFound       => (StatusCode::NOT_FOUND,             "Session not found"    ),
+         AuthAPIError::TotpAlreadyEnrolled   => (StatusCode::CONFLICT,              "Authenticator already enrolled"),
+         AuthAPIError::TotpNotEnrolled       => (StatusCode::BAD_REQUEST,           "Authenticator not enrolled"    ),
+         AuthAPIError::TooManyAttempts       => (StatusCode::TOO_MANY_REQUESTS,     "Too many attempts"    ),
+         AuthAPIError::TwoFAAlreadyEnabled   => (StatusCode::CONFLICT,              "Two-factor authentication already enabled"),
+         AuthAPIError::TwoFANotEnabled       => (StatusCode::BAD_REQUEST,           "Two-factor authentication not enabled"),
          AuthAPIError::UnexpectedError(_)    => (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error"     ),
          AuthAPIError::UserAlreadyExists     => (StatusCode::CONFLICT,              "User already exists"  ),
       };
       let error = error_message.to_string();
       let error = ErrorResponse{error};
       let body  = Json(error);
-      (status, body).into_response()
+      let mut response = (status, body).into_response();
+      if challenge {                                        // RFC 6749, section 5.2
+         let value = HeaderValue::from_static(r#"Basic realm="auth-service""#);
+         response.headers_mut().insert(WWW_AUTHENTICATE, value);
+      }
+      response
    }
 }
 
@@ -0,0 +1,52 @@
+use secrecy::{ExposeSecret, Secret};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PhoneNumberError {
+    #[error("Phone number format is not valid")]
+    BadFormat,
+    #[error("Phone number value is empty")]
+    EmptyValue,
+    #[error("Phone number is missing its country code")]
+    MissingCountryCode,
+}
+
+// A phone number in E.164 form: a plus sign, the country code, and at most 15 digits in all.
+// Spaces, dashes, dots and parentheses are accepted and dropped, so "+1 (555) 010-9999"
+// is stored as "+15550109999".
+//
+#[derive(Clone, Debug)]
+pub struct PhoneNumber(Secret<String>);
+
+impl PhoneNumber {
+    pub fn parse(number: Secret<String>) -> Result<Self, PhoneNumberError> {
+        let s: String = number.expose_secret()
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+            .collect();
+        if s.is_empty()                                { return Err(PhoneNumberError::EmptyValue);         }
+        let digits = match s.strip_prefix('+') {
+            Some(digits) => digits,
+            None         => return Err(PhoneNumberError::MissingCountryCode),
+        };
+        if !digits.chars().all(|c| c.is_ascii_digit()) { return Err(PhoneNumberError::BadFormat);          }
+        if digits.starts_with('0')                     { return Err(PhoneNumberError::BadFormat);          }
+        if !(8..=15).contains(&digits.len())           { return Err(PhoneNumberError::BadFormat);          }
+        Ok(PhoneNumber(Secret::new(format!("+{}", digits))))
+    }
+
+    pub fn expose_secret(&self) -> &str {self.0.expose_secret()}
+}
+
+impl Eq        for PhoneNumber {}
+impl PartialEq for PhoneNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for PhoneNumber {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
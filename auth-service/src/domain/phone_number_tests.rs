@@ -0,0 +1,49 @@
+use crate::domain::phone_number::{PhoneNumber, PhoneNumberError};
+use secrecy::Secret;
+
+fn parse(input: &str) -> Result<PhoneNumber, PhoneNumberError> {
+   PhoneNumber::parse(Secret::new(input.to_owned()))
+}
+
+#[test]
+pub fn e164_number_is_parsed_successfully()
+{
+   let output  = parse("+15550109999").unwrap();
+   assert_eq!(output.expose_secret(), "+15550109999");
+}
+
+#[test]
+pub fn separators_are_dropped()
+{
+   let output  = parse(" +1 (555) 010-99.99 ").unwrap();
+   assert_eq!(output, parse("+15550109999").unwrap());
+}
+
+#[test]
+pub fn parse_empty_string_fails_as_expected()
+{
+   assert_eq!(parse("").unwrap_err(),      PhoneNumberError::EmptyValue);
+   assert_eq!(parse(" - ").unwrap_err(),   PhoneNumberError::EmptyValue);
+}
+
+#[test]
+pub fn number_without_country_code_is_rejected()
+{
+   assert_eq!(parse("5550109999").unwrap_err(),   PhoneNumberError::MissingCountryCode);
+   assert_eq!(parse("0044201234567").unwrap_err(), PhoneNumberError::MissingCountryCode);
+}
+
+#[test]
+pub fn malformed_numbers_are_rejected()
+{
+   let test_cases = [
+      "+1555010999x",          // Not a digit
+      "+0445550109999",        // Country codes never start with 0
+      "+1555010",              // Too short
+      "+1555010999912345",     // Longer than E.164 allows
+      "++15550109999",
+   ];
+   for input in test_cases {
+      assert_eq!(parse(input).unwrap_err(), PhoneNumberError::BadFormat, "Failed for input: {}", input);
+   }
+}
@@ -3,13 +3,16 @@ use super::password::Password;
 
 #[derive(Debug, Clone)]
 pub struct User {
-      pub email:        Email,
-      pub password:     Password,
-      pub requires_2fa: bool,
+      pub email:          Email,
+      pub password:       Password,
+      pub requires_2fa:   bool,
+      pub email_verified: bool,
 }
 
 impl User {
+   // A new user has yet to verify their email address.
+   //
    pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
-      User {email, password, requires_2fa}
+      User {email, password, requires_2fa, email_verified: false}
    }
 }
@@ -3,8 +3,11 @@ extern crate core;
 use crate::routes::*;
 use crate::utils::constants::{prod, test};
 use app_state::AppState;
+use axum::extract::connect_info::IntoMakeServiceWithConnectInfo;
+use axum::extract::ConnectInfo;
 use axum::http::Method;
-use axum::routing::post;
+use axum::middleware::{from_fn_with_state, AddExtension};
+use axum::routing::{delete, get, post};
 use axum::serve::Serve;
 use axum::Router;
 use redis;
@@ -12,6 +15,7 @@ use redis::RedisResult;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
+use std::net::SocketAddr;
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
@@ -27,8 +31,10 @@ pub mod utils;
 // This struct encapsulates our application-related logic.
 // address is exposed as a public field so we have access to it in tests.
 //
+type ConnectedRouter = AddExtension<Router, ConnectInfo<SocketAddr>>;
+
 pub struct Application {
-    server:      Serve<Router, Router>,
+    server:      Serve<IntoMakeServiceWithConnectInfo<Router, SocketAddr>, ConnectedRouter>,
     pub address: String,
 }
 
@@ -44,7 +50,7 @@ impl Application {
         ];
         
         let cors = CorsLayer::new()
-           .allow_methods([Method::GET, Method::POST])
+           .allow_methods([Method::GET, Method::POST, Method::DELETE])
            .allow_credentials(true)                        // Allow cookies to be included in requests
            .allow_origin(allowed_origins);
 
@@ -57,16 +63,46 @@ impl Application {
             .nest_service("/",        ServeDir::new("assets"))
             .route("/signup",         post(signup))
             .route("/login",          post(login))
+            .route("/introspect",     post(introspect))
             .route("/logout",         post(logout))
+            .route("/logout-all",     post(logout_all))
+            .route("/refresh",        post(refresh))
+            .route("/sessions",       get(list_sessions))
+            .route("/sessions/:id",   delete(delete_session))
+            .route("/account",        delete(delete_account))
+            .route("/verify-email",   get(verify_email))
+            .route("/password/forgot",    post(forgot_password))
+            .route("/password/reset",     post(reset_password))
+            .route("/password/change",    post(change_password))
+            .route("/email/change",         post(change_email))
+            .route("/email/change/confirm", get(confirm_email_change))
+            .route("/email/change/cancel",  get(cancel_email_change))
             .route("/verify-2fa",     post(verify_2fa))
+            .route("/resend-2fa",     post(resend_2fa))
+            .route("/2fa/totp/enroll",  post(enroll_totp))
+            .route("/2fa/totp/confirm", post(confirm_totp))
+            .route("/2fa/recovery-codes", post(regenerate_recovery_codes))
+            .route("/2fa/email/enable",   post(enable_email_2fa))
+            .route("/2fa/email/confirm",  post(confirm_email_2fa))
+            .route("/2fa/email/disable",  post(disable_email_2fa))
+            .route("/2fa/phone",          post(register_phone))
+            .route("/2fa/phone/verify",   post(verify_phone))
+            .route("/2fa/channel",        post(set_2fa_channel))
+            .route("/passkeys/register/start",  post(start_passkey_registration))
+            .route("/passkeys/register/finish", post(finish_passkey_registration))
+            .route("/passkeys/login/start",     post(start_passkey_login))
+            .route("/passkeys/login/finish",    post(finish_passkey_login))
             .route("/verify-token",   post(verify_token))
+            .route("/.well-known/jwks.json", get(jwks))
+            .layer(from_fn_with_state(app_state.clone(), renew_auth_cookie))
             .with_state(app_state)
             .layer(cors)
             .layer(trace);
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address  = listener.local_addr()?.to_string();
-        let server   = axum::serve(listener, router);
+        let service  = router.into_make_service_with_connect_info::<SocketAddr>();  // Client address for sessions
+        let server   = axum::serve(listener, service);
         let rv       = Application {server, address};
         Ok(rv)
     }
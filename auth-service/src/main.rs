@@ -1,19 +1,34 @@
 //use auth_service::services::data_stores::hashmap_user_store::HashmapUserStore;
-use auth_service::utils::constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME};
-use auth_service::{app_state::AppState, create_postgres_pool, create_redis_client, Application};
+use auth_service::utils::constants::{prod, DATABASE_URL, EXPIRED_ROW_PURGE_PERIOD, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME, STORE_BACKEND, STORE_BACKEND_POSTGRES};
+use auth_service::utils::constants::{TWILIO_ACCOUNT_SID, TWILIO_AUTH_TOKEN, TWILIO_SENDER};
+use auth_service::app_state::{AppState, TokenStoreType, TwoFactorCodeStoreType};
+use auth_service::{create_postgres_pool, create_redis_client, Application};
 use sqlx::PgPool;
 use std::sync::Arc;
 use color_eyre;
-use redis::ConnectionLike;
+use redis::aio::ConnectionManager;
 use reqwest::Client;
 use secrecy::Secret;
 use tokio::sync::RwLock;
-use auth_service::domain::Email;
-use auth_service::services::data_stores::hashmap_2fa_code_store::HashmapTwoFACodeStore;
+use auth_service::domain::{Email, PhoneNumber};
+use auth_service::services::data_stores::postgres_2fa_code_store::PostgresTwoFACodeStore;
+use auth_service::services::data_stores::postgres_banned_token_store::PostgresBannedTokenStore;
+use auth_service::services::data_stores::postgres_email_change_store::PostgresEmailChangeStore;
+use auth_service::services::data_stores::postgres_purge::spawn_expired_row_purge;
+use auth_service::services::data_stores::redis_2fa_code_store::RedisTwoFACodeStore;
 use auth_service::services::data_stores::redis_banned_token_store::RedisBannedTokenStore;
+use auth_service::services::data_stores::redis_password_reset_token_store::RedisPasswordResetTokenStore;
+use auth_service::services::data_stores::redis_refresh_token_store::RedisRefreshTokenStore;
+use auth_service::services::data_stores::redis_webauthn_challenge_store::RedisWebAuthnChallengeStore;
+use auth_service::services::data_stores::postgres_session_store::PostgresSessionStore;
+use auth_service::services::data_stores::postgres_passkey_store::PostgresPasskeyStore;
+use auth_service::services::data_stores::postgres_phone_store::PostgresPhoneStore;
+use auth_service::services::data_stores::postgres_recovery_code_store::PostgresRecoveryCodeStore;
+use auth_service::services::data_stores::postgres_totp_store::PostgresTotpStore;
 use auth_service::services::data_stores::postgres_user_store::PostgresUserStore;
 //use auth_service::services::mock_email_client::MockEmailClient;
 use auth_service::services::postmark_email_client::PostmarkEmailClient;
+use auth_service::services::twilio_sms_client::TwilioSmsClient;
 use auth_service::utils::tracing::init_tracing;
 
 #[tokio::main]
@@ -23,13 +38,21 @@ async fn main()
 	configure_tracing();
 	
 	let pg_pool        = configure_postgresql().await;
-	let redis_cx       = configure_redis();
-	let redis_cx       = Arc::new(RwLock::new(redis_cx));
-	let user_store     = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-	let banned_tokens  = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_cx.clone())));
-	let code_store     = Arc::new(RwLock::new(HashmapTwoFACodeStore::new()));
+	let redis_cx       = configure_redis().await;
+	let user_store     = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+	let sessions       = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+	let refresh_tokens = Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_cx.clone())));
+	let totp_store     = Arc::new(RwLock::new(PostgresTotpStore::new(pg_pool.clone())));
+	let recovery_codes = Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+	let passkeys       = Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.clone())));
+	let challenges     = Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(redis_cx.clone())));
+	let phones         = Arc::new(RwLock::new(PostgresPhoneStore::new(pg_pool.clone())));
+	let resets         = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_cx.clone())));
+	let email_changes  = Arc::new(RwLock::new(PostgresEmailChangeStore::new(pg_pool.clone())));
+	let (banned_tokens, code_store) = configure_token_stores(pg_pool, redis_cx);
 	let email_client   = Arc::new(RwLock::new(configure_postmark_email_client()));
-	let app_state      = AppState::new(user_store, banned_tokens, code_store, email_client);
+	let sms_client     = Arc::new(RwLock::new(configure_twilio_sms_client()));
+	let app_state      = AppState::new(user_store, banned_tokens, refresh_tokens, sessions, code_store, totp_store, recovery_codes, passkeys, challenges, phones, resets, email_changes, email_client, sms_client);
 	let e_build        = "Failed to build application";
 	let e_run          = "Failed to run application";
 	let app            = Application::build(app_state, prod::APP_ADDRESS)
@@ -56,19 +79,43 @@ async fn configure_postgresql() -> PgPool
 	pg_pool
 }
 
-fn configure_redis() -> redis::Connection
+// One multiplexed, self reconnecting connection is shared by every Redis backed store.
+//
+async fn configure_redis() -> ConnectionManager
 {
 	let e_client  = "Failed to create Redis client";
 	let e_connect = "Failed to connect to Redis";
 	let host =  REDIS_HOST_NAME.to_owned();
 	let redis = create_redis_client(host)	
 		.expect(e_client)
-		.get_connection()
+		.get_connection_manager()
+		.await
 		.expect(e_connect);
-	println!("Connected to Redis: Open? {}", redis.is_open());
+	println!("Connected to Redis");
 	redis
 }
 
+// Banned tokens and 2FA codes live in Redis, unless STORE_BACKEND selects Postgres.
+// The other short lived stores are Redis only, so the connection is needed either way.
+// Postgres does not expire rows by itself, so it gets a periodic purge.
+//
+fn configure_token_stores(pg_pool: PgPool, redis_cx: ConnectionManager) -> (TokenStoreType, TwoFactorCodeStoreType)
+{
+	match STORE_BACKEND.as_str() {
+		STORE_BACKEND_POSTGRES => {
+			spawn_expired_row_purge(pg_pool.clone(), EXPIRED_ROW_PURGE_PERIOD);
+			let banned_tokens = Arc::new(RwLock::new(PostgresBannedTokenStore::new(pg_pool.clone())));
+			let code_store    = Arc::new(RwLock::new(PostgresTwoFACodeStore::new(pg_pool)));
+			(banned_tokens, code_store)
+		},
+		_ => {
+			let banned_tokens = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_cx.clone())));
+			let code_store    = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_cx)));
+			(banned_tokens, code_store)
+		},
+	}
+}
+
 fn configure_postmark_email_client() -> PostmarkEmailClient 
 {
 	let http_client = Client::builder()
@@ -82,4 +129,19 @@ fn configure_postmark_email_client() -> PostmarkEmailClient
 	let sender     = Email::parse(email).unwrap();
 	let auth_token = POSTMARK_AUTH_TOKEN.to_owned();
 	PostmarkEmailClient::new(base_url, sender, auth_token, http_client)
-}
\ No newline at end of file
+}
+
+fn configure_twilio_sms_client() -> TwilioSmsClient
+{
+	let http_client = Client::builder()
+		.timeout(prod::sms_client::TIMEOUT)
+		.build()
+		.expect("Failed to build HTTP client");
+
+	let base_url    = prod::sms_client::BASE_URL.to_owned();
+	let account_sid = TWILIO_ACCOUNT_SID.to_owned();
+	let number      = Secret::new(TWILIO_SENDER.to_owned());
+	let sender      = PhoneNumber::parse(number).expect("TWILIO_SENDER must be a phone number in E.164 form");
+	let auth_token  = TWILIO_AUTH_TOKEN.to_owned();
+	TwilioSmsClient::new(base_url, account_sid, sender, auth_token, http_client)
+}
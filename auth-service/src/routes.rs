@@ -1,14 +1,49 @@
 
 pub mod login;                   // Define routes sub-modules
+pub mod account;
+pub mod change_email;
+pub mod email_2fa;
 pub mod signup;
 pub mod verify_2fa;
+pub mod verify_email;
 pub mod verify_token;
+pub mod jwks;
+pub mod introspect;
 pub mod logout;
-mod handler_helpers;
+pub mod logout_all;
+pub mod passkeys;
+pub mod password;
+pub mod phone;
+pub mod recovery_codes;
+pub mod refresh;
+pub mod resend_2fa;
+pub mod sessions;
+pub mod sliding_expiry;
+pub mod totp;
+mod extractors;
+mod notifications;
+mod second_factor;
+mod session_helpers;
 
 pub use login::*;
 // Re-export items from sub-modules
+pub use account::*;
+pub use change_email::*;
+pub use email_2fa::*;
+pub use introspect::*;
+pub use jwks::*;
 pub use logout::*;
+pub use logout_all::*;
+pub use passkeys::*;
+pub use password::*;
+pub use phone::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use resend_2fa::*;
+pub use sessions::*;
+pub use sliding_expiry::*;
+pub use totp::*;
 pub use signup::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
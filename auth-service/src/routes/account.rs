@@ -0,0 +1,58 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, SessionStoreError};
+use crate::routes::extractors::AuthenticatedUser;
+use crate::routes::notifications::send_notification;
+use crate::routes::session_helpers::{check_password, end_session, remove_session_cookies, revoke_all_sessions, subject_email};
+use crate::utils::audit::{self, AuditEvent};
+use axum::extract::State;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct DeleteAccountResponse {
+    pub message: String,
+}
+
+// Delete the authenticated user's account, who has to enter the password again.
+// Every session and pending 2FA login is revoked before the user is removed; the rows
+// that belong to the user go with it. Only a tombstone with a random id is left, in the
+// user store and in the audit log.
+//
+#[tracing::instrument(name = "delete account", skip_all)]
+pub async fn delete_account(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    jar:                         CookieJar,
+    Json(request):               Json<DeleteAccountRequest>,
+    ) -> Result<(CookieJar, Json<DeleteAccountResponse>), AuthAPIError> {
+    let email = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    check_password(&email, request.password, &state).await?;
+
+    // Ban the token making the request, and end its session along with its refresh tokens
+    state.banned_tokens.write().await
+        .add_token(&claims.jti, claims.exp).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    match end_session(&email, &claims.sid, &state).await {
+        Ok(()) | Err(SessionStoreError::SessionNotFound) => {},
+        Err(e)                                           => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    revoke_all_sessions(&email, &state).await?;
+    let tombstone = state.user_store.write().await
+        .delete_user(&email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let content = "Your account and the personal data it held were deleted.\n";
+    send_notification(&email, "Your account was deleted", content, &state).await?;
+    audit::emit(AuditEvent::AccountDeleted {tombstone: &tombstone});
+    info!("Account deleted.");
+    let message = "Account has been deleted".to_owned();
+    Ok((remove_session_cookies(jar), Json(DeleteAccountResponse {message})))
+}
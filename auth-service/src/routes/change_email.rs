@@ -0,0 +1,140 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, EmailChange, EmailChangeStoreError, UserStoreError};
+use crate::routes::extractors::AuthenticatedUser;
+use crate::routes::notifications::send_notification;
+use crate::routes::session_helpers::{check_password, revoke_all_sessions, subject_email};
+use crate::utils::audit::{self, AuditEvent};
+use crate::utils::auth::generate_email_change_token;
+use crate::utils::constants::AUTH_SERVICE_URL;
+use axum::extract::{Query, State};
+use axum::Json;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmailRequest {
+    #[serde(rename = "newEmail")]
+    pub new_email: String,
+    pub password:  Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailChangeQuery {
+    pub token: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
+
+// Start moving the authenticated user's account to another email address. They enter their
+// password again. The new address gets a link that confirms the change, the old one a link
+// that cancels it. Nothing changes until the new address confirms.
+//
+#[tracing::instrument(name = "change email", skip_all)]
+pub async fn change_email(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(request):               Json<ChangeEmailRequest>,
+    ) -> Result<Json<ChangeEmailResponse>, AuthAPIError> {
+    let email     = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    check_password(&email, request.password, &state).await?;
+    let new_email = Email::parse(Secret::new(request.new_email)).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    if new_email == email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+    match state.user_store.read().await.get_user(&new_email).await {
+        Ok(_)                             => return Err(AuthAPIError::UserAlreadyExists),
+        Err(UserStoreError::UserNotFound) => {},
+        Err(e)                            => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let confirm   = generate_email_change_token();
+    let cancel    = generate_email_change_token();
+    let change    = EmailChange {email: email.clone(), new_email: new_email.clone()};
+    state.email_changes.write().await
+        .start_change(change, &confirm, &cancel).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let link      = format!("{}/email/change/confirm?token={}", *AUTH_SERVICE_URL, confirm.expose_secret());
+    let content   = format!("Open this link to move your account to this email address: {}\n", link);
+    send_notification(&new_email, "Confirm your new email address", &content, &state).await?;
+    let link      = format!("{}/email/change/cancel?token={}", *AUTH_SERVICE_URL, cancel.expose_secret());
+    let content   = format!(
+        "Someone asked to move your account to {}. If it was not you, open this link to cancel: {}\n",
+        new_email.expose_secret(), link);
+    send_notification(&email, "Your email address is about to change", &content, &state).await?;
+
+    audit::emit(AuditEvent::EmailChangeRequested {email: &email, new_email: &new_email});
+    let message   = "A confirmation link has been sent to the new address".to_owned();
+    Ok(Json(ChangeEmailResponse {message}))
+}
+
+// The link sent to the new address lands here. The account moves to the new address, which
+// counts as verified. Every session is revoked, with the tokens issued to the old address,
+// and so are login attempts still waiting for their 2FA code. The user logs in again.
+//
+#[tracing::instrument(name = "confirm email change", skip_all)]
+pub async fn confirm_email_change(
+    State(state):  State<AppState>,
+    Query(query):  Query<EmailChangeQuery>,
+    ) -> Result<Json<ChangeEmailResponse>, AuthAPIError> {
+    let change = match state.email_changes.write().await.confirm_change(&query.token).await {
+        Ok(change)                                => change,
+        Err(EmailChangeStoreError::ChangeNotFound) => {
+            warn!("Email change confirmation token is unknown, used or expired.");
+            return Err(AuthAPIError::InvalidToken);
+        },
+        Err(e)                                    => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    let EmailChange {email, new_email} = change;
+
+    let mut user_store = state.user_store.write().await;
+    match user_store.change_email(&email, &new_email).await {
+        Ok(())                                 => {},
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(UserStoreError::UserNotFound)      => return Err(AuthAPIError::InvalidToken),
+        Err(e)                                 => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    user_store
+        .set_email_verified(&new_email, true).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(user_store);
+
+    // Sessions stored next to the user followed it to the new address
+    revoke_all_sessions(&email, &state).await?;
+    state.sessions.write().await
+        .remove_sessions(&new_email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let content = format!("The email address of your account was changed to {}.\n", new_email.expose_secret());
+    send_notification(&email, "Your email address was changed", &content, &state).await?;
+    audit::emit(AuditEvent::EmailChanged {email: &email, new_email: &new_email});
+    info!("Email address changed.");
+    let message = "Email address changed".to_owned();
+    Ok(Json(ChangeEmailResponse {message}))
+}
+
+// The link sent to the old address lands here, and drops the pending change.
+//
+#[tracing::instrument(name = "cancel email change", skip_all)]
+pub async fn cancel_email_change(
+    State(state):  State<AppState>,
+    Query(query):  Query<EmailChangeQuery>,
+    ) -> Result<Json<ChangeEmailResponse>, AuthAPIError> {
+    let change = match state.email_changes.write().await.cancel_change(&query.token).await {
+        Ok(change)                                => change,
+        Err(EmailChangeStoreError::ChangeNotFound) => {
+            warn!("Email change cancellation token is unknown, used or expired.");
+            return Err(AuthAPIError::InvalidToken);
+        },
+        Err(e)                                    => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    audit::emit(AuditEvent::EmailChangeCancelled {email: &change.email});
+    info!("Email change cancelled.");
+    let message = "Email change cancelled".to_owned();
+    Ok(Json(ChangeEmailResponse {message}))
+}
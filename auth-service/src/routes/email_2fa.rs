@@ -0,0 +1,128 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode};
+use crate::routes::extractors::AuthenticatedUser;
+use crate::routes::notifications::send_notification;
+use crate::routes::second_factor::record_failure;
+use crate::routes::session_helpers::{check_password, subject_email};
+use crate::utils::audit::{self, AuditEvent};
+use crate::utils::constants::TWO_FA_CODE_HASHER;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordConfirmation {
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Email2FAEnableResponse {
+    #[serde(rename = "attemptId")]
+    pub attempt_id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Email2FAConfirmRequest {
+    #[serde(rename = "attemptId")]
+    pub attempt_id: String,
+    pub code:       String,
+}
+
+// Start turning on email 2FA for the authenticated user, who has to enter their password again.
+// A code is emailed to them, which they confirm with the attempt id in the response. The code
+// waits in the 2FA code store under an attempt id of its own, like a login attempt would, so
+// whatever drops the user's pending login attempts drops it too: a login finished with a second
+// factor, a password change, or logout-all. The user then asks for a new code.
+//
+#[tracing::instrument(name = "enable email 2fa", skip_all)]
+pub async fn enable_email_2fa(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(request):               Json<PasswordConfirmation>,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email      = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    check_password(&email, request.password, &state).await?;
+    let user       = state.user_store.read().await
+        .get_user(&email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if user.requires_2fa {
+        return Err(AuthAPIError::TwoFAAlreadyEnabled);
+    }
+
+    let attempt_id = LoginAttemptId::new();
+    let code       = TwoFACode::new();
+    let content    = format!("Code [{}] turns on two-factor authentication for your account.\n", code);
+    let code_hash  = TWO_FA_CODE_HASHER.hash(&code, attempt_id.as_ref());
+    state.two_fa_code_store.write().await
+        .add_code(email.clone(), attempt_id.clone(), code_hash).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    send_notification(&email, "Confirm two-factor authentication", &content, &state).await?;
+    info!("Email 2FA confirmation code sent.");
+    Ok(Json(Email2FAEnableResponse {attempt_id: attempt_id.to_string()}))
+}
+
+// Turn on email 2FA with the emailed code. Wrong codes count against the attempt
+// like wrong 2FA answers at login, and use up the attempt the same way.
+//
+#[tracing::instrument(name = "confirm email 2fa", skip_all)]
+pub async fn confirm_email_2fa(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(request):               Json<Email2FAConfirmRequest>,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email      = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    let attempt_id = LoginAttemptId::parse(request.attempt_id).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let code       = TwoFACode::parse(request.code)           .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let (owner, expected) = state.two_fa_code_store.read().await
+        .get_code(&attempt_id).await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    if owner != email {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+    if !TWO_FA_CODE_HASHER.hash(&code, attempt_id.as_ref()).is_match(&expected) {
+        return Err(record_failure(&email, &attempt_id, &state).await);
+    }
+
+    state.two_fa_code_store.write().await
+        .remove_code(&attempt_id).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    set_email_2fa(&email, true, &state).await?;
+    Ok(StatusCode::OK)
+}
+
+// Turn off email 2FA for the authenticated user, who has to enter their password again.
+// An authenticator app, if any, stays enrolled and keeps asking for its codes.
+//
+#[tracing::instrument(name = "disable email 2fa", skip_all)]
+pub async fn disable_email_2fa(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(request):               Json<PasswordConfirmation>,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    check_password(&email, request.password, &state).await?;
+    let user  = state.user_store.read().await
+        .get_user(&email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if !user.requires_2fa {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+    set_email_2fa(&email, false, &state).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn set_email_2fa(email: &Email, enabled: bool, state: &AppState) -> Result<(), AuthAPIError> {
+    state.user_store.write().await
+        .set_requires_2fa(email, enabled).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    audit::emit(AuditEvent::TwoFASettingChanged {email, enabled});
+    let (subject, content) = match enabled {
+        true  => ("Two-factor authentication turned on",  "Logging in to your account now requires a code as well as your password.\n"),
+        false => ("Two-factor authentication turned off", "Logging in to your account no longer requires a code. If you did not turn it off, change your password.\n"),
+    };
+    send_notification(email, subject, content, state).await
+}
@@ -0,0 +1,99 @@
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header::{AUTHORIZATION, USER_AGENT};
+use axum::http::request::Parts;
+use axum_extra::extract::CookieJar;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use subtle::ConstantTimeEq;
+use tracing::warn;
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+use crate::utils::auth::{validate_token, Claims};
+use crate::utils::constants::{INTROSPECTION_CLIENTS, JWT_COOKIE_NAME};
+
+// The device a request comes from. Behind a proxy the client address is taken
+// from X-Forwarded-For, otherwise from the connection itself.
+//
+#[derive(Clone, Debug, Default)]
+pub struct ClientInfo {
+	pub user_agent: Option<String>,
+	pub ip:         Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+	S: Send + Sync,
+{
+	type Rejection = Infallible;
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+		let header     = |name| parts.headers.get(name).and_then(|v: &axum::http::HeaderValue| v.to_str().ok());
+		let user_agent = header(USER_AGENT.as_str()).map(str::to_owned);
+		let forwarded  = header("x-forwarded-for").and_then(|v| v.split(',').next()).map(|ip| ip.trim().to_owned());
+		let connected  = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip().to_string());
+		let ip         = forwarded.or(connected);
+		Ok(ClientInfo {user_agent, ip})
+	}
+}
+
+// The claims of a valid JWT auth cookie. Handlers that take this extractor are only
+// reached by authenticated requests.
+//
+pub struct AuthenticatedUser(pub Claims);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+	type Rejection = AuthAPIError;
+
+	async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+		let jar    = CookieJar::from_headers(&parts.headers);
+		let cookie = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
+		let store  = state.banned_tokens.clone();
+		match validate_token(cookie.value(), store).await {
+			Ok(claims) => Ok(AuthenticatedUser(claims)),
+			Err(e)     => {
+				warn!("Token is invalid: {:?}", e);
+				Err(AuthAPIError::InvalidToken)
+			},
+		}
+	}
+}
+
+// A client that authenticated with HTTP Basic credentials (RFC 6749, section 2.3.1)
+// matching one of the configured introspection clients.
+//
+pub struct AuthenticatedClient(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthenticatedClient
+where
+	S: Send + Sync,
+{
+	type Rejection = AuthAPIError;
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+		let header      = parts.headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+		let encoded     = header.and_then(|v| v.strip_prefix("Basic ")).ok_or(AuthAPIError::InvalidClient)?;
+		let decoded     = STANDARD.decode(encoded.trim()).map_err(|_| AuthAPIError::InvalidClient)?;
+		let decoded     = String::from_utf8(decoded).map_err(|_| AuthAPIError::InvalidClient)?;
+		let (id, given) = decoded.split_once(':').ok_or(AuthAPIError::InvalidClient)?;
+		let expected    = INTROSPECTION_CLIENTS.get(id).ok_or(AuthAPIError::InvalidClient)?;
+
+		// Compare digests in constant time, so the comparison takes as long for any secret of any length
+		let given       = Sha256::digest(given.as_bytes());
+		let expected    = Sha256::digest(expected.expose_secret().as_bytes());
+		match bool::from(given.ct_eq(&expected)) {
+			true  => Ok(AuthenticatedClient(id.to_owned())),
+			false => {
+				warn!(client = id, "Client presented a wrong secret");
+				Err(AuthAPIError::InvalidClient)
+			},
+		}
+	}
+}
@@ -0,0 +1,88 @@
+use crate::app_state::AppState;
+use crate::routes::extractors::AuthenticatedClient;
+use crate::utils::auth::{validate_token, Claims};
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::{Form, Json};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+// RFC 7662, section 2.1. Only access tokens can be introspected, so the hint is ignored.
+//
+#[derive(Deserialize, Debug)]
+pub struct IntrospectRequest {
+    pub token:           String,
+    pub token_type_hint: Option<String>,
+}
+
+// RFC 7662, section 2.2. An inactive token reveals nothing but `active: false`.
+//
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct IntrospectResponse {
+    pub active:     bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope:      Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles:      Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub:        Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp:        Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat:        Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf:        Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss:        Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud:        Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti:        Option<String>,
+}
+
+impl IntrospectResponse {
+    pub fn inactive() -> Self {
+        Self::default()
+    }
+}
+
+impl From<Claims> for IntrospectResponse {
+    fn from(claims: Claims) -> Self {
+        Self {
+            active:     true,
+            scope:      Some(claims.scope),
+            roles:      Some(claims.roles),
+            token_type: Some("Bearer".to_owned()),
+            sub:        Some(claims.sub),
+            exp:        Some(claims.exp),
+            iat:        Some(claims.iat),
+            nbf:        Some(claims.nbf),
+            iss:        Some(claims.iss),
+            aud:        Some(claims.aud),
+            jti:        Some(claims.jti),
+        }
+    }
+}
+
+// Tell an authenticated client whether a token is active, and if so, what it says.
+// A banned, expired, or otherwise invalid token is simply inactive.
+//
+#[tracing::instrument(name = "introspect", skip_all, fields(client = %client.0))]
+pub async fn introspect(
+    State(state):   State<AppState>,
+    client:         AuthenticatedClient,
+    Form(request):  Form<IntrospectRequest>,
+    ) -> impl IntoResponse {
+    debug!(hint = ?request.token_type_hint, "Introspecting token");
+    let banned_tokens = state.banned_tokens.clone();
+    let response      = match validate_token(&request.token, banned_tokens).await {
+        Ok(claims) => IntrospectResponse::from(claims),
+        Err(e)     => {
+            debug!("Token is inactive: {:?}", e);
+            IntrospectResponse::inactive()
+        },
+    };
+    Json(response)
+}
@@ -0,0 +1,11 @@
+use crate::utils::constants::KEY_RING;
+use axum::response::IntoResponse;
+use axum::Json;
+
+// Publish the public half of every key in the key ring so other services can verify
+// our JWT auth tokens without sharing a secret. Clients pick the key by the token's `kid`.
+//
+#[tracing::instrument(name = "jwks", skip_all)]
+pub async fn jwks() -> impl IntoResponse {
+    Json(KEY_RING.jwks())
+}
@@ -3,8 +3,13 @@ use crate::domain::email::Email;
 use crate::domain::error::AuthAPIError;
 use crate::domain::password::Password;
 use crate::domain::{LoginAttemptId, TwoFACode};
+use crate::routes::extractors::ClientInfo;
+use crate::routes::notifications::send_verification_email;
+use crate::routes::second_factor::{confirmed_totp_secret, send_2fa_code};
+use crate::routes::session_helpers::start_session;
 use crate::routes::LoginResponse::TwoFactorAuth;
-use crate::utils::auth::generate_auth_cookie;
+use crate::utils::auth::generate_pending_2fa_cookie;
+use crate::utils::constants::TWO_FA_CODE_HASHER;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
@@ -18,6 +23,8 @@ use tracing::debug;
 pub struct LoginRequest {
     pub email:          String,
     pub password:       Secret<String>,
+    #[serde(default, rename = "rememberMe")]
+    pub remember_me:    bool,
 }
 
 // The login route can return 2 possible success responses.
@@ -49,6 +56,7 @@ impl TwoFactorAuthResponse {
 pub async fn login(
     State(state):   State<AppState>,
     jar:            CookieJar,
+    client:         ClientInfo,
     Json(request):  Json<LoginRequest>,
     ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     debug!("Received login request: {:?}", request);
@@ -87,41 +95,52 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
     println!("User acquired.");
-    let auth_cookie = match generate_auth_cookie(&user.email) {
-        Ok(cookie) => cookie,
-        Err(e)     => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
-    };
-
-    println!("Cookie generated: {}", auth_cookie.value());
-    let updated_jar = jar.add(auth_cookie);
-    println!("Cookie jar updated. {}.", updated_jar.iter().count());
+    drop(user_store);
+
+    // Only the owner of the address gets in. Users who lost the link get a new one
+    // when they log in with their password.
+    if !user.email_verified {
+        if let Err(e) = send_verification_email(&email, &state).await {
+            return (jar, Err(e));
+        }
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
 
-    match user.requires_2fa {
-        true  => handle_2fa(&email, &state, updated_jar).await,
-        false => handle_no_2fa(&user.email, updated_jar).await,
+    // Enrolling an authenticator app turns on 2FA.
+    // The auth cookie only comes with a session, so a 2FA user gets none until the second factor passes.
+    let authenticator = match confirmed_totp_secret(&user.email, &state).await {
+        Ok(secret) => secret.is_some(),
+        Err(e)     => return (jar, Err(e)),
+    };
+    match user.requires_2fa || authenticator {
+        true  => handle_2fa(&email, &state, jar, authenticator).await,
+        false => handle_no_2fa(&user.email, &state, jar, client, request.remember_me).await,
     }
 }
 
 #[tracing::instrument(name = "handle non 2fa", skip_all)]
-async fn handle_no_2fa(email: &Email, jar: CookieJar) ->
+async fn handle_no_2fa(email: &Email, state: &AppState, jar: CookieJar, client: ClientInfo, remember_me: bool) ->
 (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 )
 {
-    let auth_cookie = match generate_auth_cookie(email) {
-        Ok(cookie) => cookie,
-        Err(e)     => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    let (auth_cookie, refresh_cookie) = match start_session(email, client, remember_me, state).await {
+        Ok(cookies) => cookies,
+        Err(e)      => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
     println!("Updating cookie jar");
-    let cookies = jar.add(auth_cookie);
+    let cookies = jar.add(auth_cookie).add(refresh_cookie);
     let body    = Json(LoginResponse::RegularAuth);
     (cookies, Ok((StatusCode::OK, body)))
 }
 
+// Users with an authenticator app answer with a code from the app, so no code is emailed to them.
+// Everyone gets a pending 2FA cookie, which only the steps answering the 2FA challenge accept, for this login attempt.
+//
 #[tracing::instrument(name = "handle 2fa", skip_all)]
-async fn handle_2fa(email: &Email, state: &AppState, jar: CookieJar) ->
+async fn handle_2fa(email: &Email, state: &AppState, jar: CookieJar, authenticator: bool) ->
 (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
@@ -131,13 +150,14 @@ async fn handle_2fa(email: &Email, state: &AppState, jar: CookieJar) ->
     match store_result {
         Err(e) => (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
         Ok((id, code)) => {
-            let cookies    = jar;
-            let emailer    = state.email_client.write().await;
-            let subject    = "Login requires 2FA code";
-            let content    = format!("Code [{}], Login Attempt ID: {}\n", id, code);
-            match emailer.send_email(email, subject, content.as_ref()).await {
-                Ok(_) => {},
-                Err(e) => return (cookies, Err(AuthAPIError::UnexpectedError(e))),
+            let cookies    = match generate_pending_2fa_cookie(email, &id) {
+                Ok(cookie) => jar.add(cookie),
+                Err(e)     => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+            };
+            if !authenticator {
+                if let Err(e) = send_2fa_code(email, &id, &code, state).await {
+                    return (cookies, Err(e));
+                }
             }
 
             let response         = TwoFactorAuthResponse::new(id);
@@ -155,8 +175,9 @@ async fn handle_2fa(email: &Email, state: &AppState, jar: CookieJar) ->
 async fn write_2fa_details_into_code_store(state: &AppState, email: &Email) -> Result<(LoginAttemptId, TwoFACode), AuthAPIError> {
     let code             = TwoFACode::new();
     let login_attempt_id = LoginAttemptId::new();
+    let code_hash        = TWO_FA_CODE_HASHER.hash(&code, login_attempt_id.as_ref());
     let mut store        = state.two_fa_code_store.write().await;
-    match store.add_code(email.clone(), login_attempt_id.clone(), code.clone()).await {
+    match store.add_code(email.clone(), login_attempt_id.clone(), code_hash).await {
         Ok(_)  => Ok((login_attempt_id, code)),
         Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
 
@@ -1,11 +1,12 @@
 use crate::app_state::AppState;
-use crate::domain::AuthAPIError;
+use crate::domain::{AuthAPIError, SessionStoreError};
+use crate::routes::session_helpers::{end_session, remove_session_cookies, subject_email};
 use crate::utils::auth::validate_token;
-use crate::utils::constants::JWT_COOKIE_NAME;
+use crate::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum_extra::extract::{cookie, CookieJar};
+use axum_extra::extract::CookieJar;
 use color_eyre::eyre::eyre;
 use secrecy::Secret;
 use tracing::warn;
@@ -23,16 +24,18 @@ pub async fn logout(
    // Validate token
    let token     = cookie.value();
    let store     = state.banned_tokens.clone();
-   if let Err(_) = validate_token(token, store).await {
-		warn!("Token is invalid.");
-      return Err(AuthAPIError::InvalidToken);
-   }
+   let claims    = match validate_token(token, store).await {
+      Ok(claims) => claims,
+      Err(_)     => {
+         warn!("Token is invalid.");
+         return Err(AuthAPIError::InvalidToken);
+      }
+   };
    
-   // Add token to banned tokens store
-   let token = Secret::new(token.to_owned());
+   // Ban the token by its id until it expires
    if state.banned_tokens
       .write().await
-      .add_token(&token).await
+      .add_token(&claims.jti, claims.exp).await
       .is_err() {
       return Err(AuthAPIError::UnexpectedError(eyre!("Failed to add token to banned tokens store.")));
    }
@@ -40,10 +43,27 @@ pub async fn logout(
    let count = state.banned_tokens.read().await.count().await
 		.unwrap();
    println!("Banned Count: {}", count);
-   
-   // Remove cookie and return modified jar
-   let cookie_for_removal = cookie::Cookie::build(JWT_COOKIE_NAME).path("/").build();
-   let updated_jar        = jar.remove(cookie_for_removal);
+
+   // End the session the token belongs to
+   let email = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+   match end_session(&email, &claims.sid, &state).await {
+      Ok(_) | Err(SessionStoreError::SessionNotFound) => {},
+      Err(e)                                          => return Err(AuthAPIError::UnexpectedError(e.into())),
+   }
+
+   // Revoke the refresh token family, if the client holds a refresh token
+   if let Some(refresh) = jar.get(REFRESH_COOKIE_NAME) {
+      let token              = Secret::new(refresh.value().to_owned());
+      let mut refresh_tokens = state.refresh_tokens.write().await;
+      if let Ok(record) = refresh_tokens.consume_token(&token).await {
+         refresh_tokens
+            .revoke_family(&record.family_id).await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+      }
+   }
+
+   // Remove cookies and return modified jar
+   let updated_jar        = remove_session_cookies(jar);
    println!("Cookie removed from jar. {}.", updated_jar.iter().count());
    Ok((updated_jar, StatusCode::OK))
 }
@@ -0,0 +1,56 @@
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+use crate::routes::session_helpers::{remove_session_cookies, revoke_all_sessions, subject_email};
+use crate::utils::auth::validate_token;
+use crate::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use tracing::{info, warn};
+
+// Log the user out on every device. Every JWT auth token and refresh token issued to the user
+// up to now is revoked by recording a "not valid before" epoch for the user.
+//
+#[tracing::instrument(name = "logout all", skip_all)]
+pub async fn logout_all(
+   State(state):   State<AppState>,
+   jar:            CookieJar,
+   ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+   let cookie = match jar.get(JWT_COOKIE_NAME) {
+      Some(c) => c,
+      None    => { return Err(AuthAPIError::MissingToken) }
+   };
+
+   // Validate token
+   let token  = cookie.value();
+   let store  = state.banned_tokens.clone();
+   let claims = match validate_token(token, store).await {
+      Ok(claims) => claims,
+      Err(_)     => {
+         warn!("Token is invalid.");
+         return Err(AuthAPIError::InvalidToken);
+      }
+   };
+
+   // Revoke everything issued to the user so far, including pending 2FA login attempts
+   let email = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+   revoke_all_sessions(&email, &state).await?;
+   info!("All tokens and sessions of the user revoked.");
+
+   // The refresh token of this device is covered by the epoch, but revoking its family
+   // stops the rotation chain right away
+   if let Some(refresh) = jar.get(REFRESH_COOKIE_NAME) {
+      let token              = Secret::new(refresh.value().to_owned());
+      let mut refresh_tokens = state.refresh_tokens.write().await;
+      if let Ok(record) = refresh_tokens.consume_token(&token).await {
+         refresh_tokens
+            .revoke_family(&record.family_id).await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+      }
+   }
+
+   let updated_jar = remove_session_cookies(jar);
+   Ok((updated_jar, StatusCode::OK))
+}
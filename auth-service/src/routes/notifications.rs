@@ -0,0 +1,38 @@
+use secrecy::{ExposeSecret, Secret};
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email};
+use crate::utils::auth::generate_email_verification_token;
+use crate::utils::constants::{AUTH_SERVICE_URL, PASSWORD_RESET_TTL_SECONDS};
+
+// Email a new user the link that verifies their address. Every link works once, and until
+// one of them is opened the user cannot log in.
+//
+pub async fn send_verification_email(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+	let token   = generate_email_verification_token(email).map_err(AuthAPIError::UnexpectedError)?;
+	let link    = format!("{}/verify-email?token={}", *AUTH_SERVICE_URL, token);
+	let content = format!("Open this link to verify your email address: {}\n", link);
+	state.email_client.read().await
+		.send_email(email, "Verify your email address", &content).await
+		.map_err(AuthAPIError::UnexpectedError)
+}
+
+// Email a user who forgot their password the token that lets them set a new one.
+//
+pub async fn send_password_reset_email(email: &Email, token: &Secret<String>, state: &AppState) -> Result<(), AuthAPIError> {
+	let minutes = PASSWORD_RESET_TTL_SECONDS / 60;
+	let content = format!(
+		"Use this token to reset your password within {} minutes: {}\nIf you did not ask to reset your password, you can ignore this email.\n",
+		minutes, token.expose_secret());
+	state.email_client.read().await
+		.send_email(email, "Reset your password", &content).await
+		.map_err(AuthAPIError::UnexpectedError)
+}
+
+// Tell the user about a change to their account, by email whatever their 2FA channel,
+// so a change they did not make does not go unnoticed.
+//
+pub async fn send_notification(email: &Email, subject: &str, content: &str, state: &AppState) -> Result<(), AuthAPIError> {
+	state.email_client.read().await
+		.send_email(email, subject, content).await
+		.map_err(AuthAPIError::UnexpectedError)
+}
@@ -0,0 +1,239 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, PasskeyStoreError, WebAuthnChallengeStoreError};
+use crate::routes::extractors::{AuthenticatedUser, ClientInfo};
+use crate::routes::session_helpers::{check_pending_2fa_cookie, remove_pending_2fa_cookie, start_session, subject_email};
+use crate::utils::constants::{TWO_FA_CODE_HASHER, WEBAUTHN};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use webauthn_rs::prelude::{
+    Base64UrlSafeData, CreationChallengeResponse, PasskeyAuthentication, PasskeyRegistration, PublicKeyCredential,
+    RegisterPublicKeyCredential, RequestChallengeResponse, Uuid,
+};
+use webauthn_rs_proto::AllowCredentials;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasskeyLoginStartRequest {
+    pub email: String,
+}
+
+// The ceremony id comes back with the signed response. The rest are the options
+// for navigator.credentials.get().
+//
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasskeyLoginStartResponse {
+    #[serde(rename = "ceremonyId")]
+    pub ceremony_id: String,
+    #[serde(flatten)]
+    pub options:     RequestChallengeResponse,
+}
+
+// Without a login attempt id this is a passwordless login. With one, the passkey answers
+// the 2FA challenge of a password login in place of a code.
+//
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasskeyLoginFinishRequest {
+    #[serde(rename = "ceremonyId")]
+    pub ceremony_id:      String,
+    pub credential:       PublicKeyCredential,
+    #[serde(default, rename = "loginAttemptId")]
+    pub login_attempt_id: Option<String>,
+    #[serde(default, rename = "rememberMe")]
+    pub remember_me:      bool,
+}
+
+#[derive(Deserialize, Serialize)]
+struct PendingRegistration {
+    user_handle: Uuid,
+    state:       PasskeyRegistration,
+}
+
+#[derive(Deserialize, Serialize)]
+struct PendingLogin {
+    email: String,
+    state: PasskeyAuthentication,
+}
+
+// Start registering a passkey for the authenticated user. The response holds the options
+// for navigator.credentials.create(). Passkeys the user already has are excluded, so an
+// authenticator is not registered twice.
+//
+#[tracing::instrument(name = "start passkey registration", skip_all)]
+pub async fn start_passkey_registration(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    ) -> Result<Json<CreationChallengeResponse>, AuthAPIError> {
+    let email       = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    let passkeys    = state.passkeys.read().await;
+    let user_handle = passkeys.get_user_handle(&email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .unwrap_or_else(Uuid::new_v4);
+    let existing    = passkeys.get_passkeys(&email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .iter()
+        .map(|passkey| passkey.cred_id().clone())
+        .collect::<Vec<_>>();
+    drop(passkeys);
+
+    let name             = email.expose_secret();
+    let (options, reg)   = WEBAUTHN
+        .start_passkey_registration(user_handle, name, name, Some(existing))
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let pending          = PendingRegistration {user_handle, state: reg};
+    save_challenge(&state, &registration_id(&claims.sid), &pending).await?;
+    Ok(Json(options))
+}
+
+#[tracing::instrument(name = "finish passkey registration", skip_all)]
+pub async fn finish_passkey_registration(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(credential):            Json<RegisterPublicKeyCredential>,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email   = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    let pending = take_challenge::<PendingRegistration>(&state, &registration_id(&claims.sid)).await?;
+    let passkey = WEBAUTHN
+        .finish_passkey_registration(&credential, &pending.state)
+        .map_err(|e| {
+            warn!(?e, "Passkey registration failed");
+            AuthAPIError::IncorrectCredentials
+        })?;
+    match state.passkeys.write().await.add_passkey(&email, pending.user_handle, &passkey).await {
+        Ok(())                                                => info!("Passkey registered."),
+        Err(PasskeyStoreError::CredentialAlreadyRegistered) => return Err(AuthAPIError::PasskeyAlreadyRegistered),
+        Err(e)                                                => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    Ok(StatusCode::CREATED)
+}
+
+// Start logging in with a passkey. Users without any passkey get a decoy ceremony that
+// looks the same, so the answer does not tell who has passkeys. Finishing it fails like a wrong credential.
+//
+#[tracing::instrument(name = "start passkey login", skip_all)]
+pub async fn start_passkey_login(
+    State(state):   State<AppState>,
+    Json(request):  Json<PasskeyLoginStartRequest>,
+    ) -> Result<Json<PasskeyLoginStartResponse>, AuthAPIError> {
+    let email       = Email::parse(Secret::new(request.email)).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let passkeys    = state.passkeys.read().await.get_passkeys(&email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let ceremony_id = Uuid::new_v4().to_string();
+    if passkeys.is_empty() {
+        return Ok(Json(PasskeyLoginStartResponse {ceremony_id, options: decoy_options(&email)?}));
+    }
+
+    let (options, auth) = WEBAUTHN
+        .start_passkey_authentication(&passkeys)
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let pending         = PendingLogin {email: email.expose_secret().to_owned(), state: auth};
+    save_challenge(&state, &login_id(&ceremony_id), &pending).await?;
+    Ok(Json(PasskeyLoginStartResponse {ceremony_id, options}))
+}
+
+// Options as for a user with one passkey. Its id is a keyed HMAC of the email, so it stays the same
+// from one request to the next like a real one, and cannot be told apart without the key.
+// No ceremony is saved for it.
+//
+fn decoy_options(email: &Email) -> Result<RequestChallengeResponse, AuthAPIError> {
+    let (mut options, _) = WEBAUTHN
+        .start_discoverable_authentication()
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let id               = TWO_FA_CODE_HASHER.tag(email.expose_secret(), "passkey decoy");
+    options.mediation                    = None;
+    options.public_key.extensions        = None;
+    options.public_key.allow_credentials = vec![AllowCredentials {
+        type_:      "public-key".to_owned(),
+        id:         Base64UrlSafeData::from(id),
+        transports: None,
+    }];
+    Ok(options)
+}
+
+#[tracing::instrument(name = "finish passkey login", skip_all)]
+pub async fn finish_passkey_login(
+    State(state):   State<AppState>,
+    jar:            CookieJar,
+    client:         ClientInfo,
+    Json(request):  Json<PasskeyLoginFinishRequest>,
+    ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let pending = take_challenge::<PendingLogin>(&state, &login_id(&request.ceremony_id)).await?;
+    let email   = Email::parse(Secret::new(pending.email)).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let result  = WEBAUTHN
+        .finish_passkey_authentication(&request.credential, &pending.state)
+        .map_err(|e| {
+            warn!(?e, "Passkey authentication failed");
+            AuthAPIError::IncorrectCredentials
+        })?;
+
+    // Keep the signature counter, so a cloned authenticator shows up as going backwards
+    let mut passkeys = state.passkeys.write().await;
+    let mut used     = passkeys.get_passkeys(&email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .find(|passkey| passkey.cred_id() == result.cred_id())
+        .ok_or(AuthAPIError::IncorrectCredentials)?;
+    used.update_credential(&result);
+    passkeys.update_passkey(&email, &used).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(passkeys);
+
+    let jar = match request.login_attempt_id {
+        Some(attempt_id) => {
+            complete_login_attempt(&state, &jar, &email, attempt_id).await?;
+            remove_pending_2fa_cookie(jar)
+        },
+        None             => jar,
+    };
+
+    let (auth_cookie, refresh_cookie) = start_session(&email, client, request.remember_me, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    info!("Logged in with a passkey.");
+    Ok((jar.add(auth_cookie).add(refresh_cookie), StatusCode::OK))
+}
+
+// The pending 2FA login a passkey answers for. Like a code, only the client that passed the password
+// step may answer it. The user's codes are dropped, so they cannot be used as well.
+//
+async fn complete_login_attempt(state: &AppState, jar: &CookieJar, email: &Email, attempt_id: String) -> Result<(), AuthAPIError> {
+    let attempt_id = LoginAttemptId::parse(attempt_id).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    check_pending_2fa_cookie(jar, email, &attempt_id)?;
+    let mut codes  = state.two_fa_code_store.write().await;
+    let (owner, _) = codes.get_code(&attempt_id).await.map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    if owner != *email {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+    codes.remove_codes(email).await.map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+fn registration_id(session_id: &str) -> String {
+    format!("registration:{}", session_id)
+}
+
+fn login_id(ceremony_id: &str) -> String {
+    format!("login:{}", ceremony_id)
+}
+
+async fn save_challenge<T: Serialize>(state: &AppState, id: &str, pending: &T) -> Result<(), AuthAPIError> {
+    let serialized = serde_json::to_string(pending).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state.webauthn_challenges.write().await
+        .add_challenge(id, serialized).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// A ceremony that was never started, already finished, or timed out fails like a wrong credential.
+//
+async fn take_challenge<T: DeserializeOwned>(state: &AppState, id: &str) -> Result<T, AuthAPIError> {
+    let serialized = match state.webauthn_challenges.write().await.take_challenge(id).await {
+        Ok(serialized)                                        => serialized,
+        Err(WebAuthnChallengeStoreError::ChallengeNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e)                                                => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    serde_json::from_str(&serialized).map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
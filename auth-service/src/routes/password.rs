@@ -0,0 +1,131 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, Password, PasswordResetTokenStoreError, UserStoreError};
+use crate::routes::extractors::AuthenticatedUser;
+use crate::routes::notifications::{send_notification, send_password_reset_email};
+use crate::routes::session_helpers::{check_password, revoke_all_sessions, revoke_other_sessions, subject_email};
+use crate::utils::audit::{self, AuditEvent};
+use crate::utils::auth::generate_password_reset_token;
+use axum::extract::State;
+use axum::Json;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn, Instrument};
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token:        Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password:     Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct PasswordResponse {
+    pub message: String,
+}
+
+// Email a reset token to the user. The response is the same whether or not the account
+// exists, so the endpoint cannot be used to find out who has one. Nor can the time it takes:
+// the token is stored and emailed after answering, so both answers only wait for the lookup.
+//
+#[tracing::instrument(name = "forgot password", skip_all)]
+pub async fn forgot_password(
+    State(state):   State<AppState>,
+    Json(request):  Json<ForgotPasswordRequest>,
+    ) -> Result<Json<PasswordResponse>, AuthAPIError> {
+    let email   = Email::parse(Secret::new(request.email)).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let message = "If the account exists, a password reset token has been emailed".to_owned();
+    let known   = state.user_store.read().await.get_user(&email).await;
+    match known {
+        Ok(_)                             => {
+            tokio::spawn(async move {
+                if let Err(e) = send_password_reset(&email, &state).await {
+                    error!(?e, "Failed to send a password reset token");
+                }
+            }.in_current_span());
+        },
+        Err(UserStoreError::UserNotFound) => info!("Password reset asked for an unknown account."),
+        Err(e)                            => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    Ok(Json(PasswordResponse {message}))
+}
+
+async fn send_password_reset(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+    let token = generate_password_reset_token();
+    state.password_resets.write().await
+        .add_token(&token, email.clone()).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    send_password_reset_email(email, &token, state).await?;
+    audit::emit(AuditEvent::PasswordResetRequested {email});
+    Ok(())
+}
+
+// Set a new password with an emailed reset token. The token is used up, and every session
+// of the user is revoked, so whoever knew the old password is logged out everywhere.
+//
+#[tracing::instrument(name = "reset password", skip_all)]
+pub async fn reset_password(
+    State(state):   State<AppState>,
+    Json(request):  Json<ResetPasswordRequest>,
+    ) -> Result<Json<PasswordResponse>, AuthAPIError> {
+    // Check the new password first, so a rejected one does not use up the token
+    let password = Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let email    = match state.password_resets.write().await.take_token(&request.token).await {
+        Ok(email)                                       => email,
+        Err(PasswordResetTokenStoreError::TokenNotFound) => {
+            warn!("Password reset token is unknown, used or expired.");
+            return Err(AuthAPIError::InvalidToken);
+        },
+        Err(e)                                          => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    match state.user_store.write().await.set_password(&email, password).await {
+        Ok(())                            => {},
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e)                            => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    revoke_all_sessions(&email, &state).await?;
+
+    let content = "The password of your account was reset, and every session was logged out.\n";
+    send_notification(&email, "Your password was reset", content, &state).await?;
+    audit::emit(AuditEvent::PasswordReset {email: &email});
+    info!("Password reset.");
+    let message = "Password has been reset".to_owned();
+    Ok(Json(PasswordResponse {message}))
+}
+
+// Change the authenticated user's password, who has to enter the current one again.
+// Every other session of the user is revoked; the one making the change stays logged in.
+//
+#[tracing::instrument(name = "change password", skip_all)]
+pub async fn change_password(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(request):               Json<ChangePasswordRequest>,
+    ) -> Result<Json<PasswordResponse>, AuthAPIError> {
+    let email    = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    check_password(&email, request.current_password, &state).await?;
+    let password = Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    state.user_store.write().await
+        .set_password(&email, password).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    revoke_other_sessions(&email, &claims.sid, &state).await?;
+
+    let content = "The password of your account was changed, and every other session was logged out.\n";
+    send_notification(&email, "Your password was changed", content, &state).await?;
+    audit::emit(AuditEvent::PasswordChanged {email: &email});
+    info!("Password changed.");
+    let message = "Password has been changed".to_owned();
+    Ok(Json(PasswordResponse {message}))
+}
@@ -0,0 +1,99 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, PhoneNumber, PhoneStoreError, TwoFAChannel, TwoFACode};
+use crate::routes::extractors::AuthenticatedUser;
+use crate::routes::session_helpers::subject_email;
+use crate::utils::constants::{MAX_2FA_ATTEMPTS, MAX_2FA_RESENDS, TWO_FA_CODE_HASHER, TWO_FA_RESEND_COOLDOWN_SECONDS};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PhoneNumberRequest {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PhoneVerifyRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TwoFAChannelRequest {
+    pub channel: TwoFAChannel,
+}
+
+// Register a phone number for the authenticated user, and send it a code by SMS.
+// Registering a number replaces the old one, and 2FA codes go by email until the
+// new number is verified. Every registration costs a text, so they are limited like
+// resending a 2FA code, and wrong codes are not forgotten by registering again.
+//
+#[tracing::instrument(name = "register phone number", skip_all)]
+pub async fn register_phone(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(request):               Json<PhoneNumberRequest>,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email        = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    let phone_number = PhoneNumber::parse(Secret::new(request.phone_number)).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let code         = TwoFACode::new();
+    let content      = format!("Your verification code is {}", code);
+    let code_hash    = TWO_FA_CODE_HASHER.hash(&code, email.expose_secret());
+    state.phones.write().await
+        .start_verification(&email, phone_number.clone(), code_hash, TWO_FA_RESEND_COOLDOWN_SECONDS, MAX_2FA_RESENDS, *MAX_2FA_ATTEMPTS).await
+        .map_err(|e| match e {
+            PhoneStoreError::ResendTooSoon      => AuthAPIError::ResendTooSoon,
+            PhoneStoreError::ResendLimitReached
+            | PhoneStoreError::TooManyAttempts  => AuthAPIError::TooManyAttempts,
+            e                                   => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    state.sms_client.read().await
+        .send_sms(&phone_number, &content).await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    info!("Phone number registered, verification code sent.");
+    Ok(StatusCode::OK)
+}
+
+// Verify the phone number with the code it was sent. Wrong codes are limited like wrong
+// 2FA codes; once the limit is reached the number has to be registered again.
+//
+#[tracing::instrument(name = "verify phone number", skip_all)]
+pub async fn verify_phone(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(request):               Json<PhoneVerifyRequest>,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    let code  = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let hash  = TWO_FA_CODE_HASHER.hash(&code, email.expose_secret());
+    match state.phones.write().await.verify_number(&email, &hash, *MAX_2FA_ATTEMPTS).await {
+        Ok(())                                => info!("Phone number verified."),
+        Err(PhoneStoreError::IncorrectCode)
+        | Err(PhoneStoreError::CodeNotFound)  => return Err(AuthAPIError::IncorrectCredentials),
+        Err(PhoneStoreError::TooManyAttempts) => return Err(AuthAPIError::TooManyAttempts),
+        Err(e)                                => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    Ok(StatusCode::OK)
+}
+
+// Choose whether emailed 2FA codes go to the user's email address or to their verified phone number.
+//
+#[tracing::instrument(name = "set 2fa channel", skip_all)]
+pub async fn set_2fa_channel(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(request):               Json<TwoFAChannelRequest>,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    match state.phones.write().await.set_channel(&email, request.channel).await {
+        Ok(())                              => info!(channel = request.channel.as_str(), "2FA channel set."),
+        Err(PhoneStoreError::NotRegistered)
+        | Err(PhoneStoreError::NotVerified) => return Err(AuthAPIError::PhoneNotVerified),
+        Err(e)                              => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,38 @@
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+use crate::routes::extractors::AuthenticatedUser;
+use crate::routes::second_factor::{confirmed_totp_secret, issue_recovery_codes};
+use crate::routes::session_helpers::subject_email;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
+
+// Replace the authenticated user's recovery codes, for when they ran out or were exposed.
+// Every earlier code stops working. Only users with a second factor have recovery codes.
+//
+#[tracing::instrument(name = "regenerate recovery codes", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email         = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    let user          = state.user_store.read().await
+        .get_user(&email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let authenticator = confirmed_totp_secret(&email, &state).await?.is_some();
+    if !user.requires_2fa && !authenticator {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    let recovery_codes = issue_recovery_codes(&email, &state).await?;
+    info!("Recovery codes regenerated.");
+    Ok(Json(RecoveryCodesResponse {recovery_codes}))
+}
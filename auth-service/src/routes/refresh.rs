@@ -0,0 +1,71 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, RefreshTokenStoreError};
+use crate::routes::session_helpers::remove_session_cookies;
+use crate::utils::auth::{generate_auth_cookie, generate_refresh_cookie, is_before_epoch};
+use crate::utils::constants::{REFRESH_COOKIE_NAME, TOKEN_POLICY};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use tracing::{debug, warn};
+
+// Exchange a refresh token for a new JWT auth token and a new refresh token.
+// The presented refresh token is consumed. Presenting it a second time is treated
+// as theft and revokes every token in its family.
+//
+#[tracing::instrument(name = "refresh", skip_all)]
+pub async fn refresh(
+    State(state):   State<AppState>,
+    jar:            CookieJar,
+    ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let token = match jar.get(REFRESH_COOKIE_NAME) {
+        Some(c) => Secret::new(c.value().to_owned()),
+        None    => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let consumed = state.refresh_tokens.write().await.consume_token(&token).await;
+    let record   = match consumed {
+        Ok(record)                                      => record,
+        Err(RefreshTokenStoreError::UnexpectedError(e)) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+        Err(RefreshTokenStoreError::TokenReused)        => {
+            warn!("Refresh token was presented twice. Token family revoked.");
+            return (remove_session_cookies(jar), Err(AuthAPIError::InvalidToken));
+        },
+        Err(e)                                          => {
+            warn!("Refresh token rejected: {}", e);
+            return (remove_session_cookies(jar), Err(AuthAPIError::InvalidToken));
+        },
+    };
+
+    // The user may have logged out everywhere since this token was issued
+    let subject = record.email.expose_secret().to_owned();
+    let epoch   = match state.banned_tokens.read().await.get_not_before(&subject).await {
+        Ok(epoch) => epoch,
+        Err(e)    => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    if is_before_epoch(record.issued_at, epoch) {
+        warn!("Refresh token was issued before the user logged out everywhere.");
+        let _ = state.refresh_tokens.write().await.revoke_family(&record.family_id).await;
+        return (remove_session_cookies(jar), Err(AuthAPIError::InvalidToken));
+    }
+    debug!("Refresh token accepted. Rotating.");
+
+    let session_id  = record.family_id.as_str();
+    if let Err(e) = state.sessions.write().await.touch_session(&record.email, session_id).await {
+        warn!("Session of refresh token could not be updated: {}", e);
+    }
+    let ttl         = TOKEN_POLICY.ttl(record.remember_me);
+    let auth_cookie = match generate_auth_cookie(&record.email, session_id, ttl) {
+        Ok(cookie) => cookie,
+        Err(e)     => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+    let store          = state.refresh_tokens.clone();
+    let refresh_cookie = match generate_refresh_cookie(&record.email, session_id, record.remember_me, store).await {
+        Ok(cookie) => cookie,
+        Err(e)     => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+    (updated_jar, Ok(StatusCode::OK))
+}
@@ -0,0 +1,72 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode, TwoFACodeStoreError};
+use crate::routes::second_factor::{confirmed_totp_secret, send_2fa_code};
+use crate::routes::session_helpers::check_pending_2fa_cookie;
+use crate::utils::auth::generate_pending_2fa_cookie;
+use crate::utils::constants::{MAX_2FA_RESENDS, TWO_FA_CODE_HASHER, TWO_FA_RESEND_COOLDOWN_SECONDS};
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct Resend2FARequest {
+    pub email:            String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+/*
+// This is what the request looks like in Json
+{
+  "email":          "user@example.com",
+  "loginAttemptId": "b59d20f4-dfcf-4fc5-af99-e312e0e2d2aa"
+}
+*/
+
+#[tracing::instrument(name = "resend 2fa", skip_all)]
+pub async fn resend_2fa(
+    State(state):  State<AppState>,
+    jar:           CookieJar,
+    Json(request): Json<Resend2FARequest>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError>
+{
+    let email      = Secret::new(request.email);
+    let email      = Email::parse(email)                              .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let attempt_id = LoginAttemptId::parse(request.login_attempt_id)  .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Only the client holding the login attempt in progress can ask for a new code, and only
+    // users who answer with an emailed code have any use for one.
+    //
+    check_pending_2fa_cookie(&jar, &email, &attempt_id)?;
+    let current    = state.two_fa_code_store.read().await.get_code(&attempt_id).await;
+    match current {
+        Ok((owner, _)) if owner == email => {},
+        _                                => return Err(AuthAPIError::IncorrectCredentials),
+    }
+    if confirmed_totp_secret(&email, &state).await?.is_some() {
+        return Err(AuthAPIError::TotpAlreadyEnrolled);
+    }
+
+    let code       = TwoFACode::new();
+    let code_hash  = TWO_FA_CODE_HASHER.hash(&code, attempt_id.as_ref());
+    state.two_fa_code_store.write().await
+        .resend_code(&attempt_id, code_hash, TWO_FA_RESEND_COOLDOWN_SECONDS, MAX_2FA_RESENDS).await
+        .map_err(|e| match e {
+            TwoFACodeStoreError::LoginAttemptIdNotFound => AuthAPIError::IncorrectCredentials,
+            TwoFACodeStoreError::ResendTooSoon          => AuthAPIError::ResendTooSoon,
+            TwoFACodeStoreError::ResendLimitReached     => AuthAPIError::TooManyAttempts,
+            TwoFACodeStoreError::UnexpectedError(e)     => AuthAPIError::UnexpectedError(e),
+        })?;
+
+    debug!("Sending the new code");
+    send_2fa_code(&email, &attempt_id, &code, &state).await?;
+
+    // The new code lives longer than the old one, and so does the cookie for it
+    let pending    = generate_pending_2fa_cookie(&email, &attempt_id).map_err(AuthAPIError::UnexpectedError)?;
+    Ok((jar.add(pending), StatusCode::OK))
+}
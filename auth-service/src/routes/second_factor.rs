@@ -0,0 +1,121 @@
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use tracing::warn;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, PhoneNumber, PhoneStoreError, RecoveryCodeStoreError, TotpStoreError};
+use crate::domain::{TwoFAChannel, TwoFACode, TwoFACodeStoreError};
+use crate::utils::audit::{self, AuditEvent};
+use crate::utils::constants::MAX_2FA_ATTEMPTS;
+use crate::utils::hash_utils::{hash_password_async, verify_password_async};
+use crate::utils::{recovery_codes, totp};
+
+// Send the code for a login attempt to the user: by SMS if they chose so and their
+// phone number is verified, otherwise by email.
+//
+pub async fn send_2fa_code(email: &Email, login_attempt_id: &LoginAttemptId, code: &TwoFACode, state: &AppState) -> Result<(), AuthAPIError> {
+	if let Some(phone_number) = sms_recipient(email, state).await? {
+		let content = format!("Your login code is {}", code);
+		return state.sms_client.read().await
+			.send_sms(&phone_number, &content).await
+			.map_err(AuthAPIError::UnexpectedError);
+	}
+	let subject = "Login requires 2FA code";
+	let content = format!("Code [{}], Login Attempt ID: {}\n", code, login_attempt_id);
+	state.email_client.read().await
+		.send_email(email, subject, &content).await
+		.map_err(AuthAPIError::UnexpectedError)
+}
+
+async fn sms_recipient(email: &Email, state: &AppState) -> Result<Option<PhoneNumber>, AuthAPIError> {
+	match state.phones.read().await.get_phone(email).await {
+		Ok(phone) if phone.verified && phone.channel == TwoFAChannel::Sms => Ok(Some(phone.phone_number)),
+		Ok(_) | Err(PhoneStoreError::NotRegistered)                         => Ok(None),
+		Err(e)                                                              => Err(AuthAPIError::UnexpectedError(e.into())),
+	}
+}
+
+// The secret of the user's authenticator app, once its enrollment has been confirmed.
+//
+pub async fn confirmed_totp_secret(email: &Email, state: &AppState) -> Result<Option<Secret<Vec<u8>>>, AuthAPIError> {
+	match state.totp_store.read().await.get_enrollment(email).await {
+		Ok(enrollment) if enrollment.confirmed => Ok(Some(enrollment.secret)),
+		Ok(_) | Err(TotpStoreError::NotEnrolled) => Ok(None),
+		Err(e)                                   => Err(AuthAPIError::UnexpectedError(e.into())),
+	}
+}
+
+// Accept an authenticator code once. The time step of an accepted code is burned,
+// so a code seen by someone else cannot be replayed.
+//
+pub async fn redeem_totp_code(email: &Email, secret: &Secret<Vec<u8>>, code: &str, state: &AppState) -> Result<(), AuthAPIError> {
+	let now  = Utc::now().timestamp() as u64;
+	let step = totp::verify_code(secret.expose_secret(), code, now).ok_or(AuthAPIError::IncorrectCredentials)?;
+	match state.totp_store.write().await.use_step(email, step).await {
+		Ok(())                           => Ok(()),
+		Err(TotpStoreError::CodeReused)  => {
+			warn!("Authenticator code was presented twice");
+			Err(AuthAPIError::IncorrectCredentials)
+		},
+		Err(e)                           => Err(AuthAPIError::UnexpectedError(e.into())),
+	}
+}
+
+// Count a wrong answer to a 2FA code. Once the attempt has had MAX_2FA_ATTEMPTS of them,
+// its code is dropped, so guessing further is pointless, and the user has to start over.
+//
+#[tracing::instrument(name = "record failed 2fa attempt", skip_all)]
+pub async fn record_failure(email: &Email, attempt_id: &LoginAttemptId, state: &AppState) -> AuthAPIError {
+	let mut store = state.two_fa_code_store.write().await;
+	let failures  = match store.record_failure(attempt_id).await {
+		Ok(failures)                                     => failures,
+		Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => return AuthAPIError::IncorrectCredentials,
+		Err(e)                                           => return AuthAPIError::UnexpectedError(e.into()),
+	};
+	if failures < *MAX_2FA_ATTEMPTS {
+		return AuthAPIError::IncorrectCredentials;
+	}
+	if let Err(e) = store.remove_code(attempt_id).await {
+		return AuthAPIError::UnexpectedError(e.into());
+	}
+	audit::emit(AuditEvent::TwoFAAttemptsExceeded {email, login_attempt_id: attempt_id, failures});
+	AuthAPIError::TooManyAttempts
+}
+
+// Replace the user's recovery codes with a fresh set. The plain codes are returned
+// to be shown once; only their hashes are kept.
+//
+pub async fn issue_recovery_codes(email: &Email, state: &AppState) -> Result<Vec<String>, AuthAPIError> {
+	let codes      = recovery_codes::generate_codes();
+	let mut hashes = Vec::with_capacity(codes.len());
+	for code in &codes {
+		let hash = hash_password_async(code.expose_secret().clone()).await.map_err(AuthAPIError::UnexpectedError)?;
+		hashes.push(hash);
+	}
+	state.recovery_codes.write().await
+		.replace_codes(email, hashes).await
+		.map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+	Ok(codes.into_iter().map(|code| code.expose_secret().clone()).collect())
+}
+
+// Accept a recovery code in place of the second factor, once. The code is checked
+// against each unused hash, since the hashes are salted and cannot be looked up.
+//
+pub async fn redeem_recovery_code(email: &Email, code: &str, state: &AppState) -> Result<(), AuthAPIError> {
+	let unused = state.recovery_codes.read().await
+		.get_unused_codes(email).await
+		.map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+	for record in unused {
+		if verify_password_async(record.hash, code.to_owned()).await.is_err() {
+			continue;
+		}
+		return match state.recovery_codes.write().await.use_code(email, record.id).await {
+			Ok(())                                      => Ok(()),
+			Err(RecoveryCodeStoreError::CodeAlreadyUsed) => {
+				warn!("Recovery code was presented twice");
+				Err(AuthAPIError::IncorrectCredentials)
+			},
+			Err(e)                                      => Err(AuthAPIError::UnexpectedError(e.into())),
+		};
+	}
+	Err(AuthAPIError::IncorrectCredentials)
+}
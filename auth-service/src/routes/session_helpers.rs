@@ -0,0 +1,121 @@
+use axum_extra::extract::{cookie, CookieJar};
+use axum_extra::extract::cookie::Cookie;
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+use secrecy::Secret;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, Password, Session, SessionStoreError, UserStoreError};
+use crate::routes::extractors::ClientInfo;
+use crate::utils::auth::{generate_auth_cookie, generate_refresh_cookie, validate_pending_2fa_token, Claims};
+use crate::utils::constants::{JWT_COOKIE_NAME, PENDING_2FA_COOKIE_NAME, REFRESH_COOKIE_NAME, REFRESH_TOKEN_TTL_SECONDS, TOKEN_POLICY};
+
+// Remove both the auth cookie and the refresh cookie from the jar.
+//
+pub fn remove_session_cookies(jar: CookieJar) -> CookieJar {
+	let auth    = cookie::Cookie::build(JWT_COOKIE_NAME    ).path("/").build();
+	let refresh = cookie::Cookie::build(REFRESH_COOKIE_NAME).path("/").build();
+	jar.remove(auth).remove(refresh)
+}
+
+// Only the client that passed the password step of a login attempt may answer its 2FA challenge,
+// or ask for a new code. The login left it a pending 2FA cookie naming the user and the attempt.
+//
+pub fn check_pending_2fa_cookie(jar: &CookieJar, email: &Email, attempt_id: &LoginAttemptId) -> Result<(), AuthAPIError> {
+	let cookie = jar.get(PENDING_2FA_COOKIE_NAME).ok_or(AuthAPIError::IncorrectCredentials)?;
+	let claims = validate_pending_2fa_token(cookie.value()).map_err(|_| AuthAPIError::IncorrectCredentials)?;
+	match claims.sub == email.expose_secret() && attempt_id.is_match_str(&claims.sid) {
+		true  => Ok(()),
+		false => Err(AuthAPIError::IncorrectCredentials),
+	}
+}
+
+// Remove the pending 2FA cookie once its login attempt is complete.
+//
+pub fn remove_pending_2fa_cookie(jar: CookieJar) -> CookieJar {
+	jar.remove(cookie::Cookie::build(PENDING_2FA_COOKIE_NAME).path("/").build())
+}
+
+// Record a new session and create the auth cookie and refresh cookie that belong to it.
+// A session that asked to be remembered gets longer lived auth tokens.
+//
+pub async fn start_session(
+	email:       &Email,
+	client:      ClientInfo,
+	remember_me: bool,
+	state:       &AppState,
+	) -> Result<(Cookie<'static>, Cookie<'static>)> {
+	let session_id     = uuid::Uuid::new_v4().to_string();
+	let session        = Session::new(session_id.clone(), email.clone(), client.user_agent, client.ip);
+	state.sessions.write().await.add_session(session).await?;
+	let ttl            = TOKEN_POLICY.ttl(remember_me);
+	let auth_cookie    = generate_auth_cookie(email, &session_id, ttl)?;
+	let refresh_tokens = state.refresh_tokens.clone();
+	let refresh_cookie = generate_refresh_cookie(email, &session_id, remember_me, refresh_tokens).await?;
+	Ok((auth_cookie, refresh_cookie))
+}
+
+// Revoke a session: forget it, stop its refresh token family, and reject the auth tokens
+// it was issued. Those live no longer than the session itself could, so that bounds the ban.
+//
+pub async fn end_session(email: &Email, session_id: &str, state: &AppState) -> Result<(), SessionStoreError> {
+	state.sessions.write().await.remove_session(email, session_id).await?;
+	state.refresh_tokens.write().await
+		.revoke_family(session_id).await
+		.map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+	let exp = (Utc::now().timestamp() + REFRESH_TOKEN_TTL_SECONDS) as usize;
+	state.banned_tokens.write().await
+		.add_token(session_id, exp).await
+		.map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+	Ok(())
+}
+
+// Revoke everything issued to the user so far: every JWT auth token and refresh token, by a
+// "not valid before" epoch, every session, and every login attempt still waiting for its 2FA code.
+//
+pub async fn revoke_all_sessions(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+	let epoch = Utc::now().timestamp_millis() as usize;
+	state.banned_tokens.write().await
+		.set_not_before(email.expose_secret(), epoch).await
+		.map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+	state.sessions.write().await
+		.remove_sessions(email).await
+		.map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+	state.two_fa_code_store.write().await
+		.remove_codes(email).await
+		.map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Revoke every session of the user but the current one, and every login attempt still
+// waiting for its 2FA code. The device the request came from stays logged in.
+//
+pub async fn revoke_other_sessions(email: &Email, current_session_id: &str, state: &AppState) -> Result<(), AuthAPIError> {
+	let sessions = state.sessions.read().await
+		.get_sessions(email).await
+		.map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+	for session in sessions.iter().filter(|session| session.id != current_session_id) {
+		match end_session(email, &session.id, state).await {
+			Ok(()) | Err(SessionStoreError::SessionNotFound) => {},
+			Err(e)                                           => return Err(AuthAPIError::UnexpectedError(e.into())),
+		}
+	}
+	state.two_fa_code_store.write().await
+		.remove_codes(email).await
+		.map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Sensitive changes to an account need the password again, not just a valid auth cookie.
+//
+pub async fn check_password(email: &Email, password: Secret<String>, state: &AppState) -> Result<(), AuthAPIError> {
+	let password = Password::parse(password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+	match state.user_store.read().await.validate_user(email, &password).await {
+		Ok(())                                  => Ok(()),
+		Err(UserStoreError::InvalidCredentials) => Err(AuthAPIError::IncorrectCredentials),
+		Err(e)                                  => Err(AuthAPIError::UnexpectedError(e.into())),
+	}
+}
+
+// The subject of our tokens is always an email address.
+//
+pub fn subject_email(claims: &Claims) -> Result<Email> {
+	Email::parse(Secret::new(claims.sub.clone())).map_err(|e| eyre!(e))
+}
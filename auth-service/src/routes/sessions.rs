@@ -0,0 +1,85 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Session, SessionStoreError};
+use crate::routes::extractors::AuthenticatedUser;
+use crate::routes::session_helpers::{end_session, remove_session_cookies, subject_email};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionResponse {
+    pub id:         String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub ip:         Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastSeen")]
+    pub last_seen:  String,
+    pub current:    bool,
+}
+
+impl SessionResponse {
+    fn new(session: Session, current_id: &str) -> Self {
+        let current = session.id == current_id;
+        Self {
+            id:         session.id,
+            user_agent: session.user_agent,
+            ip:         session.ip,
+            created_at: session.created_at.to_rfc3339(),
+            last_seen:  session.last_seen.to_rfc3339(),
+            current,
+        }
+    }
+}
+
+// List the open sessions of the authenticated user, most recently seen first.
+//
+#[tracing::instrument(name = "list sessions", skip_all)]
+pub async fn list_sessions(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email    = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    let sessions = state.sessions.read().await
+        .get_sessions(&email).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let sessions = sessions
+        .into_iter()
+        .map(|s| SessionResponse::new(s, &claims.sid))
+        .collect();
+    Ok(Json(SessionsResponse {sessions}))
+}
+
+// Revoke one of the authenticated user's sessions. Revoking the current session
+// also removes the session cookies, like a logout.
+//
+#[tracing::instrument(name = "delete session", skip_all)]
+pub async fn delete_session(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    jar:                         CookieJar,
+    Path(session_id):            Path<String>,
+    ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let email = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    match end_session(&email, &session_id, &state).await {
+        Ok(_)                                   => info!("Session revoked."),
+        Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::SessionNotFound),
+        Err(e)                                  => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let jar = match session_id == claims.sid {
+        true  => remove_session_cookies(jar),
+        false => jar,
+    };
+    Ok((jar, StatusCode::NO_CONTENT))
+}
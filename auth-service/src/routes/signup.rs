@@ -3,6 +3,8 @@ use crate::domain::email::Email;
 use crate::domain::error::AuthAPIError;
 use crate::domain::password::Password;
 use crate::domain::user::User;
+use crate::routes::notifications::send_verification_email;
+use crate::routes::second_factor::issue_recovery_codes;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
@@ -19,9 +21,13 @@ pub struct SignupRequest {
     pub requires_2fa: bool,
 }
 
+// Users who sign up with 2FA get their recovery codes here, the only time they are shown.
+//
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub struct SignupResponse {
     pub message: String,
+    #[serde(default, rename = "recoveryCodes", skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
 }
 
 #[instrument(name = "Signup", skip_all)]
@@ -39,13 +45,19 @@ pub async fn signup(
         return Err(AuthAPIError::UserAlreadyExists) 
     }
 
-    let user      = User::new(email, password, request.requires_2fa);
+    let user      = User::new(email.clone(), password, request.requires_2fa);
     let result    = user_store.add_user(user).await;
+    drop(user_store);
     match result {
         Ok(_) => {
             info!("User added successfully");
+            send_verification_email(&email, &state).await?;
+            let recovery_codes = match request.requires_2fa {
+                true  => Some(issue_recovery_codes(&email, &state).await?),
+                false => None,
+            };
             let message  = "User created successfully!".to_owned();
-            let response = Json(SignupResponse{message});
+            let response = Json(SignupResponse{message, recovery_codes});
             Ok((StatusCode::CREATED, response))
         }
         Err(e) => {
@@ -0,0 +1,60 @@
+use crate::app_state::AppState;
+use crate::routes::session_helpers::subject_email;
+use crate::utils::auth::{generate_auth_cookie, validate_token};
+use crate::utils::constants::{JWT_COOKIE_NAME, TOKEN_POLICY};
+use axum::extract::{Request, State};
+use axum::http::header::SET_COOKIE;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use tracing::{debug, warn};
+
+// Sliding expiry. When a request carries a valid auth cookie that expires within the
+// sliding window, the response carries a fresh one with the same lifetime and session.
+// Responses that already set or remove the auth cookie (login, logout, refresh) are left alone.
+//
+#[tracing::instrument(name = "renew auth cookie", skip_all)]
+pub async fn renew_auth_cookie(
+    State(state):  State<AppState>,
+    jar:           CookieJar,
+    request:       Request,
+    next:          Next,
+    ) -> Response {
+    let mut response = next.run(request).await;
+    if TOKEN_POLICY.sliding_window.is_none() {
+        return response;
+    }
+    let token = match jar.get(JWT_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_owned(),
+        None         => return response,
+    };
+    let prefix  = format!("{}=", JWT_COOKIE_NAME);
+    let handled = response.headers().get_all(SET_COOKIE).iter()
+        .any(|v| v.to_str().is_ok_and(|v| v.starts_with(&prefix)));
+    if handled {
+        return response;
+    }
+
+    let claims = match validate_token(&token, state.banned_tokens.clone()).await {
+        Ok(claims) => claims,
+        Err(_)     => return response,
+    };
+    let now    = Utc::now().timestamp() as usize;
+    if !TOKEN_POLICY.needs_renewal(claims.exp, now) {
+        return response;
+    }
+
+    // The renewed token lives as long as the one it replaces did
+    let ttl    = claims.exp.saturating_sub(claims.iat) as i64;
+    let cookie = subject_email(&claims).and_then(|email| generate_auth_cookie(&email, &claims.sid, ttl));
+    match cookie.map(|c| c.to_string().parse()) {
+        Ok(Ok(value)) => {
+            debug!("Auth cookie is about to expire. Renewed.");
+            response.headers_mut().append(SET_COOKIE, value);
+        },
+        Ok(Err(e))    => warn!("Renewed auth cookie is not a valid header: {}", e),
+        Err(e)        => warn!("Failed to renew auth cookie: {:?}", e),
+    }
+    response
+}
@@ -0,0 +1,80 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, TotpStoreError};
+use crate::routes::extractors::AuthenticatedUser;
+use crate::routes::second_factor::issue_recovery_codes;
+use crate::routes::session_helpers::subject_email;
+use crate::routes::recovery_codes::RecoveryCodesResponse;
+use crate::utils::constants::TOTP_ISSUER;
+use crate::utils::totp;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TotpEnrollmentResponse {
+    pub secret:       String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri:  String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TotpConfirmRequest {
+    pub code: String,
+}
+
+// Start enrolling an authenticator app for the authenticated user. The response carries
+// the secret both plain (base32) and as an otpauth:// URI for a QR code. Enrolling again
+// before confirming replaces the secret.
+//
+#[tracing::instrument(name = "enroll totp", skip_all)]
+pub async fn enroll_totp(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email       = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    let secret      = totp::generate_secret();
+    let encoded     = totp::encode_secret(secret.expose_secret());
+    let otpauth_uri = totp::provisioning_uri(secret.expose_secret(), email.expose_secret(), TOTP_ISSUER);
+    match state.totp_store.write().await.start_enrollment(&email, secret).await {
+        Ok(())                               => info!("Authenticator enrollment started."),
+        Err(TotpStoreError::AlreadyEnrolled) => return Err(AuthAPIError::TotpAlreadyEnrolled),
+        Err(e)                               => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    Ok(Json(TotpEnrollmentResponse {secret: encoded, otpauth_uri}))
+}
+
+// Finish enrolling with a first code from the app. From then on, logging in requires
+// a code from the app. The response carries a fresh set of recovery codes, for when
+// the app is lost.
+//
+#[tracing::instrument(name = "confirm totp", skip_all)]
+pub async fn confirm_totp(
+    State(state):                State<AppState>,
+    AuthenticatedUser(claims):   AuthenticatedUser,
+    Json(request):               Json<TotpConfirmRequest>,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let email      = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    let enrollment = match state.totp_store.read().await.get_enrollment(&email).await {
+        Ok(enrollment)                   => enrollment,
+        Err(TotpStoreError::NotEnrolled) => return Err(AuthAPIError::TotpNotEnrolled),
+        Err(e)                           => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if enrollment.confirmed {
+        return Err(AuthAPIError::TotpAlreadyEnrolled);
+    }
+
+    let now  = Utc::now().timestamp() as u64;
+    let step = totp::verify_code(enrollment.secret.expose_secret(), &request.code, now)
+        .ok_or(AuthAPIError::IncorrectCredentials)?;
+    match state.totp_store.write().await.confirm_enrollment(&email, step).await {
+        Ok(())                           => info!("Authenticator enrolled."),
+        Err(TotpStoreError::NotEnrolled) => return Err(AuthAPIError::TotpNotEnrolled),
+        Err(e)                           => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    let recovery_codes = issue_recovery_codes(&email, &state).await?;
+    Ok(Json(RecoveryCodesResponse {recovery_codes}))
+}
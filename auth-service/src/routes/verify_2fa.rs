@@ -7,8 +7,12 @@ use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 use crate::app_state::AppState;
-use crate::domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode};
-use crate::utils::auth::generate_auth_cookie;
+use crate::domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode, TwoFACodeHash};
+use crate::routes::extractors::ClientInfo;
+use crate::routes::second_factor::{confirmed_totp_secret, record_failure, redeem_recovery_code, redeem_totp_code};
+use crate::routes::session_helpers::{check_pending_2fa_cookie, remove_pending_2fa_cookie, start_session};
+use crate::utils::constants::TWO_FA_CODE_HASHER;
+use crate::utils::recovery_codes;
 
 #[derive(Deserialize, Debug, Serialize)]
 pub struct Verify2FARequest {
@@ -17,22 +21,32 @@ pub struct Verify2FARequest {
    pub login_attempt_id: String,
    #[serde(rename = "2FACode")]
    pub code:             String,
+   #[serde(default, rename = "rememberMe")]
+   pub remember_me:      bool,
 }
 
 /*
-// This is what the request looks like in Json
+// This is what the request looks like in Json. A recovery code ("abcde-fghjk") may take the place of the 2FACode
 {
   "email":          "user@example.com",
   "loginAttemptId": "b59d20f4-dfcf-4fc5-af99-e312e0e2d2aa",
-  "2FACode":        "123456"
+  "2FACode":        "123456",
+  "rememberMe":     false          (optional)
 }
 */
 
+// What the user answered the second factor challenge with.
+//
+enum SecondFactor {
+   Code(TwoFACode),
+   RecoveryCode(String),
+}
 
 #[tracing::instrument(name = "verify 2fa", skip_all)]
 pub async fn verify_2fa(
    State(state):  State<AppState>,
    jar:           CookieJar,
+   client:        ClientInfo,
    Json(request): Json<Verify2FARequest>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError>
 {
@@ -40,41 +54,73 @@ pub async fn verify_2fa(
    let email       = Email::parse(email)              .map_err(|_| AuthAPIError::InvalidCredentials)?;
    let attempt_id  = request.login_attempt_id;
    let attempt_id  = LoginAttemptId::parse(attempt_id).map_err(|_| AuthAPIError::InvalidCredentials)?;
-   let two_fa_code = TwoFACode::parse(request.code)   .map_err(|_| AuthAPIError::InvalidCredentials)?;
-   let tuple       = get_tuple_from_store(&state, &email).await?;
+   let answer      = match recovery_codes::normalize(&request.code) {
+      Some(code) => SecondFactor::RecoveryCode(code),
+      None       => SecondFactor::Code(TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidCredentials)?),
+   };
+   check_pending_2fa_cookie(&jar, &email, &attempt_id)?;
+   let tuple       = get_tuple_from_store(&state, &attempt_id).await?;
 
    // Verify the user's post contains the information we have in the store.
-   if !attempt_id .is_match(&tuple.0)  { return Err(AuthAPIError::IncorrectCredentials); }
-   if !two_fa_code.is_match(&tuple.1)  { return Err(AuthAPIError::IncorrectCredentials); }
+   // Every wrong answer to the login attempt counts against it.
+   if email != tuple.0 { return Err(AuthAPIError::IncorrectCredentials); }
+   match check_answer(&state, &email, &attempt_id, answer, &tuple.1).await {
+      Ok(())                                  => {},
+      Err(AuthAPIError::IncorrectCredentials) => return Err(record_failure(&email, &attempt_id, &state).await),
+      Err(e)                                  => return Err(e),
+   }
 
    debug!("Credentials verified");
    
    // Verified.
-   // * Remove the entry from the store
-   // * Generate a new auth cookie
+   // * Remove the user's pending login attempts from the store, this one and any other
+   // * Start a session with a new auth cookie and a refresh cookie, in place of the pending 2FA cookie
    // * Return success result
    //
    remove_entry_from_store(&state, &email).await?;
-   let auth_cookie = match generate_auth_cookie(&email) {
-      Ok(cookie) => cookie,
-      Err(e)     => return Err(AuthAPIError::UnexpectedError(e)),
-   };
+   let (auth_cookie, refresh_cookie) = start_session(&email, client, request.remember_me, &state)
+      .await
+      .map_err(AuthAPIError::UnexpectedError)?;
 
    debug!("Adding to cookie jar");
-   let cookies = jar.add(auth_cookie);
+   let cookies = remove_pending_2fa_cookie(jar).add(auth_cookie).add(refresh_cookie);
    Ok((cookies, StatusCode::OK.into_response()))
 }
 
+// Users with an authenticator app answer with a code from the app, everyone else with the emailed code.
+// Anyone who lost their second factor can answer with one of their recovery codes instead.
+// The store only has the hash of the emailed code, so the answer is hashed the same way.
+//
+async fn check_answer(
+   state:      &AppState,
+   email:      &Email,
+   attempt_id: &LoginAttemptId,
+   answer:     SecondFactor,
+   emailed:    &TwoFACodeHash,
+) -> Result<(), AuthAPIError>
+{
+   match answer {
+      SecondFactor::Code(code) => match confirmed_totp_secret(email, state).await? {
+         Some(secret) => redeem_totp_code(email, &secret, code.as_ref(), state).await,
+         None         => match TWO_FA_CODE_HASHER.hash(&code, attempt_id.as_ref()).is_match(emailed) {
+            true  => Ok(()),
+            false => Err(AuthAPIError::IncorrectCredentials),
+         },
+      },
+      SecondFactor::RecoveryCode(code) => redeem_recovery_code(email, &code, state).await,
+   }
+}
+
 // This helper function ensures that our read lock is dropped as soon as we've completed the read
 //
 #[tracing::instrument(name = "get tuple from store", skip_all)]
 async fn get_tuple_from_store(
-   state: &AppState,
-   email: &Email,
-) -> Result<(LoginAttemptId, TwoFACode), AuthAPIError>
+   state:      &AppState,
+   attempt_id: &LoginAttemptId,
+) -> Result<(Email, TwoFACodeHash), AuthAPIError>
 {
    let two_fa_code_store = state.two_fa_code_store.read().await;
-   let raw               = two_fa_code_store.get_code(email).await;
+   let raw               = two_fa_code_store.get_code(attempt_id).await;
    raw.map_err(|_| AuthAPIError::IncorrectCredentials)
 }
 
@@ -85,6 +131,6 @@ async fn remove_entry_from_store(
 ) -> Result<(), AuthAPIError>
 {
    let mut two_fa_code_store = state.two_fa_code_store.write().await;
-   let raw                   = two_fa_code_store.remove_code(email).await;
+   let raw                   = two_fa_code_store.remove_codes(email).await;
    raw.map_err(|_| AuthAPIError::IncorrectCredentials)
 }
@@ -0,0 +1,50 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, UserStoreError};
+use crate::routes::session_helpers::subject_email;
+use crate::utils::audit::{self, AuditEvent};
+use crate::utils::auth::validate_email_verification_token;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+#[derive(Deserialize, Debug)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
+
+// The link in the verification email lands here. It activates the account, so the user can log in,
+// and is then used up: opening it again is refused.
+//
+#[tracing::instrument(name = "verify email", skip_all)]
+pub async fn verify_email(
+    State(state):  State<AppState>,
+    Query(query):  Query<VerifyEmailQuery>,
+    ) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = validate_email_verification_token(&query.token, state.banned_tokens.clone()).await
+        .map_err(|e| {
+            warn!("Email verification token is invalid: {:?}", e);
+            AuthAPIError::InvalidToken
+        })?;
+    let email  = subject_email(&claims).map_err(AuthAPIError::UnexpectedError)?;
+    match state.user_store.write().await.set_email_verified(&email, true).await {
+        Ok(())                             => {},
+        Err(UserStoreError::UserNotFound)  => return Err(AuthAPIError::InvalidToken),
+        Err(e)                             => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    state.banned_tokens.write().await
+        .add_token(&claims.jti, claims.exp).await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    audit::emit(AuditEvent::EmailVerified {email: &email});
+    info!("Email address verified.");
+    let message = "Email address verified".to_owned();
+    Ok((StatusCode::OK, Json(VerifyEmailResponse {message})))
+}
@@ -1,10 +1,16 @@
 
 pub mod data_stores;
 pub mod mock_email_client;
+pub mod mock_sms_client;
 pub mod postmark_email_client;
+pub mod twilio_sms_client;
 
 #[cfg(test)]
 mod mock_email_client_tests;
 #[cfg(test)]
+mod mock_sms_client_tests;
+#[cfg(test)]
 mod postmark_email_client_tests;
+#[cfg(test)]
+mod twilio_sms_client_tests;
 mod config;
@@ -1,15 +1,34 @@
 pub mod hashmap_2fa_code_store;
 pub mod hashset_token_store;
+pub mod hashmap_password_reset_token_store;
+pub mod hashmap_refresh_token_store;
 pub mod hashmap_user_store;
+pub mod postgres_2fa_code_store;
+pub mod postgres_banned_token_store;
+pub mod postgres_email_change_store;
+pub mod postgres_passkey_store;
+pub mod postgres_phone_store;
+pub mod postgres_purge;
+pub mod postgres_recovery_code_store;
+pub mod postgres_session_store;
+pub mod postgres_totp_store;
 pub mod postgres_user_store;
 pub mod redis_banned_token_store;
+pub mod redis_password_reset_token_store;
 pub mod redis_2fa_code_store;
+pub mod redis_refresh_token_store;
+pub mod redis_session_store;
+pub mod redis_webauthn_challenge_store;
 
 #[cfg(test)]
 mod hashmap_2fa_code_store_tests;
 #[cfg(test)]
 mod hashset_token_store_tests;
 #[cfg(test)]
+mod hashmap_password_reset_token_store_tests;
+#[cfg(test)]
+mod hashmap_refresh_token_store_tests;
+#[cfg(test)]
 mod hashmap_user_store_tests;
 #[cfg(test)]
 mod postgres_user_store_tests;
@@ -1,47 +1,121 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use color_eyre::eyre::eyre;
 use crate::domain::{
-	data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+	data_stores::{LoginAttemptId, TwoFACodeHash, TwoFACodeStore, TwoFACodeStoreError},
 	email::Email,
 };
+use crate::utils::constants::TWO_FA_CODE_TTL_SECONDS;
+use tracing::debug;
 
-#[derive(Default)]
+// Everything we know about one pending login attempt.
+//
+struct PendingAttempt {
+	email:     Email,
+	code_hash: TwoFACodeHash,
+	failures:  u32,
+	resends:   Option<(u32, Instant)>,   // how many, and when the last one went out
+	expires:   Instant,
+}
+
+// Codes expire TWO_FA_CODE_TTL_SECONDS after they are issued, or resent. Expired attempts
+// are treated as missing, and dropped whenever a new one is added.
+//
 pub struct HashmapTwoFACodeStore {
-	codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+	attempts: HashMap<LoginAttemptId, PendingAttempt>,
+	ttl:      Duration,
 }
 
 impl HashmapTwoFACodeStore {
 	pub fn new() -> Self {
-		Self::default()
+		Self::with_ttl(Duration::from_secs(TWO_FA_CODE_TTL_SECONDS as u64))
+	}
+
+	pub fn with_ttl(ttl: Duration) -> Self {
+		Self {attempts: HashMap::new(), ttl}
+	}
+
+	fn live(&self, login_attempt_id: &LoginAttemptId) -> Option<&PendingAttempt> {
+		self.attempts.get(login_attempt_id).filter(|attempt| Instant::now() < attempt.expires)
+	}
+
+	fn live_mut(&mut self, login_attempt_id: &LoginAttemptId) -> Option<&mut PendingAttempt> {
+		self.attempts.get_mut(login_attempt_id).filter(|attempt| Instant::now() < attempt.expires)
 	}
 }
 
+impl Default for HashmapTwoFACodeStore {
+	fn default() -> Self { Self::new() }
+}
+
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
 	#[tracing::instrument(name = "add_code", skip_all)]
-	async fn add_code(&mut self, email: Email, login_attempt_id: LoginAttemptId, code: TwoFACode)
+	async fn add_code(&mut self, email: Email, login_attempt_id: LoginAttemptId, code_hash: TwoFACodeHash)
 		-> Result<(), TwoFACodeStoreError>
 	{
-		let value = (login_attempt_id, code);
-		println!("Adding value to store: {:?}", value);
-		self.codes.insert(email, value);
-		println!("Count                : {:?}", self.codes.len());
+		let now     = Instant::now();
+		self.attempts.retain(|_, attempt| now < attempt.expires);
+		let expires = now + self.ttl;
+		self.attempts.insert(login_attempt_id, PendingAttempt {email, code_hash, failures: 0, resends: None, expires});
+		debug!(count = self.attempts.len(), "Added code to store");
 		Ok(())
 	}
 
 	#[tracing::instrument(name = "remove_code", skip_all)]
-	async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-		match self.codes.remove(email) {
+	async fn remove_code(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+		match self.attempts.remove(login_attempt_id) {
 			Some(_) => Ok(()),
 			None    => Err(TwoFACodeStoreError::UnexpectedError(eyre!("No token to remove"))),
 		}
 	}
 
+	#[tracing::instrument(name = "remove_codes", skip_all)]
+	async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+		self.attempts.retain(|_, attempt| attempt.email != *email);
+		Ok(())
+	}
+
 	#[tracing::instrument(name = "get_code", skip_all)]
-	async fn get_code(&self, email: &Email) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-		match self.codes.get(email) {
-			Some(v) => Ok(v.clone()),
-			None    => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+	async fn get_code(&self, login_attempt_id: &LoginAttemptId) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError> {
+		match self.live(login_attempt_id) {
+			Some(attempt) => Ok((attempt.email.clone(), attempt.code_hash.clone())),
+			None          => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+		}
+	}
+
+	#[tracing::instrument(name = "record_failure", skip_all)]
+	async fn record_failure(&mut self, login_attempt_id: &LoginAttemptId) -> Result<u32, TwoFACodeStoreError> {
+		let attempt = self.live_mut(login_attempt_id).ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+		attempt.failures += 1;
+		Ok(attempt.failures)
+	}
+
+	#[tracing::instrument(name = "resend_code", skip_all)]
+	async fn resend_code(
+		&mut self,
+		login_attempt_id: &LoginAttemptId,
+		code_hash:        TwoFACodeHash,
+		cooldown_seconds: i64,
+		max_resends:      u32,
+	) -> Result<(), TwoFACodeStoreError> {
+		let ttl      = self.ttl;
+		let attempt  = self.live_mut(login_attempt_id).ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+		let cooldown = Duration::from_secs(cooldown_seconds.max(0) as u64);
+		let (resends, last) = match attempt.resends {
+			Some((count, last)) => (count, Some(last)),
+			None                => (0, None),
+		};
+		if resends >= max_resends {
+			return Err(TwoFACodeStoreError::ResendLimitReached);
 		}
+		if last.is_some_and(|last| last.elapsed() < cooldown) {
+			return Err(TwoFACodeStoreError::ResendTooSoon);
+		}
+		let now           = Instant::now();
+		attempt.resends   = Some((resends + 1, now));
+		attempt.code_hash = code_hash;
+		attempt.expires   = now + ttl;
+		Ok(())
 	}
 }
@@ -1,13 +1,18 @@
+use std::time::Duration;
 use secrecy::Secret;
-use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
+use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeHash, TwoFACodeStore, TwoFACodeStoreError};
 use crate::services::data_stores::hashmap_2fa_code_store::HashmapTwoFACodeStore;
+use crate::utils::code_hasher::CodeHasher;
+
+fn hash_of(code: TwoFACode) -> TwoFACodeHash {
+	CodeHasher::new(&[7u8; 32]).unwrap().hash(&code, "test")
+}
 
 #[tokio::test]
 async fn lookup_returns_not_found_for_empty_store() {
 	let store   = HashmapTwoFACodeStore::default();
-	let email   = Secret::new("a@b.com".to_owned());
-	let email   = Email::parse(email).unwrap();
-	let missing = store.get_code(&email).await;
+	let id      = LoginAttemptId::default();
+	let missing = store.get_code(&id).await;
 	match missing {
 		Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => { println!("Correctly failed to find code"); }
 		Err(e)                                           => { core::panic!("Unexpected error: {:?}", e); }
@@ -21,16 +26,16 @@ async fn lookup_after_adding_code_returns_code() {
 	let mut store  = HashmapTwoFACodeStore::default();
 	let email      = Secret::new("a@b.com".to_owned());
 	let email      = Email::parse(email).unwrap();
-	let code       = TwoFACode::default();
+	let code       = hash_of(TwoFACode::default());
 	let id         = LoginAttemptId::default();
 	let add_result = store.add_code(email.clone(), id.clone(), code.clone()).await;
 	assert!(add_result.is_ok());
 
-	let returned = store.get_code(&email).await;
+	let returned = store.get_code(&id).await;
 	println!("{:?}", returned);
 	assert!(returned.is_ok());
 	let returned = returned.unwrap();
-	assert!(id == returned.0);
+	assert!(email == returned.0);
 	assert_eq!(returned.1, code);
 }
 
@@ -39,13 +44,142 @@ async fn lookup_after_add_then_remove_returns_not_found() {
 	let mut store  = HashmapTwoFACodeStore::default();
 	let email      = Secret::new("a@b.com".to_owned());
 	let email      = Email::parse(email).unwrap();
-	let code       = TwoFACode::default();
+	let code       = hash_of(TwoFACode::default());
 	let id         = LoginAttemptId::default();
 	let add_result = store.add_code(email.clone(), id.clone(), code.clone()).await;
 	assert!(add_result.is_ok());
-	let del_result = store.remove_code(&email).await;
+	let del_result = store.remove_code(&id).await;
 	assert!(del_result.is_ok());
-	let returned   = store.get_code(&email).await;
+	let returned   = store.get_code(&id).await;
 	assert!(returned.is_err());
 //	assert_eq!(returned, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
 }
+
+#[tokio::test]
+async fn login_attempts_of_one_user_coexist() {
+	let mut store  = HashmapTwoFACodeStore::default();
+	let email      = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let phone      = LoginAttemptId::default();
+	let laptop     = LoginAttemptId::default();
+	let first      = hash_of(TwoFACode::parse("111111".to_owned()).unwrap());
+	let second     = hash_of(TwoFACode::parse("222222".to_owned()).unwrap());
+	store.add_code(email.clone(), phone.clone(),  first.clone()).await.unwrap();
+	store.add_code(email.clone(), laptop.clone(), second.clone()).await.unwrap();
+	assert_eq!(store.get_code(&phone).await.unwrap().1,  first);
+	assert_eq!(store.get_code(&laptop).await.unwrap().1, second);
+
+	store.remove_code(&laptop).await.unwrap();
+	assert!(store.get_code(&phone).await.is_ok(), "Removing one attempt must leave the other");
+}
+
+#[tokio::test]
+async fn remove_codes_drops_only_the_users_attempts() {
+	let mut store  = HashmapTwoFACodeStore::default();
+	let email      = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let other      = Email::parse(Secret::new("c@d.com".to_owned())).unwrap();
+	let phone      = LoginAttemptId::default();
+	let laptop     = LoginAttemptId::default();
+	let others     = LoginAttemptId::default();
+	store.add_code(email.clone(), phone.clone(),  hash_of(TwoFACode::default())).await.unwrap();
+	store.add_code(email.clone(), laptop.clone(), hash_of(TwoFACode::default())).await.unwrap();
+	store.add_code(other.clone(), others.clone(), hash_of(TwoFACode::default())).await.unwrap();
+
+	assert_eq!(store.remove_codes(&email).await, Ok(()));
+	assert_eq!(store.get_code(&phone).await,  Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+	assert_eq!(store.get_code(&laptop).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+	assert!(store.get_code(&others).await.is_ok());
+}
+
+#[tokio::test]
+async fn failures_are_counted_per_login_attempt() {
+	let mut store  = HashmapTwoFACodeStore::default();
+	let email      = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let first      = LoginAttemptId::default();
+	store.add_code(email.clone(), first.clone(), hash_of(TwoFACode::default())).await.unwrap();
+	assert_eq!(store.record_failure(&first).await, Ok(1));
+	assert_eq!(store.record_failure(&first).await, Ok(2));
+
+	let second     = LoginAttemptId::default();
+	store.add_code(email.clone(), second.clone(), hash_of(TwoFACode::default())).await.unwrap();
+	assert_eq!(store.record_failure(&second).await, Ok(1));
+	assert_eq!(store.record_failure(&first).await,  Ok(3));
+}
+
+#[tokio::test]
+async fn failures_cannot_be_recorded_without_a_code() {
+	let mut store  = HashmapTwoFACodeStore::default();
+	let result     = store.record_failure(&LoginAttemptId::default()).await;
+	assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+}
+
+#[tokio::test]
+async fn resend_replaces_the_code_and_keeps_the_failures() {
+	let mut store  = HashmapTwoFACodeStore::default();
+	let email      = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let id         = LoginAttemptId::default();
+	store.add_code(email.clone(), id.clone(), hash_of(TwoFACode::default())).await.unwrap();
+	store.record_failure(&id).await.unwrap();
+
+	let code       = hash_of(TwoFACode::parse("123456".to_owned()).unwrap());
+	assert_eq!(store.resend_code(&id, code.clone(), 0, 3).await, Ok(()));
+	let (stored_email, stored_code) = store.get_code(&id).await.unwrap();
+	assert!(stored_email == email);
+	assert_eq!(stored_code, code);
+	assert_eq!(store.record_failure(&id).await, Ok(2));
+}
+
+#[tokio::test]
+async fn resend_is_refused_within_the_cooldown() {
+	let mut store  = HashmapTwoFACodeStore::default();
+	let email      = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let id         = LoginAttemptId::default();
+	store.add_code(email.clone(), id.clone(), hash_of(TwoFACode::default())).await.unwrap();
+	assert_eq!(store.resend_code(&id, hash_of(TwoFACode::default()), 60, 3).await, Ok(()));
+	assert_eq!(store.resend_code(&id, hash_of(TwoFACode::default()), 60, 3).await, Err(TwoFACodeStoreError::ResendTooSoon));
+}
+
+#[tokio::test]
+async fn resend_is_refused_once_the_limit_is_reached() {
+	let mut store  = HashmapTwoFACodeStore::default();
+	let email      = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let id         = LoginAttemptId::default();
+	store.add_code(email.clone(), id.clone(), hash_of(TwoFACode::default())).await.unwrap();
+	assert_eq!(store.resend_code(&id, hash_of(TwoFACode::default()), 0, 2).await, Ok(()));
+	assert_eq!(store.resend_code(&id, hash_of(TwoFACode::default()), 0, 2).await, Ok(()));
+	assert_eq!(store.resend_code(&id, hash_of(TwoFACode::default()), 0, 2).await, Err(TwoFACodeStoreError::ResendLimitReached));
+
+	// A new login attempt starts over
+	let second     = LoginAttemptId::default();
+	store.add_code(email.clone(), second.clone(), hash_of(TwoFACode::default())).await.unwrap();
+	assert_eq!(store.resend_code(&second, hash_of(TwoFACode::default()), 0, 2).await, Ok(()));
+}
+
+#[tokio::test]
+async fn resend_needs_a_pending_login_attempt() {
+	let mut store  = HashmapTwoFACodeStore::default();
+	let email      = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	store.add_code(email.clone(), LoginAttemptId::default(), hash_of(TwoFACode::default())).await.unwrap();
+	let result     = store.resend_code(&LoginAttemptId::default(), hash_of(TwoFACode::default()), 0, 3).await;
+	assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+}
+
+#[tokio::test]
+async fn an_expired_login_attempt_is_not_found() {
+	let mut store  = HashmapTwoFACodeStore::with_ttl(Duration::ZERO);
+	let email      = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let id         = LoginAttemptId::default();
+	store.add_code(email.clone(), id.clone(), hash_of(TwoFACode::default())).await.unwrap();
+	assert_eq!(store.get_code(&id).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+	assert_eq!(store.record_failure(&id).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+	assert_eq!(store.resend_code(&id, hash_of(TwoFACode::default()), 0, 3).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+}
+
+#[tokio::test]
+async fn adding_a_code_drops_expired_login_attempts() {
+	let mut store  = HashmapTwoFACodeStore::with_ttl(Duration::ZERO);
+	let email      = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let abandoned  = LoginAttemptId::default();
+	store.add_code(email.clone(), abandoned.clone(), hash_of(TwoFACode::default())).await.unwrap();
+	store.add_code(email.clone(), LoginAttemptId::default(), hash_of(TwoFACode::default())).await.unwrap();
+	assert!(store.remove_code(&abandoned).await.is_err(), "The abandoned attempt must be gone from memory");
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use crate::domain::{Email, PasswordResetTokenStore, PasswordResetTokenStoreError};
+use crate::utils::constants::PASSWORD_RESET_TTL_SECONDS;
+
+pub struct HashmapPasswordResetTokenStore {
+	tokens: HashMap<String, (Email, Instant)>,   // token digest -> (email, expiry)
+	ttl:    Duration,
+}
+
+impl HashmapPasswordResetTokenStore {
+	pub fn new() -> Self {
+		Self::with_ttl(Duration::from_secs(PASSWORD_RESET_TTL_SECONDS as u64))
+	}
+
+	pub fn with_ttl(ttl: Duration) -> Self {
+		Self {tokens: HashMap::new(), ttl}
+	}
+}
+
+impl Default for HashmapPasswordResetTokenStore {
+	fn default() -> Self { Self::new() }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+	#[tracing::instrument(name = "add password reset token", skip_all)]
+	async fn add_token(&mut self, token: &Secret<String>, email: Email) -> Result<(), PasswordResetTokenStoreError> {
+		self.tokens.retain(|_, (owner, _)| *owner != email);
+		let expires = Instant::now() + self.ttl;
+		self.tokens.insert(make_digest(token), (email, expires));
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "take password reset token", skip_all)]
+	async fn take_token(&mut self, token: &Secret<String>) -> Result<Email, PasswordResetTokenStoreError> {
+		match self.tokens.remove(&make_digest(token)) {
+			Some((email, expires)) if Instant::now() < expires => Ok(email),
+			_                                                  => Err(PasswordResetTokenStoreError::TokenNotFound),
+		}
+	}
+}
+
+fn make_digest(token: &Secret<String>) -> String {
+	format!("{:x}", Sha256::digest(token.expose_secret().as_bytes()))
+}
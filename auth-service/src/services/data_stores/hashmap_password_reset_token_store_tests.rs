@@ -0,0 +1,55 @@
+use std::time::Duration;
+use secrecy::Secret;
+use crate::domain::{Email, PasswordResetTokenStore, PasswordResetTokenStoreError};
+use crate::services::data_stores::hashmap_password_reset_token_store::HashmapPasswordResetTokenStore;
+
+fn email(address: &str) -> Email {
+	Email::parse(Secret::new(address.to_owned())).unwrap()
+}
+
+fn token(value: &str) -> Secret<String> {
+	Secret::new(value.to_owned())
+}
+
+#[tokio::test]
+async fn taking_an_unknown_token_returns_not_found() {
+	let mut store = HashmapPasswordResetTokenStore::new();
+	let result    = store.take_token(&token("unknown")).await;
+	assert_eq!(result.err(), Some(PasswordResetTokenStoreError::TokenNotFound));
+}
+
+#[tokio::test]
+async fn taking_a_stored_token_returns_its_email() {
+	let mut store = HashmapPasswordResetTokenStore::new();
+	store.add_token(&token("token-1"), email("a@b.com")).await.unwrap();
+	let returned  = store.take_token(&token("token-1")).await.unwrap();
+	assert_eq!(returned, email("a@b.com"));
+}
+
+#[tokio::test]
+async fn a_token_can_only_be_taken_once() {
+	let mut store = HashmapPasswordResetTokenStore::new();
+	store.add_token(&token("token-1"), email("a@b.com")).await.unwrap();
+	store.take_token(&token("token-1")).await.unwrap();
+	let second    = store.take_token(&token("token-1")).await;
+	assert_eq!(second.err(), Some(PasswordResetTokenStoreError::TokenNotFound));
+}
+
+#[tokio::test]
+async fn a_new_token_replaces_the_users_previous_one() {
+	let mut store = HashmapPasswordResetTokenStore::new();
+	store.add_token(&token("token-1"), email("a@b.com")).await.unwrap();
+	store.add_token(&token("token-2"), email("c@d.com")).await.unwrap();
+	store.add_token(&token("token-3"), email("a@b.com")).await.unwrap();
+	assert_eq!(store.take_token(&token("token-1")).await.err(), Some(PasswordResetTokenStoreError::TokenNotFound));
+	assert_eq!(store.take_token(&token("token-2")).await.unwrap(), email("c@d.com"));
+	assert_eq!(store.take_token(&token("token-3")).await.unwrap(), email("a@b.com"));
+}
+
+#[tokio::test]
+async fn an_expired_token_is_not_found() {
+	let mut store = HashmapPasswordResetTokenStore::with_ttl(Duration::ZERO);
+	store.add_token(&token("token-1"), email("a@b.com")).await.unwrap();
+	let result    = store.take_token(&token("token-1")).await;
+	assert_eq!(result.err(), Some(PasswordResetTokenStoreError::TokenNotFound));
+}
@@ -0,0 +1,49 @@
+use std::collections::{HashMap, HashSet};
+use secrecy::{ExposeSecret, Secret};
+use crate::domain::{RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError};
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+	tokens:           HashMap<String, (RefreshTokenRecord, bool)>,   // token -> (record, used)
+	revoked_families: HashSet<String>,
+}
+
+impl HashmapRefreshTokenStore {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+	#[tracing::instrument(name = "add refresh token", skip_all)]
+	async fn add_token(&mut self, token: &Secret<String>, record: RefreshTokenRecord) -> Result<(), RefreshTokenStoreError> {
+		let key = token.expose_secret().to_owned();
+		self.tokens.insert(key, (record, false));
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "consume refresh token", skip_all)]
+	async fn consume_token(&mut self, token: &Secret<String>) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+		let (record, used) = match self.tokens.get_mut(token.expose_secret()) {
+			Some(entry) => entry,
+			None        => return Err(RefreshTokenStoreError::TokenNotFound),
+		};
+		if self.revoked_families.contains(&record.family_id) {
+			return Err(RefreshTokenStoreError::FamilyRevoked);
+		}
+		if *used {
+			let family_id = record.family_id.clone();
+			self.revoked_families.insert(family_id);
+			return Err(RefreshTokenStoreError::TokenReused);
+		}
+		*used = true;
+		Ok(record.clone())
+	}
+
+	#[tracing::instrument(name = "revoke refresh token family", skip_all)]
+	async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+		self.revoked_families.insert(family_id.to_owned());
+		Ok(())
+	}
+}
@@ -0,0 +1,67 @@
+use secrecy::Secret;
+use crate::domain::{Email, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError};
+use crate::services::data_stores::hashmap_refresh_token_store::HashmapRefreshTokenStore;
+
+fn record(family_id: &str) -> RefreshTokenRecord {
+	let email = Secret::new("a@b.com".to_owned());
+	let email = Email::parse(email).unwrap();
+	RefreshTokenRecord::new(email, family_id.to_owned(), false)
+}
+
+#[tokio::test]
+async fn consuming_an_unknown_token_returns_not_found() {
+	let mut store = HashmapRefreshTokenStore::new();
+	let token     = Secret::new("unknown".to_owned());
+	let result    = store.consume_token(&token).await;
+	assert_eq!(result.err(), Some(RefreshTokenStoreError::TokenNotFound));
+}
+
+#[tokio::test]
+async fn consuming_a_stored_token_returns_its_record() {
+	let mut store = HashmapRefreshTokenStore::new();
+	let token     = Secret::new("token-1".to_owned());
+	let expected  = record("family-1");
+	store.add_token(&token, expected.clone()).await.unwrap();
+	let returned  = store.consume_token(&token).await.unwrap();
+	assert_eq!(returned, expected);
+}
+
+#[tokio::test]
+async fn consuming_a_token_twice_is_reported_as_reuse() {
+	let mut store = HashmapRefreshTokenStore::new();
+	let token     = Secret::new("token-1".to_owned());
+	store.add_token(&token, record("family-1")).await.unwrap();
+	let first     = store.consume_token(&token).await;
+	let second    = store.consume_token(&token).await;
+	assert!(first.is_ok());
+	assert_eq!(second.err(), Some(RefreshTokenStoreError::TokenReused));
+}
+
+#[tokio::test]
+async fn reuse_revokes_every_token_in_the_family() {
+	let mut store = HashmapRefreshTokenStore::new();
+	let original  = Secret::new("token-1".to_owned());
+	let rotated   = Secret::new("token-2".to_owned());
+	let unrelated = Secret::new("token-3".to_owned());
+	store.add_token(&original,  record("family-1")).await.unwrap();
+	store.add_token(&unrelated, record("family-2")).await.unwrap();
+	store.consume_token(&original).await.unwrap();
+	store.add_token(&rotated,   record("family-1")).await.unwrap();
+
+	let reuse     = store.consume_token(&original).await;     // Replay of the rotated-out token
+	let revoked   = store.consume_token(&rotated).await;
+	let untouched = store.consume_token(&unrelated).await;
+	assert_eq!(reuse.err(),   Some(RefreshTokenStoreError::TokenReused));
+	assert_eq!(revoked.err(), Some(RefreshTokenStoreError::FamilyRevoked));
+	assert!(untouched.is_ok());
+}
+
+#[tokio::test]
+async fn tokens_of_a_revoked_family_are_rejected() {
+	let mut store = HashmapRefreshTokenStore::new();
+	let token     = Secret::new("token-1".to_owned());
+	store.add_token(&token, record("family-1")).await.unwrap();
+	store.revoke_family("family-1").await.unwrap();
+	let result    = store.consume_token(&token).await;
+	assert_eq!(result.err(), Some(RefreshTokenStoreError::FamilyRevoked));
+}
@@ -1,14 +1,17 @@
 pub use crate::domain::data_stores::UserStore;
 pub use crate::domain::data_stores::UserStoreError;
+use crate::domain::data_stores::AccountTombstone;
 use crate::domain::email::Email;
 use crate::domain::password::Password;
 use crate::domain::user::User;
 use std::collections::HashMap;
+use uuid::Uuid;
 
 #[derive(Default)]
 pub struct HashmapUserStore 
 {
-    users: HashMap<Email, User>,
+    users:      HashMap<Email, User>,
+    tombstones: HashMap<Uuid, AccountTombstone>,
 }
 
 impl HashmapUserStore {
@@ -42,4 +45,43 @@ impl UserStore for HashmapUserStore {
             None                                     => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.requires_2fa = requires_2fa;
+        Ok(())
+    }
+
+    async fn set_email_verified(&mut self, email: &Email, verified: bool) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.email_verified = verified;
+        Ok(())
+    }
+
+    async fn set_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.password = password;
+        Ok(())
+    }
+
+    async fn change_email(&mut self, email: &Email, new_email: &Email) -> Result<(), UserStoreError> {
+        if self.users.contains_key(new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let mut user = self.users.remove(email).ok_or(UserStoreError::UserNotFound)?;
+        user.email   = new_email.clone();
+        self.users.insert(new_email.clone(), user);
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<AccountTombstone, UserStoreError> {
+        self.users.remove(email).ok_or(UserStoreError::UserNotFound)?;
+        let tombstone = AccountTombstone::new();
+        self.tombstones.insert(tombstone.id, tombstone.clone());
+        Ok(tombstone)
+    }
+
+    async fn get_tombstone(&self, id: &Uuid) -> Result<AccountTombstone, UserStoreError> {
+        self.tombstones.get(id).cloned().ok_or(UserStoreError::TombstoneNotFound)
+    }
 }
@@ -68,6 +68,93 @@ async fn test_validate_user() {
    }
 }
 
+#[tokio::test]
+async fn test_set_requires_2fa() {
+   let (email, mut store) = construct_store_with_test_user().await;
+   assert_eq!(store.set_requires_2fa(&email, true).await, Ok(()));
+   assert!(store.get_user(&email).await.unwrap().requires_2fa);
+   assert_eq!(store.set_requires_2fa(&email, false).await, Ok(()));
+   assert!(!store.get_user(&email).await.unwrap().requires_2fa);
+}
+
+#[tokio::test]
+async fn test_set_requires_2fa_for_missing_user_fails() {
+   let mut store = HashmapUserStore::default();
+   let email     = Email::parse(Secret::new("somebody@missing.com".to_owned())).unwrap();
+   let result    = store.set_requires_2fa(&email, true).await;
+   assert_eq!(result, Err(UserStoreError::UserNotFound));
+}
+
+#[tokio::test]
+async fn test_new_users_start_unverified() {
+   let (email, mut store) = construct_store_with_test_user().await;
+   assert!(!store.get_user(&email).await.unwrap().email_verified);
+   assert_eq!(store.set_email_verified(&email, true).await, Ok(()));
+   assert!(store.get_user(&email).await.unwrap().email_verified);
+}
+
+#[tokio::test]
+async fn test_set_email_verified_for_missing_user_fails() {
+   let mut store = HashmapUserStore::default();
+   let email     = Email::parse(Secret::new("somebody@missing.com".to_owned())).unwrap();
+   let result    = store.set_email_verified(&email, true).await;
+   assert_eq!(result, Err(UserStoreError::UserNotFound));
+}
+
+#[tokio::test]
+async fn test_set_password_replaces_the_old_one() {
+   let (email, mut store) = construct_store_with_test_user().await;
+   let old       = Password::parse(Secret::new("Horse1234!".to_owned())).unwrap();
+   let new       = Password::parse(Secret::new("Battery5678!".to_owned())).unwrap();
+   assert_eq!(store.set_password(&email, new.clone()).await, Ok(()));
+   assert_eq!(store.validate_user(&email, &new).await, Ok(()));
+   assert_eq!(store.validate_user(&email, &old).await, Err(UserStoreError::InvalidCredentials));
+}
+
+#[tokio::test]
+async fn test_set_password_for_missing_user_fails() {
+   let mut store = HashmapUserStore::default();
+   let email     = Email::parse(Secret::new("somebody@missing.com".to_owned())).unwrap();
+   let password  = Password::parse(Secret::new("Battery5678!".to_owned())).unwrap();
+   let result    = store.set_password(&email, password).await;
+   assert_eq!(result, Err(UserStoreError::UserNotFound));
+}
+
+#[tokio::test]
+async fn test_change_email_moves_the_user() {
+   let (email, mut store) = construct_store_with_test_user().await;
+   let new_email = Email::parse(Secret::new("joe@new.io".to_owned())).unwrap();
+   assert_eq!(store.change_email(&email, &new_email).await, Ok(()));
+   assert_eq!(store.get_user(&email).await.err(), Some(UserStoreError::UserNotFound));
+   assert_eq!(store.get_user(&new_email).await.unwrap().email, new_email);
+}
+
+#[tokio::test]
+async fn test_change_email_to_a_taken_address_fails() {
+   let (email, mut store) = construct_store_with_test_user().await;
+   let taken     = Email::parse(Secret::new("jane@boo.io".to_owned())).unwrap();
+   let password  = Password::parse(Secret::new("Horse1234!".to_owned())).unwrap();
+   store.add_user(User::new(taken.clone(), password, false)).await.unwrap();
+   assert_eq!(store.change_email(&email, &taken).await, Err(UserStoreError::UserAlreadyExists));
+   assert!(store.get_user(&email).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_delete_user_removes_the_user() {
+   let (email, mut store) = construct_store_with_test_user().await;
+   assert!(store.delete_user(&email).await.is_ok());
+   assert_eq!(store.get_user(&email).await.err(), Some(UserStoreError::UserNotFound));
+   assert_eq!(store.delete_user(&email).await.err(), Some(UserStoreError::UserNotFound));
+}
+
+#[tokio::test]
+async fn test_delete_user_leaves_a_tombstone() {
+   let (email, mut store) = construct_store_with_test_user().await;
+   let tombstone          = store.delete_user(&email).await.unwrap();
+   assert_eq!(store.get_tombstone(&tombstone.id).await, Ok(tombstone.clone()));
+   assert_eq!(store.get_tombstone(&uuid::Uuid::new_v4()).await.err(), Some(UserStoreError::TombstoneNotFound));
+}
+
 async fn construct_store_with_test_user<'a>() -> (Email, HashmapUserStore) {
    let mut store = HashmapUserStore::default();
    let email   = Secret::new("joe@boo.io".to_owned());
@@ -1,45 +1,71 @@
 use crate::domain::TokenStoreError::BlankToken;
 use crate::domain::{TokenStore, TokenStoreError};
-use secrecy::{ExposeSecret, Secret};
-use std::collections::HashSet;
+use chrono::Utc;
+use std::collections::HashMap;
 
+// Maps each revoked jti to the expiry of its token. Expired entries are ignored,
+// and dropped whenever the store is modified.
+//
 #[derive(Default)]
 pub struct HashSetTokenStore
 {
-	tokens: HashSet<String>,
+	tokens:     HashMap<String, usize>,
+	not_before: HashMap<String, usize>,      // subject -> epoch
 }
 
 impl HashSetTokenStore {
 	pub fn new() -> Self {
 		Self::default()
 	}
+
+	fn purge_expired(&mut self) {
+		let now = now();
+		self.tokens.retain(|_, exp| *exp > now);
+	}
 }
 
 #[async_trait::async_trait]
 impl TokenStore for HashSetTokenStore {
-	async fn add_token(&mut self, token: &Secret<String>) -> Result<(), TokenStoreError> {
-		let secret = token.expose_secret();
-		let copy   = secret.clone();
-		if secret.is_empty() { Err(BlankToken)                   }
-		else                { self.tokens.insert(copy); Ok(()) }
+	async fn add_token(&mut self, jti: &str, exp: usize) -> Result<(), TokenStoreError> {
+		if jti.is_empty() { return Err(BlankToken) }
+		self.purge_expired();
+		if exp > now()    { self.tokens.insert(jti.to_owned(), exp); }
+		Ok(())
 	}
 
 	async fn clear(&mut self) -> Result<(), TokenStoreError> {
 		self.tokens.clear();
+		self.not_before.clear();
 		Ok(())
 	}
 	
 	async fn count(&self) -> Result<u64, TokenStoreError> {
-		Ok(self.tokens.len() as u64)
+		let now   = now();
+		let count = self.tokens.values().filter(|exp| **exp > now).count();
+		Ok(count as u64)
 	}	
-	async fn delete_token(&mut self, token: &Secret<String>) -> Result<(), TokenStoreError> {
-		let token = token.expose_secret();
-		if token.is_empty() { Err(BlankToken)                   }
-		else                { self.tokens.remove(token); Ok(()) }
+	async fn delete_token(&mut self, jti: &str) -> Result<(), TokenStoreError> {
+		if jti.is_empty() { return Err(BlankToken) }
+		self.purge_expired();
+		self.tokens.remove(jti);
+		Ok(())
 	}
 	
-	async fn contains_token(&self, token: &Secret<String>) -> bool {
-		let token = token.expose_secret();
-		self.tokens.contains(token)
+	async fn contains_token(&self, jti: &str) -> Result<bool, TokenStoreError> {
+		Ok(self.tokens.get(jti).is_some_and(|exp| *exp > now()))
 	}
+
+	async fn set_not_before(&mut self, subject: &str, epoch: usize) -> Result<(), TokenStoreError> {
+		if subject.is_empty() { return Err(BlankToken) }
+		self.not_before.insert(subject.to_owned(), epoch);
+		Ok(())
+	}
+
+	async fn get_not_before(&self, subject: &str) -> Result<Option<usize>, TokenStoreError> {
+		Ok(self.not_before.get(subject).copied())
+	}
+}
+
+fn now() -> usize {
+	Utc::now().timestamp() as usize
 }
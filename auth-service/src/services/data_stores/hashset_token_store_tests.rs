@@ -1,8 +1,16 @@
 use crate::domain::{TokenStore};
 use crate::domain::TokenStoreError::BlankToken;
 use crate::services::data_stores::hashset_token_store::HashSetTokenStore;
+use chrono::Utc;
 use fake::{Fake, faker::internet::en::Password};
-use secrecy::Secret;
+
+fn in_an_hour() -> usize {
+	(Utc::now().timestamp() + 3600) as usize
+}
+
+fn an_hour_ago() -> usize {
+	(Utc::now().timestamp() - 3600) as usize
+}
 
 #[tokio::test]
 async fn test_new_token_store_has_no_tokens() {
@@ -14,9 +22,7 @@ async fn test_new_token_store_has_no_tokens() {
 #[tokio::test]
 async fn test_storing_an_empty_token_is_an_error() {
 	let mut store = HashSetTokenStore::new();
-	let empty     = "".to_string();
-	let empty     = Secret::new(empty);
-	let error     = store.add_token(&empty).await.err().unwrap();
+	let error     = store.add_token("", in_an_hour()).await.err().unwrap();
 	assert_eq!(error, BlankToken);
 }
 
@@ -24,19 +30,17 @@ async fn test_storing_an_empty_token_is_an_error() {
 async fn test_random_token_is_not_found_in_empty_store() {
 	let store  = HashSetTokenStore::new();
 	let rando  = Password(12..16).fake::<String>().to_owned();
-	let rando     = Secret::new(rando);
-	let exists = store.contains_token(&rando).await;
+	let exists = store.contains_token(&rando).await.unwrap();
 	assert_eq!(exists, false);
 }
 
 #[tokio::test]
 async fn test_a_stored_token_exists() {
 	let mut store = HashSetTokenStore::new();
-	let token     = "Dingle".to_string();
-	let token     = Secret::new(token);
-	let _         = store.add_token(&token.clone()).await.unwrap();
+	let jti       = "Dingle";
+	let _         = store.add_token(jti, in_an_hour()).await.unwrap();
 	let count     = store.count().await.unwrap();
-	let exists    = store.contains_token(&token).await;
+	let exists    = store.contains_token(jti).await.unwrap();
 	assert_eq!(count, 1);
 	assert_eq!(exists, true);
 }
@@ -44,12 +48,11 @@ async fn test_a_stored_token_exists() {
 #[tokio::test]
 async fn storing_a_token_twice_does_not_increase_count() {
 	let mut store = HashSetTokenStore::new();
-	let token     = "Dingle".to_string();
-	let token     = Secret::new(token);
-	let _         = store.add_token(&token.clone()).await.unwrap();
-	let _         = store.add_token(&token.clone()).await.unwrap();
+	let jti       = "Dingle";
+	let _         = store.add_token(jti, in_an_hour()).await.unwrap();
+	let _         = store.add_token(jti, in_an_hour()).await.unwrap();
 	let count     = store.count().await.unwrap();
-	let exists    = store.contains_token(&token).await;
+	let exists    = store.contains_token(jti).await.unwrap();
 	assert_eq!(count, 1);
 	assert_eq!(exists, true);
 }
@@ -57,16 +60,44 @@ async fn storing_a_token_twice_does_not_increase_count() {
 #[tokio::test]
 async fn deleting_a_token_decreases_count() {
 	let mut store = HashSetTokenStore::new();
-	let token     = "Dingle".to_string();
-	let token     = Secret::new(token);
-	let _         = store.add_token(&token.clone()).await.unwrap();
-	let was_there = store.contains_token(&token).await;
+	let jti       = "Dingle";
+	let _         = store.add_token(jti, in_an_hour()).await.unwrap();
+	let was_there = store.contains_token(jti).await.unwrap();
 	let before    = store.count().await.unwrap();
-	let _         = store.delete_token(&token).await.unwrap();
+	let _         = store.delete_token(jti).await.unwrap();
 	let after     = store.count().await.unwrap();
-	let is_there  = store.contains_token(&token).await;
+	let is_there  = store.contains_token(jti).await.unwrap();
 	assert_eq!(before,       1);
 	assert_eq!(after,        0);
 	assert_eq!(was_there, true);
 	assert_eq!(is_there, false);
 }
+
+#[tokio::test]
+async fn an_already_expired_token_is_not_stored() {
+	let mut store = HashSetTokenStore::new();
+	let jti       = "Dingle";
+	let _         = store.add_token(jti, an_hour_ago()).await.unwrap();
+	let count     = store.count().await.unwrap();
+	let exists    = store.contains_token(jti).await.unwrap();
+	assert_eq!(count, 0);
+	assert_eq!(exists, false);
+}
+
+#[tokio::test]
+async fn a_subject_without_an_epoch_has_no_not_before() {
+	let store = HashSetTokenStore::new();
+	let epoch = store.get_not_before("a@b.com").await.unwrap();
+	assert_eq!(epoch, None);
+}
+
+#[tokio::test]
+async fn the_latest_not_before_epoch_wins() {
+	let mut store = HashSetTokenStore::new();
+	let _         = store.set_not_before("a@b.com", 100).await.unwrap();
+	let _         = store.set_not_before("a@b.com", 200).await.unwrap();
+	let epoch     = store.get_not_before("a@b.com").await.unwrap();
+	let other     = store.get_not_before("c@d.com").await.unwrap();
+	assert_eq!(epoch, Some(200));
+	assert_eq!(other, None);
+}
@@ -0,0 +1,171 @@
+use crate::domain::data_stores::{LoginAttemptId, TwoFACodeHash, TwoFACodeStore, TwoFACodeStoreError};
+use crate::domain::Email;
+use crate::utils::constants::TWO_FA_CODE_TTL_SECONDS;
+use chrono::{Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::Secret;
+use sqlx::PgPool;
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct TwoFACodeRecord {
+	pub email:     String,
+	pub code_hash: String,
+}
+
+// Codes expire TWO_FA_CODE_TTL_SECONDS after they are issued. Expired rows are ignored
+// by every query, and removed by `purge_expired` now and then.
+//
+pub struct PostgresTwoFACodeStore {
+	pool: PgPool,
+}
+
+impl PostgresTwoFACodeStore {
+	pub fn new(pool: PgPool) -> Self {
+		Self { pool }
+	}
+
+	#[tracing::instrument(name = "Purge expired 2FA codes from PostgreSQL", skip_all)]
+	pub async fn purge_expired(&self) -> Result<u64, TwoFACodeStoreError> {
+		let result = sqlx::query("DELETE FROM two_fa_codes WHERE expires_at <= NOW()")
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+		Ok(result.rows_affected())
+	}
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for PostgresTwoFACodeStore {
+	#[tracing::instrument(name = "Add 2FA code to PostgreSQL", skip_all)]
+	async fn add_code(
+		&mut self,
+		email:            Email,
+		login_attempt_id: LoginAttemptId,
+		code_hash:        TwoFACodeHash,
+	) -> Result<(), TwoFACodeStoreError> {
+		let expires_at = Utc::now() + Duration::seconds(TWO_FA_CODE_TTL_SECONDS);
+		sqlx::query(
+			r#"
+	        INSERT INTO two_fa_codes (login_attempt_id, email, code_hash, expires_at)
+	        VALUES ($1, $2, $3, $4)
+	        "#
+			)
+			.bind(login_attempt_id.as_ref())
+			.bind(email.expose_secret())
+			.bind(code_hash.as_ref())
+			.bind(expires_at)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "Remove 2FA code from PostgreSQL", skip_all)]
+	async fn remove_code(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+		sqlx::query("DELETE FROM two_fa_codes WHERE login_attempt_id = $1")
+			.bind(login_attempt_id.as_ref())
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "Remove user's 2FA codes from PostgreSQL", skip_all)]
+	async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+		sqlx::query("DELETE FROM two_fa_codes WHERE email = $1")
+			.bind(email.expose_secret())
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "Retrieve 2FA code from PostgreSQL", skip_all)]
+	async fn get_code(&self, login_attempt_id: &LoginAttemptId) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError> {
+		let record = sqlx::query_as::<_, TwoFACodeRecord>(
+			r#"
+	        SELECT email, code_hash
+	        FROM two_fa_codes
+	        WHERE login_attempt_id = $1 AND expires_at > NOW()
+	        "#
+			)
+			.bind(login_attempt_id.as_ref())
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+			.ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+		let email  = Email::parse(Secret::new(record.email)).map_err(|e| TwoFACodeStoreError::UnexpectedError(eyre!(e)))?;
+		let hash   = TwoFACodeHash::parse(record.code_hash).map_err(TwoFACodeStoreError::UnexpectedError)?;
+		Ok((email, hash))
+	}
+
+	#[tracing::instrument(name = "Record failed 2FA attempt in PostgreSQL", skip_all)]
+	async fn record_failure(&mut self, login_attempt_id: &LoginAttemptId) -> Result<u32, TwoFACodeStoreError> {
+		let failures = sqlx::query_scalar::<_, i32>(
+			r#"
+	        UPDATE two_fa_codes SET failed_attempts = failed_attempts + 1
+	        WHERE login_attempt_id = $1 AND expires_at > NOW()
+	        RETURNING failed_attempts
+	        "#
+			)
+			.bind(login_attempt_id.as_ref())
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+			.ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+		Ok(failures as u32)
+	}
+
+	// The update only goes through while the limits allow it. When it does not, look at the row to
+	// tell the caller why.
+	//
+	#[tracing::instrument(name = "Resend 2FA code in PostgreSQL", skip_all)]
+	async fn resend_code(
+		&mut self,
+		login_attempt_id: &LoginAttemptId,
+		code_hash:        TwoFACodeHash,
+		cooldown_seconds: i64,
+		max_resends:      u32,
+	) -> Result<(), TwoFACodeStoreError> {
+		let now        = Utc::now();
+		let expires_at = now + Duration::seconds(TWO_FA_CODE_TTL_SECONDS);
+		let resent_by  = now - Duration::seconds(cooldown_seconds);
+		let updated    = sqlx::query(
+			r#"
+	        UPDATE two_fa_codes
+	        SET code_hash = $2, expires_at = $3, resend_count = resend_count + 1, last_resent_at = $4
+	        WHERE login_attempt_id = $1 AND expires_at > NOW()
+	          AND resend_count < $5 AND (last_resent_at IS NULL OR last_resent_at <= $6)
+	        "#
+			)
+			.bind(login_attempt_id.as_ref())
+			.bind(code_hash.as_ref())
+			.bind(expires_at)
+			.bind(now)
+			.bind(max_resends as i32)
+			.bind(resent_by)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+		if updated.rows_affected() > 0 {
+			return Ok(());
+		}
+
+		let resends    = sqlx::query_scalar::<_, i32>(
+			r#"
+	        SELECT resend_count
+	        FROM two_fa_codes
+	        WHERE login_attempt_id = $1 AND expires_at > NOW()
+	        "#
+			)
+			.bind(login_attempt_id.as_ref())
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+			.ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+		match resends as u32 >= max_resends {
+			true  => Err(TwoFACodeStoreError::ResendLimitReached),
+			false => Err(TwoFACodeStoreError::ResendTooSoon),
+		}
+	}
+}
@@ -0,0 +1,133 @@
+use crate::domain::data_stores::{TokenStore, TokenStoreError};
+use crate::utils::constants::REFRESH_TOKEN_TTL_SECONDS;
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use sqlx::PgPool;
+use tracing::debug;
+
+// Expired rows are ignored by every query, and removed by `purge_expired` now and then.
+//
+pub struct PostgresBannedTokenStore {
+	pool: PgPool,
+}
+
+impl PostgresBannedTokenStore {
+	pub fn new(pool: PgPool) -> Self {
+		Self { pool }
+	}
+
+	#[tracing::instrument(name = "Purge expired tokens from PostgreSQL", skip_all)]
+	pub async fn purge_expired(&self) -> Result<u64, TokenStoreError> {
+		let tokens = sqlx::query("DELETE FROM banned_tokens WHERE expires_at <= NOW()")
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+		let epochs = sqlx::query("DELETE FROM token_not_before WHERE expires_at <= NOW()")
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+		Ok(tokens.rows_affected() + epochs.rows_affected())
+	}
+}
+
+#[async_trait::async_trait]
+impl TokenStore for PostgresBannedTokenStore {
+	#[tracing::instrument(name = "Add token to PostgreSQL", skip_all)]
+	async fn add_token(&mut self, jti: &str, exp: usize) -> Result<(), TokenStoreError> {
+		if jti.is_empty() { return Err(TokenStoreError::BlankToken) }
+		let expires_at = to_datetime(exp as i64)?;
+		if expires_at <= Utc::now() {
+			debug!("Token has already expired, nothing to ban");
+			return Ok(());
+		}
+		sqlx::query(
+			r#"
+	        INSERT INTO banned_tokens (jti, expires_at)
+	        VALUES ($1, $2)
+	        ON CONFLICT (jti) DO UPDATE SET expires_at = GREATEST(banned_tokens.expires_at, EXCLUDED.expires_at)
+	        "#
+			)
+			.bind(jti)
+			.bind(expires_at)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "Clear tokens in PostgreSQL", skip_all)]
+	async fn clear(&mut self) -> Result<(), TokenStoreError> {
+		sqlx::query("DELETE FROM banned_tokens")
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "Count tokens in PostgreSQL", skip_all)]
+	async fn count(&self) -> Result<u64, TokenStoreError> {
+		let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM banned_tokens WHERE expires_at > NOW()")
+			.fetch_one(&self.pool)
+			.await
+			.map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+		Ok(count as u64)
+	}
+
+	#[tracing::instrument(name = "Delete token from PostgreSQL", skip_all)]
+	async fn delete_token(&mut self, jti: &str) -> Result<(), TokenStoreError> {
+		sqlx::query("DELETE FROM banned_tokens WHERE jti = $1")
+			.bind(jti)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "Check token in PostgreSQL", skip_all)]
+	async fn contains_token(&self, jti: &str) -> Result<bool, TokenStoreError> {
+		let found = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM banned_tokens WHERE jti = $1 AND expires_at > NOW())")
+			.bind(jti)
+			.fetch_one(&self.pool)
+			.await
+			.map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+		Ok(found)
+	}
+
+	// The epoch has to be remembered for as long as the longest lived credential
+	// issued before it, which is a refresh token.
+	//
+	#[tracing::instrument(name = "Set not before in PostgreSQL", skip_all)]
+	async fn set_not_before(&mut self, subject: &str, epoch: usize) -> Result<(), TokenStoreError> {
+		if subject.is_empty() { return Err(TokenStoreError::BlankToken) }
+		let expires_at = Utc::now() + Duration::seconds(REFRESH_TOKEN_TTL_SECONDS);
+		sqlx::query(
+			r#"
+	        INSERT INTO token_not_before (subject, epoch, expires_at)
+	        VALUES ($1, $2, $3)
+	        ON CONFLICT (subject) DO UPDATE SET epoch = EXCLUDED.epoch, expires_at = EXCLUDED.expires_at
+	        "#
+			)
+			.bind(subject)
+			.bind(epoch as i64)
+			.bind(expires_at)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "Get not before from PostgreSQL", skip_all)]
+	async fn get_not_before(&self, subject: &str) -> Result<Option<usize>, TokenStoreError> {
+		let epoch: Option<i64> = sqlx::query_scalar("SELECT epoch FROM token_not_before WHERE subject = $1 AND expires_at > NOW()")
+			.bind(subject)
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+		Ok(epoch.map(|e| e as usize))
+	}
+}
+
+fn to_datetime(timestamp: i64) -> Result<DateTime<Utc>, TokenStoreError> {
+	DateTime::from_timestamp(timestamp, 0)
+		.ok_or(TokenStoreError::UnexpectedError(eyre!("Invalid token expiry {}", timestamp)))
+}
@@ -0,0 +1,99 @@
+use crate::domain::data_stores::{EmailChange, EmailChangeStore, EmailChangeStoreError};
+use crate::domain::Email;
+use crate::utils::constants::EMAIL_CHANGE_TTL_SECONDS;
+use chrono::{Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct EmailChangeRecord {
+	pub email:     String,
+	pub new_email: String,
+}
+
+impl EmailChangeRecord {
+	pub fn into_change(self) -> Result<EmailChange, EmailChangeStoreError> {
+		let parse     = |email: String| Email::parse(Secret::new(email)).map_err(|e| EmailChangeStoreError::UnexpectedError(eyre!(e)));
+		let email     = parse(self.email)?;
+		let new_email = parse(self.new_email)?;
+		Ok(EmailChange {email, new_email})
+	}
+}
+
+// Expired changes are ignored by every query, and removed by `purge_expired` now and then.
+//
+pub struct PostgresEmailChangeStore {
+	pool: PgPool,
+}
+
+impl PostgresEmailChangeStore {
+	pub fn new(pool: PgPool) -> Self {
+		Self { pool }
+	}
+
+	#[tracing::instrument(name = "Purge expired email changes from PostgreSQL", skip_all)]
+	pub async fn purge_expired(&self) -> Result<u64, EmailChangeStoreError> {
+		let result = sqlx::query("DELETE FROM email_changes WHERE expires_at <= NOW()")
+			.execute(&self.pool)
+			.await
+			.map_err(|e| EmailChangeStoreError::UnexpectedError(e.into()))?;
+		Ok(result.rows_affected())
+	}
+
+	// DELETE .. RETURNING, so of two requests presenting the same token only one gets the change.
+	//
+	async fn take_change(&mut self, column: &str, token: &Secret<String>) -> Result<EmailChange, EmailChangeStoreError> {
+		let query  = format!("DELETE FROM email_changes WHERE {} = $1 AND expires_at > NOW() RETURNING email, new_email", column);
+		let record = sqlx::query_as::<_, EmailChangeRecord>(&query)
+			.bind(make_digest(token))
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| EmailChangeStoreError::UnexpectedError(e.into()))?
+			.ok_or(EmailChangeStoreError::ChangeNotFound)?;
+		record.into_change()
+	}
+}
+
+#[async_trait::async_trait]
+impl EmailChangeStore for PostgresEmailChangeStore {
+	#[tracing::instrument(name = "Start email change in PostgreSQL", skip_all)]
+	async fn start_change(&mut self, change: EmailChange, confirm_token: &Secret<String>, cancel_token: &Secret<String>) -> Result<(), EmailChangeStoreError> {
+		let expires_at = Utc::now() + Duration::seconds(EMAIL_CHANGE_TTL_SECONDS);
+		sqlx::query(
+			r#"
+	        INSERT INTO email_changes (email, new_email, confirm_digest, cancel_digest, expires_at)
+	        VALUES ($1, $2, $3, $4, $5)
+	        ON CONFLICT (email) DO UPDATE
+	        SET new_email      = EXCLUDED.new_email,
+	            confirm_digest = EXCLUDED.confirm_digest,
+	            cancel_digest  = EXCLUDED.cancel_digest,
+	            expires_at     = EXCLUDED.expires_at
+	        "#
+			)
+			.bind(change.email.expose_secret())
+			.bind(change.new_email.expose_secret())
+			.bind(make_digest(confirm_token))
+			.bind(make_digest(cancel_token))
+			.bind(expires_at)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| EmailChangeStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+
+	#[tracing::instrument(name = "Confirm email change in PostgreSQL", skip_all)]
+	async fn confirm_change(&mut self, confirm_token: &Secret<String>) -> Result<EmailChange, EmailChangeStoreError> {
+		self.take_change("confirm_digest", confirm_token).await
+	}
+
+	#[tracing::instrument(name = "Cancel email change in PostgreSQL", skip_all)]
+	async fn cancel_change(&mut self, cancel_token: &Secret<String>) -> Result<EmailChange, EmailChangeStoreError> {
+		self.take_change("cancel_digest", cancel_token).await
+	}
+}
+
+fn make_digest(token: &Secret<String>) -> String {
+	format!("{:x}", Sha256::digest(token.expose_secret().as_bytes()))
+}
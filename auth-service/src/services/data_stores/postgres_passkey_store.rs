@@ -0,0 +1,86 @@
+use crate::domain::data_stores::{PasskeyStore, PasskeyStoreError};
+use crate::domain::Email;
+use color_eyre::eyre::{eyre, Result};
+use data_encoding::BASE64URL_NOPAD;
+use sqlx::PgPool;
+use webauthn_rs::prelude::{Passkey, Uuid};
+
+pub struct PostgresPasskeyStore {
+	pool: PgPool,
+}
+
+impl PostgresPasskeyStore {
+	pub fn new(pool: PgPool) -> Self {
+		Self { pool }
+	}
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for PostgresPasskeyStore {
+	#[tracing::instrument(name = "Add passkey to PostgreSQL", skip_all)]
+	async fn add_passkey(&mut self, email: &Email, user_handle: Uuid, passkey: &Passkey) -> Result<(), PasskeyStoreError> {
+		let serialized = serde_json::to_string(passkey).map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+		let result     = sqlx::query(
+			r#"
+	        INSERT INTO passkeys (credential_id, email, user_handle, passkey, created_at)
+	        VALUES ($1, $2, $3, $4, NOW())
+	        ON CONFLICT (credential_id) DO NOTHING
+	        "#
+			)
+			.bind(credential_id(passkey))
+			.bind(email.expose_secret())
+			.bind(user_handle.to_string())
+			.bind(serialized)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(PasskeyStoreError::CredentialAlreadyRegistered),
+			_ => Ok(()),
+		}
+	}
+
+	#[tracing::instrument(name = "Retrieve passkeys from PostgreSQL", skip_all)]
+	async fn get_passkeys(&self, email: &Email) -> Result<Vec<Passkey>, PasskeyStoreError> {
+		let rows = sqlx::query_scalar::<_, String>("SELECT passkey FROM passkeys WHERE email = $1 ORDER BY created_at")
+			.bind(email.expose_secret())
+			.fetch_all(&self.pool)
+			.await
+			.map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+		rows.iter()
+			.map(|row| serde_json::from_str(row).map_err(|e| PasskeyStoreError::UnexpectedError(e.into())))
+			.collect()
+	}
+
+	#[tracing::instrument(name = "Retrieve passkey user handle from PostgreSQL", skip_all)]
+	async fn get_user_handle(&self, email: &Email) -> Result<Option<Uuid>, PasskeyStoreError> {
+		let handle = sqlx::query_scalar::<_, String>("SELECT user_handle FROM passkeys WHERE email = $1 LIMIT 1")
+			.bind(email.expose_secret())
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+		handle
+			.map(|h| Uuid::parse_str(&h).map_err(|e| PasskeyStoreError::UnexpectedError(e.into())))
+			.transpose()
+	}
+
+	#[tracing::instrument(name = "Update passkey in PostgreSQL", skip_all)]
+	async fn update_passkey(&mut self, email: &Email, passkey: &Passkey) -> Result<(), PasskeyStoreError> {
+		let serialized = serde_json::to_string(passkey).map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+		let result     = sqlx::query("UPDATE passkeys SET passkey = $3, last_used_at = NOW() WHERE credential_id = $1 AND email = $2")
+			.bind(credential_id(passkey))
+			.bind(email.expose_secret())
+			.bind(serialized)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(PasskeyStoreError::UnexpectedError(eyre!("Passkey not found"))),
+			_ => Ok(()),
+		}
+	}
+}
+
+fn credential_id(passkey: &Passkey) -> String {
+	BASE64URL_NOPAD.encode(passkey.cred_id().as_ref())
+}
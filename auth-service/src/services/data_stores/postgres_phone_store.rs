@@ -0,0 +1,175 @@
+use crate::domain::data_stores::{PhoneEnrollment, PhoneStore, PhoneStoreError, TwoFAChannel, TwoFACodeHash};
+use crate::domain::{Email, PhoneNumber};
+use crate::utils::constants::TWO_FA_CODE_TTL_SECONDS;
+use chrono::{Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::Secret;
+use sqlx::PgPool;
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct PhoneLimitsRecord {
+	pub resend_count:    i32,
+	pub failed_attempts: i32,
+}
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct PhoneRecord {
+	pub phone_number:   String,
+	pub verified:       bool,
+	pub two_fa_channel: String,
+}
+
+pub struct PostgresPhoneStore {
+	pool: PgPool,
+}
+
+impl PostgresPhoneStore {
+	pub fn new(pool: PgPool) -> Self {
+		Self { pool }
+	}
+}
+
+#[async_trait::async_trait]
+impl PhoneStore for PostgresPhoneStore {
+	// The limits only hold while the previous code is alive. Once it expired, or the number was
+	// verified, registering starts afresh. When the upsert does not go through, look at the row
+	// to tell the caller why.
+	//
+	#[tracing::instrument(name = "Start phone verification in PostgreSQL", skip_all)]
+	async fn start_verification(
+		&mut self,
+		email:            &Email,
+		phone_number:     PhoneNumber,
+		code_hash:        TwoFACodeHash,
+		cooldown_seconds: i64,
+		max_resends:      u32,
+		max_attempts:     u32,
+	) -> Result<(), PhoneStoreError> {
+		let now        = Utc::now();
+		let expires_at = now + Duration::seconds(TWO_FA_CODE_TTL_SECONDS);
+		let sent_by    = now - Duration::seconds(cooldown_seconds);
+		let started    = sqlx::query(
+			r#"
+	        INSERT INTO phone_numbers (email, phone_number, verified, two_fa_channel, verification_code_hash, code_expires_at, failed_attempts, resend_count, last_sent_at)
+	        VALUES ($1, $2, FALSE, 'email', $3, $4, 0, 0, $5)
+	        ON CONFLICT (email) DO UPDATE
+	        SET phone_number           = EXCLUDED.phone_number,
+	            verified               = FALSE,
+	            two_fa_channel         = 'email',
+	            verification_code_hash = EXCLUDED.verification_code_hash,
+	            code_expires_at        = EXCLUDED.code_expires_at,
+	            failed_attempts        = CASE WHEN phone_numbers.code_expires_at > $5 THEN phone_numbers.failed_attempts  ELSE 0 END,
+	            resend_count           = CASE WHEN phone_numbers.code_expires_at > $5 THEN phone_numbers.resend_count + 1 ELSE 0 END,
+	            last_sent_at           = EXCLUDED.last_sent_at
+	        WHERE phone_numbers.code_expires_at IS NULL OR phone_numbers.code_expires_at <= $5
+	           OR (phone_numbers.resend_count < $6 AND phone_numbers.failed_attempts < $7
+	               AND (phone_numbers.last_sent_at IS NULL OR phone_numbers.last_sent_at <= $8))
+	        "#
+			)
+			.bind(email.expose_secret())
+			.bind(phone_number.expose_secret())
+			.bind(code_hash.as_ref())
+			.bind(expires_at)
+			.bind(now)
+			.bind(max_resends as i32)
+			.bind(max_attempts as i32)
+			.bind(sent_by)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| PhoneStoreError::UnexpectedError(e.into()))?;
+		if started.rows_affected() > 0 {
+			return Ok(());
+		}
+
+		let limits     = sqlx::query_as::<_, PhoneLimitsRecord>("SELECT resend_count, failed_attempts FROM phone_numbers WHERE email = $1")
+			.bind(email.expose_secret())
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| PhoneStoreError::UnexpectedError(e.into()))?
+			.ok_or(PhoneStoreError::UnexpectedError(eyre!("Phone number vanished while registering it")))?;
+		if limits.failed_attempts as u32 >= max_attempts {
+			return Err(PhoneStoreError::TooManyAttempts);
+		}
+		match limits.resend_count as u32 >= max_resends {
+			true  => Err(PhoneStoreError::ResendLimitReached),
+			false => Err(PhoneStoreError::ResendTooSoon),
+		}
+	}
+
+	// A right code and a wrong one are each settled by a single statement, so of two requests
+	// racing with the right code only one verifies the number, and no wrong answer goes uncounted.
+	// Only keyed hashes are compared here, so how long the comparison takes says nothing about the code.
+	//
+	#[tracing::instrument(name = "Verify phone number in PostgreSQL", skip_all)]
+	async fn verify_number(&mut self, email: &Email, code_hash: &TwoFACodeHash, max_attempts: u32) -> Result<(), PhoneStoreError> {
+		let email    = email.expose_secret();
+		let verified = sqlx::query(
+			r#"
+	        UPDATE phone_numbers
+	        SET verified = TRUE, verification_code_hash = NULL, code_expires_at = NULL, failed_attempts = 0
+	        WHERE email = $1 AND verified = FALSE AND verification_code_hash = $2 AND code_expires_at > NOW()
+	        "#
+			)
+			.bind(email)
+			.bind(code_hash.as_ref())
+			.execute(&self.pool)
+			.await
+			.map_err(|e| PhoneStoreError::UnexpectedError(e.into()))?;
+		if verified.rows_affected() > 0 {
+			return Ok(());
+		}
+
+		let failures = sqlx::query_scalar::<_, i32>(
+			r#"
+	        UPDATE phone_numbers
+	        SET failed_attempts        = failed_attempts + 1,
+	            verification_code_hash = CASE WHEN failed_attempts + 1 >= $2 THEN NULL ELSE verification_code_hash END
+	        WHERE email = $1 AND verified = FALSE AND verification_code_hash IS NOT NULL AND code_expires_at > NOW()
+	        RETURNING failed_attempts
+	        "#
+			)
+			.bind(email)
+			.bind(max_attempts as i32)
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| PhoneStoreError::UnexpectedError(e.into()))?;
+		match failures {
+			None                                              => Err(PhoneStoreError::CodeNotFound),
+			Some(failures) if failures as u32 >= max_attempts => Err(PhoneStoreError::TooManyAttempts),
+			Some(_)                                           => Err(PhoneStoreError::IncorrectCode),
+		}
+	}
+
+	#[tracing::instrument(name = "Retrieve phone number from PostgreSQL", skip_all)]
+	async fn get_phone(&self, email: &Email) -> Result<PhoneEnrollment, PhoneStoreError> {
+		let record       = sqlx::query_as::<_, PhoneRecord>("SELECT phone_number, verified, two_fa_channel FROM phone_numbers WHERE email = $1")
+			.bind(email.expose_secret())
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| PhoneStoreError::UnexpectedError(e.into()))?
+			.ok_or(PhoneStoreError::NotRegistered)?;
+		let phone_number = PhoneNumber::parse(Secret::new(record.phone_number))
+			.map_err(|e| PhoneStoreError::UnexpectedError(eyre!(e)))?;
+		let channel      = TwoFAChannel::parse(&record.two_fa_channel).map_err(PhoneStoreError::UnexpectedError)?;
+		Ok(PhoneEnrollment {phone_number, verified: record.verified, channel})
+	}
+
+	// Going back to email needs no phone number at all.
+	//
+	#[tracing::instrument(name = "Set 2FA channel in PostgreSQL", skip_all)]
+	async fn set_channel(&mut self, email: &Email, channel: TwoFAChannel) -> Result<(), PhoneStoreError> {
+		let result = sqlx::query("UPDATE phone_numbers SET two_fa_channel = $2 WHERE email = $1 AND (verified OR $2 = 'email')")
+			.bind(email.expose_secret())
+			.bind(channel.as_str())
+			.execute(&self.pool)
+			.await
+			.map_err(|e| PhoneStoreError::UnexpectedError(e.into()))?;
+		if result.rows_affected() > 0 || channel == TwoFAChannel::Email {
+			return Ok(());
+		}
+		match self.get_phone(email).await {
+			Ok(_)  => Err(PhoneStoreError::NotVerified),
+			Err(e) => Err(e),
+		}
+	}
+}
@@ -0,0 +1,34 @@
+use super::postgres_2fa_code_store::PostgresTwoFACodeStore;
+use super::postgres_banned_token_store::PostgresBannedTokenStore;
+use super::postgres_email_change_store::PostgresEmailChangeStore;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+// Postgres does not expire rows on its own the way Redis expires keys.
+// Expired rows are already ignored by the stores, this only keeps the tables small.
+//
+pub fn spawn_expired_row_purge(pool: PgPool, every: Duration) -> JoinHandle<()> {
+	let tokens = PostgresBannedTokenStore::new(pool.clone());
+	let codes  = PostgresTwoFACodeStore::new(pool.clone());
+	let emails = PostgresEmailChangeStore::new(pool);
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(every);
+		loop {
+			interval.tick().await;
+			match tokens.purge_expired().await {
+				Ok(count) => debug!(count, "Purged expired tokens"),
+				Err(e)    => warn!("Failed to purge expired tokens: {:?}", e),
+			}
+			match codes.purge_expired().await {
+				Ok(count) => debug!(count, "Purged expired 2FA codes"),
+				Err(e)    => warn!("Failed to purge expired 2FA codes: {:?}", e),
+			}
+			match emails.purge_expired().await {
+				Ok(count) => debug!(count, "Purged expired email changes"),
+				Err(e)    => warn!("Failed to purge expired email changes: {:?}", e),
+			}
+		}
+	})
+}
@@ -0,0 +1,72 @@
+use crate::domain::data_stores::{RecoveryCodeRecord, RecoveryCodeStore, RecoveryCodeStoreError};
+use crate::domain::Email;
+use color_eyre::eyre::Result;
+use sqlx::PgPool;
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct RecoveryCodeRow {
+	pub id:         i64,
+	pub code_hash:  String,
+}
+
+pub struct PostgresRecoveryCodeStore {
+	pool: PgPool,
+}
+
+impl PostgresRecoveryCodeStore {
+	pub fn new(pool: PgPool) -> Self {
+		Self { pool }
+	}
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for PostgresRecoveryCodeStore {
+	// The old set goes and the new one arrives in one transaction, so a user never
+	// ends up with both sets or with none.
+	//
+	#[tracing::instrument(name = "Replace recovery codes in PostgreSQL", skip_all)]
+	async fn replace_codes(&mut self, email: &Email, hashes: Vec<String>) -> Result<(), RecoveryCodeStoreError> {
+		let email  = email.expose_secret();
+		let e_sqlx = |e: sqlx::Error| RecoveryCodeStoreError::UnexpectedError(e.into());
+		let mut tx = self.pool.begin().await.map_err(e_sqlx)?;
+		sqlx::query("DELETE FROM recovery_codes WHERE email = $1")
+			.bind(email)
+			.execute(&mut *tx)
+			.await
+			.map_err(e_sqlx)?;
+		sqlx::query("INSERT INTO recovery_codes (email, code_hash) SELECT $1, UNNEST($2::TEXT[])")
+			.bind(email)
+			.bind(hashes)
+			.execute(&mut *tx)
+			.await
+			.map_err(e_sqlx)?;
+		tx.commit().await.map_err(e_sqlx)
+	}
+
+	#[tracing::instrument(name = "Retrieve unused recovery codes from PostgreSQL", skip_all)]
+	async fn get_unused_codes(&self, email: &Email) -> Result<Vec<RecoveryCodeRecord>, RecoveryCodeStoreError> {
+		let rows = sqlx::query_as::<_, RecoveryCodeRow>("SELECT id, code_hash FROM recovery_codes WHERE email = $1 AND used_at IS NULL ORDER BY id")
+			.bind(email.expose_secret())
+			.fetch_all(&self.pool)
+			.await
+			.map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+		let codes = rows.into_iter().map(|row| RecoveryCodeRecord {id: row.id, hash: row.code_hash}).collect();
+		Ok(codes)
+	}
+
+	// Of two requests racing with the same code, only one gets to spend it.
+	//
+	#[tracing::instrument(name = "Use recovery code in PostgreSQL", skip_all)]
+	async fn use_code(&mut self, email: &Email, id: i64) -> Result<(), RecoveryCodeStoreError> {
+		let result = sqlx::query("UPDATE recovery_codes SET used_at = NOW() WHERE id = $1 AND email = $2 AND used_at IS NULL")
+			.bind(id)
+			.bind(email.expose_secret())
+			.execute(&self.pool)
+			.await
+			.map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(RecoveryCodeStoreError::CodeAlreadyUsed),
+			_ => Ok(()),
+		}
+	}
+}
@@ -0,0 +1,125 @@
+use crate::domain::data_stores::{Session, SessionStore, SessionStoreError};
+use crate::domain::Email;
+use crate::utils::constants::REFRESH_TOKEN_TTL_SECONDS;
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::Secret;
+use sqlx::PgPool;
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct SessionRecord {
+	pub id:          String,
+	pub email:       String,
+	pub user_agent:  Option<String>,
+	pub ip:          Option<String>,
+	pub created_at:  DateTime<Utc>,
+	pub last_seen:   DateTime<Utc>,
+}
+
+impl SessionRecord {
+	pub fn into_session(self) -> Result<Session, SessionStoreError> {
+		let email = Secret::new(self.email);
+		let email = Email::parse(email).map_err(|e| SessionStoreError::UnexpectedError(eyre!(e)))?;
+		Ok(Session {
+			id:         self.id,
+			email,
+			user_agent: self.user_agent,
+			ip:         self.ip,
+			created_at: self.created_at,
+			last_seen:  self.last_seen,
+		})
+	}
+}
+
+pub struct PostgresSessionStore {
+	pool: PgPool,
+}
+
+impl PostgresSessionStore {
+	pub fn new(pool: PgPool) -> Self {
+		Self { pool }
+	}
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+	#[tracing::instrument(name = "Add session to PostgreSQL", skip_all)]
+	async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+		sqlx::query(
+			r#"
+	        INSERT INTO sessions (id, email, user_agent, ip, created_at, last_seen)
+	        VALUES ($1, $2, $3, $4, $5, $6)
+	        "#
+			)
+			.bind(&session.id)
+			.bind(session.email.expose_secret())
+			.bind(&session.user_agent)
+			.bind(&session.ip)
+			.bind(session.created_at)
+			.bind(session.last_seen)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+
+	// A session that has not been seen for longer than a refresh token lives cannot be resumed,
+	// so it is no longer listed.
+	//
+	#[tracing::instrument(name = "Retrieve sessions from PostgreSQL", skip_all)]
+	async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+		let cutoff  = Utc::now() - Duration::seconds(REFRESH_TOKEN_TTL_SECONDS);
+		let records = sqlx::query_as::<_, SessionRecord>(
+			r#"
+	        SELECT id, email, user_agent, ip, created_at, last_seen
+	        FROM sessions
+	        WHERE email = $1 AND last_seen > $2
+	        ORDER BY last_seen DESC
+	        "#
+			)
+			.bind(email.expose_secret())
+			.bind(cutoff)
+			.fetch_all(&self.pool)
+			.await
+			.map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+		records.into_iter().map(SessionRecord::into_session).collect()
+	}
+
+	#[tracing::instrument(name = "Touch session in PostgreSQL", skip_all)]
+	async fn touch_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError> {
+		let result = sqlx::query("UPDATE sessions SET last_seen = NOW() WHERE id = $1 AND email = $2")
+			.bind(id)
+			.bind(email.expose_secret())
+			.execute(&self.pool)
+			.await
+			.map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(SessionStoreError::SessionNotFound),
+			_ => Ok(()),
+		}
+	}
+
+	#[tracing::instrument(name = "Remove session from PostgreSQL", skip_all)]
+	async fn remove_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError> {
+		let result = sqlx::query("DELETE FROM sessions WHERE id = $1 AND email = $2")
+			.bind(id)
+			.bind(email.expose_secret())
+			.execute(&self.pool)
+			.await
+			.map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(SessionStoreError::SessionNotFound),
+			_ => Ok(()),
+		}
+	}
+
+	#[tracing::instrument(name = "Remove all sessions from PostgreSQL", skip_all)]
+	async fn remove_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+		sqlx::query("DELETE FROM sessions WHERE email = $1")
+			.bind(email.expose_secret())
+			.execute(&self.pool)
+			.await
+			.map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+}
@@ -0,0 +1,114 @@
+use crate::domain::data_stores::{TotpEnrollment, TotpStore, TotpStoreError};
+use crate::domain::Email;
+use crate::utils::constants::TOTP_CIPHER;
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct TotpRecord {
+	pub secret:    Vec<u8>,
+	pub confirmed: bool,
+}
+
+// Secrets are sealed with TOTP_CIPHER before they reach the database,
+// bound to the email address they belong to.
+//
+pub struct PostgresTotpStore {
+	pool: PgPool,
+}
+
+impl PostgresTotpStore {
+	pub fn new(pool: PgPool) -> Self {
+		Self { pool }
+	}
+}
+
+#[async_trait::async_trait]
+impl TotpStore for PostgresTotpStore {
+	#[tracing::instrument(name = "Start TOTP enrollment in PostgreSQL", skip_all)]
+	async fn start_enrollment(&mut self, email: &Email, secret: Secret<Vec<u8>>) -> Result<(), TotpStoreError> {
+		let email  = email.expose_secret();
+		let sealed = TOTP_CIPHER
+			.seal(secret.expose_secret(), email.as_bytes())
+			.map_err(TotpStoreError::UnexpectedError)?;
+		let result = sqlx::query(
+			r#"
+	        INSERT INTO totp_secrets (email, secret, confirmed, last_used_step, created_at)
+	        VALUES ($1, $2, FALSE, NULL, NOW())
+	        ON CONFLICT (email) DO UPDATE
+	        SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+	        WHERE totp_secrets.confirmed = FALSE
+	        "#
+			)
+			.bind(email)
+			.bind(sealed)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(TotpStoreError::AlreadyEnrolled),
+			_ => Ok(()),
+		}
+	}
+
+	#[tracing::instrument(name = "Retrieve TOTP enrollment from PostgreSQL", skip_all)]
+	async fn get_enrollment(&self, email: &Email) -> Result<TotpEnrollment, TotpStoreError> {
+		let email  = email.expose_secret();
+		let record = sqlx::query_as::<_, TotpRecord>("SELECT secret, confirmed FROM totp_secrets WHERE email = $1")
+			.bind(email)
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| TotpStoreError::UnexpectedError(e.into()))?
+			.ok_or(TotpStoreError::NotEnrolled)?;
+		let secret = TOTP_CIPHER
+			.open(&record.secret, email.as_bytes())
+			.map_err(TotpStoreError::UnexpectedError)?;
+		Ok(TotpEnrollment {secret, confirmed: record.confirmed})
+	}
+
+	#[tracing::instrument(name = "Confirm TOTP enrollment in PostgreSQL", skip_all)]
+	async fn confirm_enrollment(&mut self, email: &Email, step: u64) -> Result<(), TotpStoreError> {
+		let result = sqlx::query("UPDATE totp_secrets SET confirmed = TRUE, last_used_step = $2 WHERE email = $1 AND confirmed = FALSE")
+			.bind(email.expose_secret())
+			.bind(step as i64)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(TotpStoreError::NotEnrolled),
+			_ => Ok(()),
+		}
+	}
+
+	// Only moves forward, so of two requests racing with the same code only one wins.
+	//
+	#[tracing::instrument(name = "Use TOTP step in PostgreSQL", skip_all)]
+	async fn use_step(&mut self, email: &Email, step: u64) -> Result<(), TotpStoreError> {
+		let result = sqlx::query(
+			r#"
+	        UPDATE totp_secrets SET last_used_step = $2
+	        WHERE email = $1 AND confirmed = TRUE AND (last_used_step IS NULL OR last_used_step < $2)
+	        "#
+			)
+			.bind(email.expose_secret())
+			.bind(step as i64)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(TotpStoreError::CodeReused),
+			_ => Ok(()),
+		}
+	}
+
+	#[tracing::instrument(name = "Remove TOTP enrollment from PostgreSQL", skip_all)]
+	async fn remove_enrollment(&mut self, email: &Email) -> Result<(), TotpStoreError> {
+		sqlx::query("DELETE FROM totp_secrets WHERE email = $1")
+			.bind(email.expose_secret())
+			.execute(&self.pool)
+			.await
+			.map_err(|e| TotpStoreError::UnexpectedError(e.into()))?;
+		Ok(())
+	}
+}
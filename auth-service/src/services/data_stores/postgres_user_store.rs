@@ -1,17 +1,20 @@
-use crate::domain::data_stores::{UserStore, UserStoreError};
+use crate::domain::data_stores::{AccountTombstone, UserStore, UserStoreError};
 use crate::domain::{Email, EmailError, Password, PasswordError, User};
+use crate::utils::constants::TOTP_CIPHER;
 use crate::utils::hash_utils;
 use crate::utils::hash_utils::hash_password_async;
 use color_eyre::eyre::{eyre, Result};
 use log::debug;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
+use uuid::Uuid;
 
 #[derive(Clone, Debug, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
 pub struct UserRecord {
 	pub email:          String,
 	pub password_hash:  String,
 	pub requires_2fa:   bool,
+	pub email_verified: bool,
 }
 
 impl UserRecord {
@@ -22,7 +25,8 @@ impl UserRecord {
 		let email    = Email::parse(email).map_err(e_email)?;
 		let password = Secret::new(self.password_hash);
 		let password = Password::parse(password).map_err(e_pword)?;
-		let user     = User::new(email, password, self.requires_2fa);
+		let mut user = User::new(email, password, self.requires_2fa);
+		user.email_verified = self.email_verified;
 		Ok(user)
 	}
 }
@@ -54,13 +58,14 @@ impl UserStore for PostgresUserStore {
 		let hash           = hash_result.map_err(UserStoreError::UnexpectedError)?;
 		sqlx::query(
 			r#"
-	        INSERT INTO users (email, password_hash, requires_2fa)
-	        VALUES ($1, $2, $3)
+	        INSERT INTO users (email, password_hash, requires_2fa, email_verified)
+	        VALUES ($1, $2, $3, $4)
 	        "#
 			)
 			.bind(&email.expose_secret())
 			.bind(hash)
 			.bind(user.requires_2fa)
+			.bind(user.email_verified)
 			.execute(&self.pool)
 			.await
 			.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
@@ -70,7 +75,7 @@ impl UserStore for PostgresUserStore {
 	#[tracing::instrument(name = "Retrieve user from PostgreSQL", skip_all)] // New!
 	async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
 		let email  = email.expose_secret().to_owned();
-		let result = sqlx::query_as!(UserRecord, "SELECT email, password_hash, requires_2fa FROM users WHERE email = $1", email)
+		let result = sqlx::query_as!(UserRecord, "SELECT email, password_hash, requires_2fa, email_verified FROM users WHERE email = $1", email)
 			.fetch_optional(&self.pool)
 			.await.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 		match result {
@@ -90,4 +95,126 @@ impl UserStore for PostgresUserStore {
 		let result        = hash_utils::verify_password_async(password_hash, password).await;
 		result.map_err(|_| UserStoreError::InvalidCredentials)
 	}
+
+	#[tracing::instrument(name = "Set 2FA requirement in PostgreSQL", skip_all)]
+	async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError> {
+		let result = sqlx::query("UPDATE users SET requires_2fa = $2 WHERE email = $1")
+			.bind(email.expose_secret())
+			.bind(requires_2fa)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(UserStoreError::UserNotFound),
+			_ => Ok(()),
+		}
+	}
+
+	#[tracing::instrument(name = "Set password in PostgreSQL", skip_all)]
+	async fn set_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError> {
+		let password = password.expose().to_owned();
+		let hash     = hash_password_async(password).await.map_err(UserStoreError::UnexpectedError)?;
+		let result   = sqlx::query("UPDATE users SET password_hash = $2 WHERE email = $1")
+			.bind(email.expose_secret())
+			.bind(hash)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(UserStoreError::UserNotFound),
+			_ => Ok(()),
+		}
+	}
+
+	// Rows of other tables follow the new address by ON UPDATE CASCADE. TOTP secrets are sealed
+	// with the email as associated data, so the user's secret is sealed again for the new one,
+	// in the same transaction.
+	//
+	#[tracing::instrument(name = "Change email in PostgreSQL", skip_all)]
+	async fn change_email(&mut self, email: &Email, new_email: &Email) -> Result<(), UserStoreError> {
+		let unexpected = |e: sqlx::Error| UserStoreError::UnexpectedError(e.into());
+		let email      = email.expose_secret();
+		let new_email  = new_email.expose_secret();
+		let mut tx     = self.pool.begin().await.map_err(unexpected)?;
+		let result     = sqlx::query("UPDATE users SET email = $2 WHERE email = $1")
+			.bind(email)
+			.bind(new_email)
+			.execute(&mut *tx)
+			.await;
+		let result     = match result {
+			Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Err(UserStoreError::UserAlreadyExists),
+			result                                                   => result.map_err(unexpected)?,
+		};
+		if result.rows_affected() == 0 {
+			return Err(UserStoreError::UserNotFound);
+		}
+
+		let sealed     = sqlx::query_scalar::<_, Vec<u8>>("SELECT secret FROM totp_secrets WHERE email = $1")
+			.bind(new_email)
+			.fetch_optional(&mut *tx)
+			.await
+			.map_err(unexpected)?;
+		if let Some(sealed) = sealed {
+			let secret = TOTP_CIPHER.open(&sealed, email.as_bytes()).map_err(UserStoreError::UnexpectedError)?;
+			let sealed = TOTP_CIPHER.seal(secret.expose_secret(), new_email.as_bytes()).map_err(UserStoreError::UnexpectedError)?;
+			sqlx::query("UPDATE totp_secrets SET secret = $2 WHERE email = $1")
+				.bind(new_email)
+				.bind(sealed)
+				.execute(&mut *tx)
+				.await
+				.map_err(unexpected)?;
+		}
+		tx.commit().await.map_err(unexpected)
+	}
+
+	// Rows of other tables that belong to the user go with it, by ON DELETE CASCADE.
+	// The tombstone is written in the same transaction.
+	//
+	#[tracing::instrument(name = "Delete user from PostgreSQL", skip_all)]
+	async fn delete_user(&mut self, email: &Email) -> Result<AccountTombstone, UserStoreError> {
+		let unexpected = |e: sqlx::Error| UserStoreError::UnexpectedError(e.into());
+		let tombstone  = AccountTombstone::new();
+		let mut tx     = self.pool.begin().await.map_err(unexpected)?;
+		let result     = sqlx::query("DELETE FROM users WHERE email = $1")
+			.bind(email.expose_secret())
+			.execute(&mut *tx)
+			.await
+			.map_err(unexpected)?;
+		if result.rows_affected() == 0 {
+			return Err(UserStoreError::UserNotFound);
+		}
+		sqlx::query("INSERT INTO account_tombstones (id, deleted_at) VALUES ($1, $2)")
+			.bind(tombstone.id.to_string())
+			.bind(tombstone.deleted_at)
+			.execute(&mut *tx)
+			.await
+			.map_err(unexpected)?;
+		tx.commit().await.map_err(unexpected)?;
+		Ok(tombstone)
+	}
+
+	#[tracing::instrument(name = "Retrieve tombstone from PostgreSQL", skip_all)]
+	async fn get_tombstone(&self, id: &Uuid) -> Result<AccountTombstone, UserStoreError> {
+		let deleted_at = sqlx::query_scalar("SELECT deleted_at FROM account_tombstones WHERE id = $1")
+			.bind(id.to_string())
+			.fetch_optional(&self.pool)
+			.await
+			.map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+			.ok_or(UserStoreError::TombstoneNotFound)?;
+		Ok(AccountTombstone {id: *id, deleted_at})
+	}
+
+	#[tracing::instrument(name = "Set email verification in PostgreSQL", skip_all)]
+	async fn set_email_verified(&mut self, email: &Email, verified: bool) -> Result<(), UserStoreError> {
+		let result = sqlx::query("UPDATE users SET email_verified = $2 WHERE email = $1")
+			.bind(email.expose_secret())
+			.bind(verified)
+			.execute(&self.pool)
+			.await
+			.map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+		match result.rows_affected() {
+			0 => Err(UserStoreError::UserNotFound),
+			_ => Ok(()),
+		}
+	}
 }
@@ -1,27 +1,49 @@
-use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use color_eyre::eyre::{eyre, Context};
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use secrecy::Secret;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock;
 use tracing::debug;
 
-const TEN_MINUTES_IN_SECONDS: u64  = 600;
 const TWO_FA_CODE_PREFIX:     &str = ACTIVE_TOKEN_KEY_PREFIX;
+const TWO_FA_ATTEMPTS_PREFIX: &str = "2FA:Tokens:Attempts";
+const TWO_FA_FAILURES_PREFIX: &str = "2FA:Tokens:Failures";
+const TWO_FA_RESENDS_PREFIX:  &str = "2FA:Tokens:Resends";
+const TWO_FA_COOLDOWN_PREFIX: &str = "2FA:Tokens:ResendCooldown";
 
 use crate::domain::{
-	data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+	data_stores::{LoginAttemptId, TwoFACodeHash, TwoFACodeStore, TwoFACodeStoreError},
 	Email,
 };
-use crate::utils::constants::ACTIVE_TOKEN_KEY_PREFIX;
+use crate::utils::constants::{ACTIVE_TOKEN_KEY_PREFIX, TWO_FA_CODE_TTL_SECONDS};
 
+// Each login attempt has its own key, and a set per user lists the user's attempts,
+// so they can all be dropped at once. Everything expires with the codes.
+// A key holds the user's email and the hash of the code, never the code itself.
+//
 pub struct RedisTwoFACodeStore {
-	cx: Arc<RwLock<Connection>>,
+	cx: ConnectionManager,
 }
 
 impl RedisTwoFACodeStore {
-	pub fn new(cx: Arc<RwLock<Connection>>) -> Self {
+	pub fn new(cx: ConnectionManager) -> Self {
 		Self { cx }
 	}
+
+	async fn delete_attempts(&mut self, login_attempt_ids: &[String]) -> Result<(), TwoFACodeStoreError> {
+		let keys: Vec<String> = login_attempt_ids.iter()
+			.flat_map(|id| [TWO_FA_CODE_PREFIX, TWO_FA_FAILURES_PREFIX, TWO_FA_RESENDS_PREFIX, TWO_FA_COOLDOWN_PREFIX]
+				.map(|prefix| make_key(prefix, id)))
+			.collect();
+		if keys.is_empty() {
+			return Ok(());
+		}
+		let _: ()   = self.cx.clone()
+			.del(&keys).await
+			.wrap_err("Failed to delete TwoFA from redis")
+			.map_err(TwoFACodeStoreError::UnexpectedError)?;
+		Ok(())
+	}
 }
 
 #[async_trait::async_trait]
@@ -32,29 +54,50 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 		&mut self,
 		email: Email,
 		login_attempt_id: LoginAttemptId,
-		code: TwoFACode,
+		code_hash: TwoFACodeHash,
 	) -> Result<(), TwoFACodeStoreError> {
-		let key     = make_key(TWO_FA_CODE_PREFIX, &email);
-		debug!(?key, ?email, ?login_attempt_id, ?code, "Adding code to redis");
-		let ttl     = TEN_MINUTES_IN_SECONDS;
-		let tuple   = TwoFATuple(login_attempt_id.as_ref().to_owned(), code.as_ref().to_owned());
+		let key     = make_key(TWO_FA_CODE_PREFIX, login_attempt_id.as_ref());
+		let index   = make_user_key(&email);
+		debug!(?key, ?email, ?login_attempt_id, "Adding code to redis");
+		let ttl     = TWO_FA_CODE_TTL_SECONDS as u64;
+		let tuple   = TwoFATuple(email.expose_secret().to_owned(), code_hash.as_ref().to_owned());
 		let body    = serde_json::to_string(&tuple)
 			.wrap_err("Failed to serialize TwoFA code")
 			.map_err(TwoFACodeStoreError::UnexpectedError)?;
-		let _: ()   = self.cx.write().await
-			.set_ex(&key, &body, ttl)
+		let _: ()   = redis::pipe()
+			.atomic()
+			.set_ex(&key, &body, ttl).ignore()
+			.sadd(&index, login_attempt_id.as_ref()).ignore()
+			.expire(&index, TWO_FA_CODE_TTL_SECONDS).ignore()
+			.query_async(&mut self.cx.clone()).await
 			.wrap_err("Failed to write TwoFA code to redis")
 			.map_err(TwoFACodeStoreError::UnexpectedError)?;
 		Ok(())
 	}
 
 	#[tracing::instrument(name = "remove code from redis", skip_all)]
-	async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-		let key     = make_key(TWO_FA_CODE_PREFIX, &email);
-		debug!(?key, "Key to remove");
-		let _: ()   = self.cx.write().await
-			.del(&key)
-			.wrap_err("Failed to delete TwoFA from redis")
+	async fn remove_code(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+		debug!(?login_attempt_id, "Login attempt to remove");
+		if let Ok((email, _)) = self.get_code(login_attempt_id).await {
+			let _: () = self.cx.clone()
+				.srem(make_user_key(&email), login_attempt_id.as_ref()).await
+				.wrap_err("Failed to unlist TwoFA login attempt in redis")
+				.map_err(TwoFACodeStoreError::UnexpectedError)?;
+		}
+		self.delete_attempts(&[login_attempt_id.as_ref().to_owned()]).await
+	}
+
+	#[tracing::instrument(name = "remove user's codes from redis", skip_all)]
+	async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+		let index   = make_user_key(email);
+		let ids     = self.cx.clone()
+			.smembers::<_, Vec<String>>(&index).await
+			.wrap_err("Failed to list TwoFA login attempts in redis")
+			.map_err(TwoFACodeStoreError::UnexpectedError)?;
+		self.delete_attempts(&ids).await?;
+		let _: ()   = self.cx.clone()
+			.del(&index).await
+			.wrap_err("Failed to delete TwoFA login attempts from redis")
 			.map_err(TwoFACodeStoreError::UnexpectedError)?;
 		Ok(())
 	}
@@ -62,21 +105,86 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 	#[tracing::instrument(name = "lookup code in redis", skip_all)]
 	async fn get_code(
 		&self,
-		email: &Email,
-	) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-		let key     = make_key(TWO_FA_CODE_PREFIX, &email);
+		login_attempt_id: &LoginAttemptId,
+	) -> Result<(Email, TwoFACodeHash), TwoFACodeStoreError> {
+		let key     = make_key(TWO_FA_CODE_PREFIX, login_attempt_id.as_ref());
 		debug!(?key, "Key to lookup");
-		let body    = self.cx.write().await
-			.get::<_, String>(&key)
+		let body    = self.cx.clone()
+			.get::<_, Option<String>>(&key).await
 			.wrap_err("Failed to get TwoFA bytes from redis")
-			.map_err(TwoFACodeStoreError::UnexpectedError)?;
+			.map_err(TwoFACodeStoreError::UnexpectedError)?
+			.ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
 		let object  = serde_json::from_str(&body)
 			.wrap_err("Failed to deserialize TwoFA tuple retrieved from redis")
 			.map_err(TwoFACodeStoreError::UnexpectedError)?;
 		let tuple   = TwoFATuple::from(object);
-		let id      = LoginAttemptId::parse(tuple.0).map_err(TwoFACodeStoreError::UnexpectedError)?;
-		let code    = TwoFACode::parse(tuple.1).map_err(TwoFACodeStoreError::UnexpectedError)?;
-		Ok((id, code))
+		let email   = Email::parse(Secret::new(tuple.0)).map_err(|e| TwoFACodeStoreError::UnexpectedError(eyre!(e)))?;
+		let hash    = TwoFACodeHash::parse(tuple.1).map_err(TwoFACodeStoreError::UnexpectedError)?;
+		Ok((email, hash))
+	}
+
+	// One counter per login attempt, so a new attempt starts from zero.
+	// It lives as long as the code could.
+	//
+	#[tracing::instrument(name = "record failed 2FA attempt in redis", skip_all)]
+	async fn record_failure(&mut self, login_attempt_id: &LoginAttemptId) -> Result<u32, TwoFACodeStoreError> {
+		self.get_code(login_attempt_id).await?;
+		let key     = make_key(TWO_FA_FAILURES_PREFIX, login_attempt_id.as_ref());
+		let (failures,): (u32,) = redis::pipe()
+			.atomic()
+			.incr(&key, 1)
+			.expire(&key, TWO_FA_CODE_TTL_SECONDS).ignore()
+			.query_async(&mut self.cx.clone()).await
+			.wrap_err("Failed to count failed 2FA attempt in redis")
+			.map_err(TwoFACodeStoreError::UnexpectedError)?;
+		Ok(failures)
+	}
+
+	// The cooldown is a key that expires when it is over, so SET NX decides which of two racing
+	// resends wins. The count lives as long as the code could, like the failure counter, and the
+	// failure counter is kept alive as long as the new code.
+	//
+	#[tracing::instrument(name = "resend 2FA code in redis", skip_all)]
+	async fn resend_code(
+		&mut self,
+		login_attempt_id: &LoginAttemptId,
+		code_hash:        TwoFACodeHash,
+		cooldown_seconds: i64,
+		max_resends:      u32,
+	) -> Result<(), TwoFACodeStoreError> {
+		let (email, _)   = self.get_code(login_attempt_id).await?;
+		let count_key    = make_key(TWO_FA_RESENDS_PREFIX,  login_attempt_id.as_ref());
+		let cooldown_key = make_key(TWO_FA_COOLDOWN_PREFIX, login_attempt_id.as_ref());
+		let failures_key = make_key(TWO_FA_FAILURES_PREFIX, login_attempt_id.as_ref());
+		let resends      = self.cx.clone()
+			.get::<_, Option<u32>>(&count_key).await
+			.wrap_err("Failed to read 2FA resend count from redis")
+			.map_err(TwoFACodeStoreError::UnexpectedError)?
+			.unwrap_or(0);
+		if resends >= max_resends {
+			return Err(TwoFACodeStoreError::ResendLimitReached);
+		}
+		if cooldown_seconds > 0 {
+			let options  = SetOptions::default()
+				.conditional_set(ExistenceCheck::NX)
+				.with_expiration(SetExpiry::EX(cooldown_seconds as usize));
+			let started  = self.cx.clone()
+				.set_options::<_, _, Option<String>>(&cooldown_key, 1, options).await
+				.wrap_err("Failed to start 2FA resend cooldown in redis")
+				.map_err(TwoFACodeStoreError::UnexpectedError)?;
+			if started.is_none() {
+				return Err(TwoFACodeStoreError::ResendTooSoon);
+			}
+		}
+		let _: ()        = redis::pipe()
+			.atomic()
+			.incr(&count_key, 1).ignore()
+			.expire(&count_key, TWO_FA_CODE_TTL_SECONDS).ignore()
+			.expire(&failures_key, TWO_FA_CODE_TTL_SECONDS).ignore()
+			.query_async(&mut self.cx.clone()).await
+			.wrap_err("Failed to count 2FA resend in redis")
+			.map_err(TwoFACodeStoreError::UnexpectedError)?;
+		self.add_code(email, login_attempt_id.clone(), code_hash).await
 	}
 }
 
@@ -84,7 +192,11 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
-fn make_key(prefix: &str, email: &Email) -> String {
+fn make_key(prefix: &str, login_attempt_id: &str) -> String {
+	format!("{}:{}", prefix, login_attempt_id)
+}
+
+fn make_user_key(email: &Email) -> String {
 	let email_hash = email.hash_secret_twox128();
-	format!("{}:{}", prefix, email_hash)
+	format!("{}:{}", TWO_FA_ATTEMPTS_PREFIX, email_hash)
 }
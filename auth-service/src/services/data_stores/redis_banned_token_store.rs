@@ -1,24 +1,23 @@
 use crate::domain::data_stores::TokenStore;
 use crate::domain::data_stores::TokenStoreError;
-use crate::utils::constants::BANNED_TOKEN_KEY_PREFIX;
+use crate::utils::constants::{BANNED_TOKEN_KEY_PREFIX, NOT_BEFORE_KEY_PREFIX, REFRESH_TOKEN_TTL_SECONDS};
+use chrono::Utc;
 use color_eyre::eyre::WrapErr;
 use color_eyre::eyre::{eyre, Result};
-use redis::Commands;
-use redis::Connection;
-use std::convert::TryInto;
-use std::sync::Arc;
-use secrecy::{ExposeSecret, Secret};
-use tokio::sync::RwLock;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
 use tracing::{debug, warn};
 
-const BANNED_TOKEN_TTL_SECONDS: u64 = 60 * 60 * 2; // 2 hours
-
+// The connection manager multiplexes every command over one connection and reconnects
+// on its own. Clones are cheap and share that connection, so each call works on its own clone.
+//
 pub struct RedisBannedTokenStore {
-    cx: Arc<RwLock<Connection>>,
+    cx: ConnectionManager,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(cx: Arc<RwLock<Connection>>) -> Self {
+    pub fn new(cx: ConnectionManager) -> Self {
         Self {cx}
     }
 }
@@ -26,13 +25,17 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl TokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "add token", skip_all)]
-    async fn add_token(&mut self, token: &Secret<String>) -> Result<(), TokenStoreError> {
-        let str  = token.expose_secret().as_str();
-        let key  = make_key(BANNED_TOKEN_KEY_PREFIX, str);
-        debug!(?key, "Adding key in Redis");
-        let ttl: u64 = BANNED_TOKEN_TTL_SECONDS.try_into().unwrap_or(60 * 60 * 2);
-        let _: ()    = self.cx.write().await
-           .set_ex(key, true, ttl)
+    async fn add_token(&mut self, jti: &str, exp: usize) -> Result<(), TokenStoreError> {
+        if jti.is_empty() { return Err(TokenStoreError::BlankToken) }
+        let ttl  = exp as i64 - Utc::now().timestamp();   // The ban expires together with the token
+        if ttl <= 0 {
+            debug!("Token has already expired, nothing to ban");
+            return Ok(());
+        }
+        let key  = make_key(BANNED_TOKEN_KEY_PREFIX, jti);
+        debug!(?key, ttl, "Adding key in Redis");
+        let _: () = self.cx.clone()
+           .set_ex(key, true, ttl as u64).await
            .wrap_err("Failed to add token to Redis")
            .map_err(TokenStoreError::UnexpectedError)?;
         Ok(())
@@ -46,8 +49,8 @@ impl TokenStore for RedisBannedTokenStore {
     async fn count(&self) -> Result<u64, TokenStoreError> {
         let prefix  = BANNED_TOKEN_KEY_PREFIX;
         let pattern = format!("{}*", prefix);
-        let mut cx  = self.cx.write().await;
-        let keys: Vec<String> = cx.keys(pattern)
+        let keys: Vec<String> = self.cx.clone()
+           .keys(pattern).await
            .wrap_err("Failed to count banned tokens in Redis")
            .map_err(TokenStoreError::UnexpectedError)?;
         let count   = keys.len();
@@ -61,28 +64,62 @@ impl TokenStore for RedisBannedTokenStore {
         Err(TokenStoreError::UnexpectedError(err))
     }
 
-    #[tracing::instrument(name = "contains token", skip_all)]   
-    async fn contains_token(&self, token: &Secret<String>) -> bool {
-        let str = token.expose_secret().as_str();
-        let key = make_key(BANNED_TOKEN_KEY_PREFIX, str);
+    #[tracing::instrument(name = "contains token", skip_all)]
+    async fn contains_token(&self, jti: &str) -> Result<bool, TokenStoreError> {
+        let key    = make_key(BANNED_TOKEN_KEY_PREFIX, jti);
         debug!(?key, "Checking key in Redis");
-        self.cx.write().await.exists(key).unwrap_or_else(|_| false)
+        let exists = self.cx.clone()
+           .exists(key).await
+           .wrap_err("Failed to check token in Redis")
+           .map_err(TokenStoreError::UnexpectedError)?;
+        Ok(exists)
     }
 
     #[tracing::instrument(name = "delete token", skip_all)]
-    async fn delete_token(&mut self, token: &Secret<String>) -> Result<(), TokenStoreError> {
-        let str = token.expose_secret().as_str();
-        let key = make_key(BANNED_TOKEN_KEY_PREFIX, str);
+    async fn delete_token(&mut self, jti: &str) -> Result<(), TokenStoreError> {
+        let key = make_key(BANNED_TOKEN_KEY_PREFIX, jti);
         debug!(?key, "Deleting key in Redis");
-        let _: () = self.cx
-            .write().await
-            .del(key)
+        let _: () = self.cx.clone()
+            .del(key).await
             .wrap_err("Failed to delete token from Redis")   
             .map_err(TokenStoreError::UnexpectedError)?;
         Ok(())      
     }
+
+    // The epoch has to be remembered for as long as the longest lived credential
+    // issued before it, which is a refresh token.
+    //
+    #[tracing::instrument(name = "set not before", skip_all)]
+    async fn set_not_before(&mut self, subject: &str, epoch: usize) -> Result<(), TokenStoreError> {
+        if subject.is_empty() { return Err(TokenStoreError::BlankToken) }
+        let key   = make_subject_key(subject);
+        let ttl   = REFRESH_TOKEN_TTL_SECONDS as u64;
+        debug!(?key, epoch, "Setting not before epoch in Redis");
+        let _: () = self.cx.clone()
+           .set_ex(key, epoch, ttl).await
+           .wrap_err("Failed to set not before epoch in Redis")
+           .map_err(TokenStoreError::UnexpectedError)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "get not before", skip_all)]
+    async fn get_not_before(&self, subject: &str) -> Result<Option<usize>, TokenStoreError> {
+        let key   = make_subject_key(subject);
+        let epoch = self.cx.clone()
+           .get(key).await
+           .wrap_err("Failed to read not before epoch from Redis")
+           .map_err(TokenStoreError::UnexpectedError)?;
+        Ok(epoch)
+    }
+}
+
+fn make_key(prefix: &str, jti: &str) -> String {
+    format!("{}:{}", prefix, jti)
 }
 
-fn make_key(prefix: &str, token: &str) -> String {
-    format!("{}:{}", prefix, token)
+// Subjects are email addresses. Keep them out of the keyspace.
+//
+fn make_subject_key(subject: &str) -> String {
+    let digest = Sha256::digest(subject.as_bytes());
+    format!("{}:{:x}", NOT_BEFORE_KEY_PREFIX, digest)
 }
\ No newline at end of file
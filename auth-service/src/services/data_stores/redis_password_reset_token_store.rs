@@ -0,0 +1,84 @@
+use color_eyre::eyre::Context;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::domain::{Email, PasswordResetTokenStore, PasswordResetTokenStoreError};
+use crate::utils::constants::{PASSWORD_RESET_TOKEN_PREFIX, PASSWORD_RESET_TTL_SECONDS, PASSWORD_RESET_USER_PREFIX};
+
+// A token's key holds the email it was sent to. A second key per user remembers the digest
+// of the user's latest token, so asking again can delete the token sent before.
+// Both expire with the token.
+//
+pub struct RedisPasswordResetTokenStore {
+	cx: ConnectionManager,
+}
+
+impl RedisPasswordResetTokenStore {
+	pub fn new(cx: ConnectionManager) -> Self {
+		Self { cx }
+	}
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for RedisPasswordResetTokenStore {
+	#[tracing::instrument(name = "add password reset token to redis", skip_all)]
+	async fn add_token(&mut self, token: &Secret<String>, email: Email) -> Result<(), PasswordResetTokenStoreError> {
+		let digest   = make_digest(token);
+		let key      = make_token_key(&digest);
+		let user_key = make_user_key(&email);
+		let ttl      = PASSWORD_RESET_TTL_SECONDS as u64;
+		let mut cx   = self.cx.clone();
+		let previous = cx
+			.get::<_, Option<String>>(&user_key).await
+			.wrap_err("Failed to read password reset token from redis")
+			.map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+		debug!(?key, "Adding password reset token to redis");
+		let mut pipe = redis::pipe();
+		pipe.atomic();
+		if let Some(previous) = previous {
+			pipe.del(make_token_key(&previous)).ignore();
+		}
+		let _: ()    = pipe
+			.set_ex(&key, email.expose_secret(), ttl).ignore()
+			.set_ex(&user_key, &digest, ttl).ignore()
+			.query_async(&mut cx).await
+			.wrap_err("Failed to write password reset token to redis")
+			.map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+		Ok(())
+	}
+
+	// GETDEL, so of two requests presenting the same token only one gets the email.
+	//
+	#[tracing::instrument(name = "take password reset token from redis", skip_all)]
+	async fn take_token(&mut self, token: &Secret<String>) -> Result<Email, PasswordResetTokenStoreError> {
+		let key   = make_token_key(&make_digest(token));
+		let email = redis::cmd("GETDEL")
+			.arg(&key)
+			.query_async::<_, Option<String>>(&mut self.cx.clone()).await
+			.wrap_err("Failed to take password reset token from redis")
+			.map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+		let email = email.ok_or(PasswordResetTokenStoreError::TokenNotFound)?;
+		Email::parse(Secret::new(email))
+			.wrap_err("Invalid email stored with password reset token")
+			.map_err(PasswordResetTokenStoreError::UnexpectedError)
+	}
+}
+
+fn make_digest(token: &Secret<String>) -> String {
+	format!("{:x}", Sha256::digest(token.expose_secret().as_bytes()))
+}
+
+fn make_token_key(digest: &str) -> String {
+	format!("{}:{}", PASSWORD_RESET_TOKEN_PREFIX, digest)
+}
+
+// Keep email addresses out of the keyspace.
+//
+fn make_user_key(email: &Email) -> String {
+	let digest = Sha256::digest(email.expose_secret().as_bytes());
+	format!("{}:{:x}", PASSWORD_RESET_USER_PREFIX, digest)
+}
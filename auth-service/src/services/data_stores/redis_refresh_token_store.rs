@@ -0,0 +1,135 @@
+use crate::domain::{Email, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError};
+use crate::utils::constants::{REFRESH_FAMILY_KEY_PREFIX, REFRESH_TOKEN_KEY_PREFIX, REFRESH_TOKEN_TTL_SECONDS};
+use color_eyre::eyre::Context;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+pub struct RedisRefreshTokenStore {
+	cx: ConnectionManager,
+}
+
+impl RedisRefreshTokenStore {
+	pub fn new(cx: ConnectionManager) -> Self {
+		Self { cx }
+	}
+}
+
+// What we keep in Redis for each refresh token. The token itself is never stored,
+// only a digest of it is used as the key.
+//
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenEntry {
+	email:       String,
+	family_id:   String,
+	issued_at:   usize,
+	#[serde(default)]
+	remember_me: bool,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+	#[tracing::instrument(name = "add refresh token to redis", skip_all)]
+	async fn add_token(&mut self, token: &Secret<String>, record: RefreshTokenRecord) -> Result<(), RefreshTokenStoreError> {
+		let key   = make_token_key(token);
+		let email = record.email.expose_secret().to_owned();
+		let entry = RefreshTokenEntry {
+			email,
+			family_id:   record.family_id,
+			issued_at:   record.issued_at,
+			remember_me: record.remember_me,
+		};
+		debug!(?key, "Adding refresh token to redis");
+		write_entry(&mut self.cx.clone(), &key, &entry).await
+	}
+
+	// A token is marked used by a guard key next to it. SET NX decides which of two racing
+	// refreshes with the same token wins, even across app instances; the other one is treated as a reuse.
+	//
+	#[tracing::instrument(name = "consume refresh token in redis", skip_all)]
+	async fn consume_token(&mut self, token: &Secret<String>) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+		let key    = make_token_key(token);
+		let mut cx = self.cx.clone();
+		let body: Option<String> = cx
+			.get(&key).await
+			.wrap_err("Failed to read refresh token from redis")
+			.map_err(RefreshTokenStoreError::UnexpectedError)?;
+		let body   = body.ok_or(RefreshTokenStoreError::TokenNotFound)?;
+		let entry: RefreshTokenEntry = serde_json::from_str(&body)
+			.wrap_err("Failed to deserialize refresh token retrieved from redis")
+			.map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+		let family_key = make_family_key(&entry.family_id);
+		let revoked: bool = cx
+			.exists(&family_key).await
+			.wrap_err("Failed to read refresh token family from redis")
+			.map_err(RefreshTokenStoreError::UnexpectedError)?;
+		if revoked {
+			return Err(RefreshTokenStoreError::FamilyRevoked);
+		}
+		let options = SetOptions::default()
+			.conditional_set(ExistenceCheck::NX)
+			.with_expiration(SetExpiry::EX(REFRESH_TOKEN_TTL_SECONDS as usize));
+		let marked  = cx
+			.set_options::<_, _, Option<String>>(make_used_key(&key), true, options).await
+			.wrap_err("Failed to mark refresh token used in redis")
+			.map_err(RefreshTokenStoreError::UnexpectedError)?;
+		if marked.is_none() {
+			warn!(family_id = %entry.family_id, "Refresh token reuse detected. Revoking family.");
+			revoke(&mut cx, &family_key).await?;
+			return Err(RefreshTokenStoreError::TokenReused);
+		}
+
+		let email  = Email::parse(Secret::new(entry.email))
+			.wrap_err("Invalid email stored with refresh token")
+			.map_err(RefreshTokenStoreError::UnexpectedError)?;
+		let issued_at   = entry.issued_at;
+		let remember_me = entry.remember_me;
+		Ok(RefreshTokenRecord {email, family_id: entry.family_id, issued_at, remember_me})
+	}
+
+	#[tracing::instrument(name = "revoke refresh token family in redis", skip_all)]
+	async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+		let key = make_family_key(family_id);
+		revoke(&mut self.cx.clone(), &key).await
+	}
+}
+
+async fn write_entry(cx: &mut ConnectionManager, key: &str, entry: &RefreshTokenEntry) -> Result<(), RefreshTokenStoreError> {
+	let ttl     = REFRESH_TOKEN_TTL_SECONDS as u64;
+	let body    = serde_json::to_string(entry)
+		.wrap_err("Failed to serialize refresh token")
+		.map_err(RefreshTokenStoreError::UnexpectedError)?;
+	let _: ()   = cx
+		.set_ex(key, body, ttl).await
+		.wrap_err("Failed to write refresh token to redis")
+		.map_err(RefreshTokenStoreError::UnexpectedError)?;
+	Ok(())
+}
+
+// A revoked family only has to be remembered for as long as any of its tokens could still be presented.
+//
+async fn revoke(cx: &mut ConnectionManager, family_key: &str) -> Result<(), RefreshTokenStoreError> {
+	let ttl   = REFRESH_TOKEN_TTL_SECONDS as u64;
+	let _: () = cx
+		.set_ex(family_key, true, ttl).await
+		.wrap_err("Failed to revoke refresh token family in redis")
+		.map_err(RefreshTokenStoreError::UnexpectedError)?;
+	Ok(())
+}
+
+fn make_token_key(token: &Secret<String>) -> String {
+	let digest = Sha256::digest(token.expose_secret().as_bytes());
+	format!("{}:{:x}", REFRESH_TOKEN_KEY_PREFIX, digest)
+}
+
+fn make_used_key(token_key: &str) -> String {
+	format!("{}:used", token_key)
+}
+
+fn make_family_key(family_id: &str) -> String {
+	format!("{}:{}", REFRESH_FAMILY_KEY_PREFIX, family_id)
+}
@@ -0,0 +1,155 @@
+use crate::domain::{Email, Session, SessionStore, SessionStoreError};
+use crate::utils::constants::{REFRESH_TOKEN_TTL_SECONDS, SESSION_KEY_PREFIX};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tracing::debug;
+
+// All sessions of a user live in one Redis hash, keyed by session id.
+// The hash expires once none of its sessions could be resumed with a refresh token.
+//
+pub struct RedisSessionStore {
+	cx: ConnectionManager,
+}
+
+impl RedisSessionStore {
+	pub fn new(cx: ConnectionManager) -> Self {
+		Self { cx }
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionEntry {
+	id:         String,
+	email:      String,
+	user_agent: Option<String>,
+	ip:         Option<String>,
+	created_at: i64,
+	last_seen:  i64,
+}
+
+impl From<&Session> for SessionEntry {
+	fn from(session: &Session) -> Self {
+		Self {
+			id:         session.id.clone(),
+			email:      session.email.expose_secret().to_owned(),
+			user_agent: session.user_agent.clone(),
+			ip:         session.ip.clone(),
+			created_at: session.created_at.timestamp(),
+			last_seen:  session.last_seen.timestamp(),
+		}
+	}
+}
+
+impl SessionEntry {
+	fn into_session(self) -> Result<Session, SessionStoreError> {
+		let email      = Email::parse(Secret::new(self.email)).map_err(|e| SessionStoreError::UnexpectedError(eyre!(e)))?;
+		let created_at = to_datetime(self.created_at)?;
+		let last_seen  = to_datetime(self.last_seen)?;
+		Ok(Session {id: self.id, email, user_agent: self.user_agent, ip: self.ip, created_at, last_seen})
+	}
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+	#[tracing::instrument(name = "add session to redis", skip_all)]
+	async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+		let key    = make_key(&session.email);
+		let entry  = SessionEntry::from(&session);
+		debug!(?key, "Adding session to redis");
+		write_entry(&mut self.cx.clone(), &key, &entry).await
+	}
+
+	#[tracing::instrument(name = "get sessions from redis", skip_all)]
+	async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+		let key    = make_key(email);
+		let bodies: HashMap<String, String> = self.cx.clone()
+			.hgetall(&key).await
+			.wrap_err("Failed to read sessions from redis")
+			.map_err(SessionStoreError::UnexpectedError)?;
+		let cutoff = Utc::now().timestamp() - REFRESH_TOKEN_TTL_SECONDS;
+		let mut sessions = Vec::with_capacity(bodies.len());
+		for body in bodies.values() {
+			let entry = read_entry(body)?;
+			if entry.last_seen > cutoff {
+				sessions.push(entry.into_session()?);
+			}
+		}
+		sessions.sort_by_key(|s| std::cmp::Reverse(s.last_seen));
+		Ok(sessions)
+	}
+
+	#[tracing::instrument(name = "touch session in redis", skip_all)]
+	async fn touch_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError> {
+		let key    = make_key(email);
+		let mut cx = self.cx.clone();
+		let body: Option<String> = cx
+			.hget(&key, id).await
+			.wrap_err("Failed to read session from redis")
+			.map_err(SessionStoreError::UnexpectedError)?;
+		let body   = body.ok_or(SessionStoreError::SessionNotFound)?;
+		let mut entry  = read_entry(&body)?;
+		entry.last_seen = Utc::now().timestamp();
+		write_entry(&mut cx, &key, &entry).await
+	}
+
+	#[tracing::instrument(name = "remove session from redis", skip_all)]
+	async fn remove_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError> {
+		let key    = make_key(email);
+		let removed: u64 = self.cx.clone()
+			.hdel(&key, id).await
+			.wrap_err("Failed to remove session from redis")
+			.map_err(SessionStoreError::UnexpectedError)?;
+		match removed {
+			0 => Err(SessionStoreError::SessionNotFound),
+			_ => Ok(()),
+		}
+	}
+
+	#[tracing::instrument(name = "remove all sessions from redis", skip_all)]
+	async fn remove_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+		let key   = make_key(email);
+		let _: () = self.cx.clone()
+			.del(&key).await
+			.wrap_err("Failed to remove sessions from redis")
+			.map_err(SessionStoreError::UnexpectedError)?;
+		Ok(())
+	}
+}
+
+async fn write_entry(cx: &mut ConnectionManager, key: &str, entry: &SessionEntry) -> Result<(), SessionStoreError> {
+	let body  = serde_json::to_string(entry)
+		.wrap_err("Failed to serialize session")
+		.map_err(SessionStoreError::UnexpectedError)?;
+	let _: () = redis::pipe()
+		.atomic()
+		.hset(key, &entry.id, body)
+		.expire(key, REFRESH_TOKEN_TTL_SECONDS)
+		.query_async(cx).await
+		.wrap_err("Failed to write session to redis")
+		.map_err(SessionStoreError::UnexpectedError)?;
+	Ok(())
+}
+
+fn read_entry(body: &str) -> Result<SessionEntry, SessionStoreError> {
+	serde_json::from_str(body)
+		.wrap_err("Failed to deserialize session retrieved from redis")
+		.map_err(SessionStoreError::UnexpectedError)
+}
+
+fn to_datetime(timestamp: i64) -> Result<DateTime<Utc>, SessionStoreError> {
+	DateTime::from_timestamp(timestamp, 0)
+		.ok_or(SessionStoreError::UnexpectedError(eyre!("Invalid session timestamp {}", timestamp)))
+}
+
+// Keep email addresses out of the keyspace.
+//
+fn make_key(email: &Email) -> String {
+	let digest = Sha256::digest(email.expose_secret().as_bytes());
+	format!("{}:{:x}", SESSION_KEY_PREFIX, digest)
+}
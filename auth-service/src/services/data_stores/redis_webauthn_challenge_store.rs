@@ -0,0 +1,51 @@
+use color_eyre::eyre::Context;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use tracing::debug;
+
+use crate::domain::data_stores::{WebAuthnChallengeStore, WebAuthnChallengeStoreError};
+use crate::utils::constants::{WEBAUTHN_CHALLENGE_PREFIX, WEBAUTHN_CHALLENGE_TTL_SECONDS};
+
+// Ceremony state lives next to the 2FA codes, and like them expires by itself.
+//
+pub struct RedisWebAuthnChallengeStore {
+	cx: ConnectionManager,
+}
+
+impl RedisWebAuthnChallengeStore {
+	pub fn new(cx: ConnectionManager) -> Self {
+		Self { cx }
+	}
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for RedisWebAuthnChallengeStore {
+	#[tracing::instrument(name = "add webauthn challenge to redis", skip_all)]
+	async fn add_challenge(&mut self, id: &str, state: String) -> Result<(), WebAuthnChallengeStoreError> {
+		let key   = make_key(id);
+		let ttl   = WEBAUTHN_CHALLENGE_TTL_SECONDS as u64;
+		debug!(?key, "Adding webauthn challenge to redis");
+		let _: () = self.cx.clone()
+			.set_ex(&key, state, ttl).await
+			.wrap_err("Failed to write webauthn challenge to redis")
+			.map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+		Ok(())
+	}
+
+	// GETDEL, so of two requests finishing the same ceremony only one gets its state.
+	//
+	#[tracing::instrument(name = "take webauthn challenge from redis", skip_all)]
+	async fn take_challenge(&mut self, id: &str) -> Result<String, WebAuthnChallengeStoreError> {
+		let key   = make_key(id);
+		let state = redis::cmd("GETDEL")
+			.arg(&key)
+			.query_async::<_, Option<String>>(&mut self.cx.clone()).await
+			.wrap_err("Failed to take webauthn challenge from redis")
+			.map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+		state.ok_or(WebAuthnChallengeStoreError::ChallengeNotFound)
+	}
+}
+
+fn make_key(id: &str) -> String {
+	format!("{}:{}", WEBAUTHN_CHALLENGE_PREFIX, id)
+}
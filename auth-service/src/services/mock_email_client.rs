@@ -1,13 +1,39 @@
 use crate::domain::{Email, EmailClient};
 use color_eyre::Result;
+use std::sync::Mutex;
 use tracing::debug;
 
-// MockEmailClient simply logs the recipient, subject, and content to standard output
+// MockEmailClient logs the recipient, subject, and content, and keeps every email it was
+// asked to send, so tests can read the codes that went out by email.
 //
-pub struct MockEmailClient;
+#[derive(Default)]
+pub struct MockEmailClient {
+	sent: Mutex<Vec<(Email, String, String)>>,
+}
 
 impl MockEmailClient {
-	pub fn new() -> Self {Self{}}
+	pub fn new() -> Self {Self::default()}
+
+	pub fn last_email_to(&self, recipient: &Email) -> Option<String> {
+		self.last_email_where(|to, _| to == recipient)
+	}
+
+	pub fn count_emails_to(&self, recipient: &Email) -> usize {
+		let sent = self.sent.lock().expect("MockEmailClient lock poisoned");
+		sent.iter().filter(|(to, _, _)| to == recipient).count()
+	}
+
+	pub fn last_email_containing(&self, needle: &str) -> Option<String> {
+		self.last_email_where(|_, content| content.contains(needle))
+	}
+
+	fn last_email_where(&self, matches: impl Fn(&Email, &str) -> bool) -> Option<String> {
+		let sent = self.sent.lock().expect("MockEmailClient lock poisoned");
+		sent.iter()
+			.rev()
+			.find(|(to, _, content)| matches(to, content))
+			.map(|(_, _, content)| content.clone())
+	}
 }
 
 #[async_trait::async_trait]
@@ -26,6 +52,9 @@ impl EmailClient for MockEmailClient
 			subject,
 			content
 		);
+		self.sent.lock()
+			.expect("MockEmailClient lock poisoned")
+			.push((recipient.clone(), subject.to_owned(), content.to_owned()));
 		Ok(())
 	}
 }
@@ -0,0 +1,34 @@
+use crate::domain::{Email, EmailClient};
+use crate::services::mock_email_client::MockEmailClient;
+use secrecy::Secret;
+
+fn email(address: &str) -> Email {
+	Email::parse(Secret::new(address.to_owned())).unwrap()
+}
+
+#[tokio::test]
+async fn last_email_to_returns_the_latest_email_for_the_recipient() {
+	let client = MockEmailClient::new();
+	let alice  = email("alice@example.com");
+	let bob    = email("bob@example.com");
+	client.send_email(&alice, "Subject", "first").await.unwrap();
+	client.send_email(&bob,   "Subject", "other").await.unwrap();
+	client.send_email(&alice, "Subject", "second").await.unwrap();
+
+	assert_eq!(client.last_email_to(&alice).as_deref(), Some("second"));
+	assert_eq!(client.last_email_to(&bob).as_deref(),   Some("other"));
+	assert_eq!(client.last_email_to(&email("carol@example.com")), None);
+}
+
+#[tokio::test]
+async fn last_email_containing_returns_the_latest_matching_email() {
+	let client = MockEmailClient::new();
+	let alice  = email("alice@example.com");
+	client.send_email(&alice, "Subject", "Code [111111], attempt one").await.unwrap();
+	client.send_email(&alice, "Subject", "Code [222222], attempt two").await.unwrap();
+	client.send_email(&alice, "Subject", "Code [333333], attempt one").await.unwrap();
+
+	assert_eq!(client.last_email_containing("attempt one").as_deref(), Some("Code [333333], attempt one"));
+	assert_eq!(client.last_email_containing("attempt two").as_deref(), Some("Code [222222], attempt two"));
+	assert_eq!(client.last_email_containing("attempt three"), None);
+}
@@ -0,0 +1,36 @@
+use crate::domain::{PhoneNumber, SmsClient};
+use color_eyre::Result;
+use std::sync::Mutex;
+use tracing::debug;
+
+// MockSmsClient logs the recipient and content, and keeps every message it was asked
+// to send, so tests can read the codes that went out by SMS.
+//
+#[derive(Default)]
+pub struct MockSmsClient {
+	sent: Mutex<Vec<(PhoneNumber, String)>>,
+}
+
+impl MockSmsClient {
+	pub fn new() -> Self {Self::default()}
+
+	pub fn last_message_to(&self, recipient: &PhoneNumber) -> Option<String> {
+		let sent = self.sent.lock().expect("MockSmsClient lock poisoned");
+		sent.iter()
+			.rev()
+			.find(|(to, _)| to == recipient)
+			.map(|(_, content)| content.clone())
+	}
+}
+
+#[async_trait::async_trait]
+impl SmsClient for MockSmsClient
+{
+	async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<()> {
+		debug!("Sending SMS to {} with content: {}", recipient.expose_secret(), content);
+		self.sent.lock()
+			.expect("MockSmsClient lock poisoned")
+			.push((recipient.clone(), content.to_owned()));
+		Ok(())
+	}
+}
@@ -0,0 +1,21 @@
+use crate::domain::{PhoneNumber, SmsClient};
+use crate::services::mock_sms_client::MockSmsClient;
+use secrecy::Secret;
+
+fn phone_number(number: &str) -> PhoneNumber {
+	PhoneNumber::parse(Secret::new(number.to_owned())).unwrap()
+}
+
+#[tokio::test]
+async fn last_message_to_returns_the_latest_message_for_the_recipient() {
+	let client = MockSmsClient::new();
+	let alice  = phone_number("+15550100001");
+	let bob    = phone_number("+15550100002");
+	client.send_sms(&alice, "first").await.unwrap();
+	client.send_sms(&bob,   "other").await.unwrap();
+	client.send_sms(&alice, "second").await.unwrap();
+
+	assert_eq!(client.last_message_to(&alice).as_deref(), Some("second"));
+	assert_eq!(client.last_message_to(&bob).as_deref(),   Some("other"));
+	assert_eq!(client.last_message_to(&phone_number("+15550100003")), None);
+}
@@ -0,0 +1,63 @@
+use color_eyre::eyre::Result;                     // For improved error handling and reporting
+use reqwest::{Client, Url};                       // For making HTTP requests
+use secrecy::{ExposeSecret, Secret};              // For securely handling sensitive data
+
+use crate::domain::{PhoneNumber, SmsClient};      // Import domain-specific modules
+
+// SMS request body, form encoded
+// For more information about the request structure, see the API docs:
+// https://www.twilio.com/docs/messaging/api/message-resource#create-a-message-resource
+//
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct SendSmsRequest<'a> {
+	from: &'a str,
+	to:   &'a str,
+	body: &'a str,
+}
+
+// Define the TwilioSmsClient struct
+pub struct TwilioSmsClient {
+	http_client:          Client,                   // HTTP client for making requests
+	base_url:             String,                   // Base URL for the SMS service
+	account_sid:          String,                   // Account the messages are sent from, also the basic auth user
+	sender:               PhoneNumber,              // Phone number of the sender
+	authorization_token:  Secret<String>,           // Authorization token for the SMS service, wrapped in Secret for security
+}
+
+impl TwilioSmsClient {
+	pub fn new(
+		base_url:            String,
+		account_sid:         String,
+		sender:              PhoneNumber,
+		authorization_token: Secret<String>,
+		http_client:         Client,
+	) -> Self {
+		Self {http_client, base_url, account_sid, sender, authorization_token}
+	}
+}
+
+#[async_trait::async_trait]
+impl SmsClient for TwilioSmsClient
+{
+	#[tracing::instrument(name = "Sending SMS", skip_all)]
+	async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<()>
+	{
+		let base   = Url::parse(&self.base_url)?;             // Parse the base URL and join it with the messages endpoint
+		let url    = base.join(&format!("/2010-04-01/Accounts/{}/Messages.json", self.account_sid))?;
+		let body   = SendSmsRequest {
+			from: self.sender.expose_secret(),
+			to:   recipient.expose_secret(),
+			body: content,
+		};
+
+		let auth_token = self.authorization_token.expose_secret();
+		let request    = self.http_client
+			.post(url)
+			.basic_auth(&self.account_sid, Some(auth_token))
+			.form(&body);
+
+		request.send().await?.error_for_status()?;             // Send the request and handle the response
+		Ok(())
+	}
+}
@@ -0,0 +1,100 @@
+use crate::utils::constants::test;
+
+use crate::services::twilio_sms_client::*;
+use fake::faker::lorem::en::Sentence;
+use fake::{Fake, Faker};
+use reqwest::Client;
+use secrecy::Secret;
+use wiremock::matchers::{any, header, header_exists, method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+use crate::domain::PhoneNumber;
+use crate::domain::sms_client::SmsClient;
+
+const ACCOUNT_SID: &str = "AC00000000000000000000000000000000";
+
+// Generate fake data for SMS testing ...
+//
+fn content()      -> String      { Sentence(1..5).fake() }
+fn phone_number() -> PhoneNumber {
+	let digits: u64   = (1_000_000_000..9_999_999_999).fake();
+	let number        = Secret::new(format!("+1{}", digits));
+	PhoneNumber::parse(number).unwrap()
+}
+
+// Helper function to create a test SMS client
+fn sms_client(base_url: String) -> TwilioSmsClient {
+	let http_client = Client::builder()
+		.timeout(test::sms_client::TIMEOUT)
+		.build()
+		.unwrap();
+	TwilioSmsClient::new(base_url, ACCOUNT_SID.to_owned(), phone_number(), Secret::new(Faker.fake()), http_client)
+}
+
+// Custom matcher to validate the form encoded SMS request body
+struct SendSmsBodyMatcher;
+impl wiremock::Match for SendSmsBodyMatcher {
+	fn matches(&self, request: &Request) -> bool {
+		let fields: Vec<(String, String)> = url::form_urlencoded::parse(&request.body).into_owned().collect();
+		let has = |name: &str| fields.iter().any(|(key, value)| key == name && !value.is_empty());
+		has("From") && has("To") && has("Body")
+	}
+}
+
+#[tokio::test]
+async fn send_sms_sends_the_expected_request() {
+	let mock_server = MockServer::start().await;
+	let mock_uri    = mock_server.uri();
+	let sms_client  = sms_client(mock_uri);
+
+	// Set up the mock server to expect a specific request
+	Mock::given(header_exists("Authorization"))
+		.and(header("Content-Type", "application/x-www-form-urlencoded"))
+		.and(path(format!("/2010-04-01/Accounts/{}/Messages.json", ACCOUNT_SID)))
+		.and(method("POST"))
+		.and(SendSmsBodyMatcher)
+		.respond_with(ResponseTemplate::new(201))
+		.expect(1)
+		.mount(&mock_server)
+		.await;
+
+	// Execute the send_sms function and check the outcome
+	let outcome = sms_client.send_sms(&phone_number(), &content()).await;
+	assert!(outcome.is_ok());
+}
+
+// Test to handle server error responses
+#[tokio::test]
+async fn send_sms_fails_if_the_server_returns_500() {
+	let mock_server = MockServer::start().await;
+	let mock_uri    = mock_server.uri();
+	let sms_client  = sms_client(mock_uri);
+
+	Mock::given(any())                                       // Set up the mock server to respond with 500
+		.respond_with(ResponseTemplate::new(500))
+		.expect(1)
+		.mount(&mock_server)
+		.await;
+
+	let outcome = sms_client.send_sms(&phone_number(), &content()).await;
+	assert!(outcome.is_err());
+}
+
+// Test to handle request timeouts
+#[tokio::test]
+async fn send_sms_times_out_if_the_server_takes_too_long() {
+	let mock_server = MockServer::start().await;
+	let mock_uri    = mock_server.uri();
+	let sms_client  = sms_client(mock_uri);
+
+	// Set up the mock server to delay the response
+	let delay    = std::time::Duration::from_secs(180); // Delay 3 minutes
+	let response = ResponseTemplate::new(200).set_delay(delay);
+	Mock::given(any())
+		.respond_with(response)
+		.expect(1)
+		.mount(&mock_server)
+		.await;
+
+	let outcome = sms_client.send_sms(&phone_number(), &content()).await;
+	assert!(outcome.is_err());
+}
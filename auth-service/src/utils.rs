@@ -1,11 +1,32 @@
 
+pub mod audit;
 pub mod auth;
+pub mod code_hasher;
 pub mod constants;
+pub mod encryption;
 pub mod hash_utils;
+pub mod key_ring;
+pub mod recovery_codes;
+pub mod token_policy;
+pub mod totp;
 pub mod tracing;
 pub mod obfuscate;
 
 #[cfg(test)]
 mod auth_tests;
 #[cfg(test)]
+mod code_hasher_tests;
+#[cfg(test)]
+mod constants_tests;
+#[cfg(test)]
+mod encryption_tests;
+#[cfg(test)]
 mod hash_utils_tests;
+#[cfg(test)]
+mod key_ring_tests;
+#[cfg(test)]
+mod recovery_codes_tests;
+#[cfg(test)]
+mod token_policy_tests;
+#[cfg(test)]
+mod totp_tests;
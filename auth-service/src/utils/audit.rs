@@ -0,0 +1,127 @@
+use crate::domain::{AccountTombstone, Email, LoginAttemptId};
+use crate::utils::obfuscate::MaskSecret;
+
+// Security relevant events, logged under their own target so they can be filtered
+// and shipped apart from the application log (RUST_LOG=audit=info).
+// Users are identified by a stable hash of their email, plus a masked email for humans.
+//
+pub const AUDIT_TARGET: &str = "audit";
+
+pub enum AuditEvent<'a> {
+	AccountDeleted {
+		tombstone:        &'a AccountTombstone,
+	},
+	EmailChangeCancelled {
+		email:            &'a Email,
+	},
+	EmailChangeRequested {
+		email:            &'a Email,
+		new_email:        &'a Email,
+	},
+	EmailChanged {
+		email:            &'a Email,
+		new_email:        &'a Email,
+	},
+	EmailVerified {
+		email:            &'a Email,
+	},
+	PasswordChanged {
+		email:            &'a Email,
+	},
+	PasswordReset {
+		email:            &'a Email,
+	},
+	PasswordResetRequested {
+		email:            &'a Email,
+	},
+	TwoFAAttemptsExceeded {
+		email:            &'a Email,
+		login_attempt_id: &'a LoginAttemptId,
+		failures:         u32,
+	},
+	TwoFASettingChanged {
+		email:            &'a Email,
+		enabled:          bool,
+	},
+}
+
+pub fn emit(event: AuditEvent) {
+	match event {
+		// Nothing that leads back to the erased user, only the random id of the stored tombstone
+		AuditEvent::AccountDeleted {tombstone} => tracing::info!(
+			target: AUDIT_TARGET,
+			event            = "account_deleted",
+			tombstone        = %tombstone.id,
+			"Account was deleted and its personal data erased. Every session was revoked."
+		),
+		AuditEvent::EmailChangeCancelled {email} => tracing::info!(
+			target: AUDIT_TARGET,
+			event            = "email_change_cancelled",
+			user             = %email.hash_secret_twox128(),
+			email            = %email.as_ref().masked(),
+			"A change of email address was cancelled from the old address."
+		),
+		AuditEvent::EmailChangeRequested {email, new_email} => tracing::info!(
+			target: AUDIT_TARGET,
+			event            = "email_change_requested",
+			user             = %email.hash_secret_twox128(),
+			email            = %email.as_ref().masked(),
+			new_email        = %new_email.as_ref().masked(),
+			"A change of email address is waiting for confirmation."
+		),
+		AuditEvent::EmailChanged {email, new_email} => tracing::info!(
+			target: AUDIT_TARGET,
+			event            = "email_changed",
+			user             = %email.hash_secret_twox128(),
+			email            = %email.as_ref().masked(),
+			new_user         = %new_email.hash_secret_twox128(),
+			new_email        = %new_email.as_ref().masked(),
+			"Email address was changed. Every session was revoked."
+		),
+		AuditEvent::EmailVerified {email} => tracing::info!(
+			target: AUDIT_TARGET,
+			event            = "email_verified",
+			user             = %email.hash_secret_twox128(),
+			email            = %email.as_ref().masked(),
+			"Email address was verified."
+		),
+		AuditEvent::PasswordChanged {email} => tracing::info!(
+			target: AUDIT_TARGET,
+			event            = "password_changed",
+			user             = %email.hash_secret_twox128(),
+			email            = %email.as_ref().masked(),
+			"Password was changed. Every other session was revoked."
+		),
+		AuditEvent::PasswordReset {email} => tracing::info!(
+			target: AUDIT_TARGET,
+			event            = "password_reset",
+			user             = %email.hash_secret_twox128(),
+			email            = %email.as_ref().masked(),
+			"Password was reset. Every session was revoked."
+		),
+		AuditEvent::PasswordResetRequested {email} => tracing::info!(
+			target: AUDIT_TARGET,
+			event            = "password_reset_requested",
+			user             = %email.hash_secret_twox128(),
+			email            = %email.as_ref().masked(),
+			"A password reset token was emailed."
+		),
+		AuditEvent::TwoFAAttemptsExceeded {email, login_attempt_id, failures} => tracing::warn!(
+			target: AUDIT_TARGET,
+			event            = "2fa_attempts_exceeded",
+			user             = %email.hash_secret_twox128(),
+			email            = %email.as_ref().masked(),
+			login_attempt_id = %login_attempt_id,
+			failures,
+			"Too many wrong 2FA answers. The login attempt was invalidated."
+		),
+		AuditEvent::TwoFASettingChanged {email, enabled} => tracing::info!(
+			target: AUDIT_TARGET,
+			event            = "2fa_setting_changed",
+			user             = %email.hash_secret_twox128(),
+			email            = %email.as_ref().masked(),
+			enabled,
+			"Email 2FA was turned {}.", if enabled {"on"} else {"off"}
+		),
+	}
+}
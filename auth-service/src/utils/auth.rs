@@ -1,22 +1,48 @@
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET, TOKEN_TTL_SECONDS};
-use crate::app_state::TokenStoreType;
+use super::constants::{
+	DEFAULT_TOKEN_SCOPE, DEFAULT_USER_ROLE, EMAIL_VERIFICATION_AUDIENCE, EMAIL_VERIFICATION_SCOPE, EMAIL_VERIFICATION_TTL_SECONDS,
+	JWT_AUDIENCES, JWT_COOKIE_NAME, JWT_ISSUER, KEY_RING, PENDING_2FA_AUDIENCE, PENDING_2FA_COOKIE_NAME, PENDING_2FA_SCOPE,
+	REFRESH_COOKIE_NAME, REFRESH_TOKEN_TTL_SECONDS, TWO_FA_CODE_TTL_SECONDS,
+};
+use crate::app_state::{RefreshTokenStoreType, TokenStoreType};
 use crate::domain::email::Email;
+use crate::domain::{LoginAttemptId, RefreshTokenRecord};
 use axum_extra::extract::cookie::{Cookie, SameSite};
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use color_eyre::eyre::{eyre, Context, Result};
 use color_eyre::Report;
 use jsonwebtoken;
 use jsonwebtoken::errors::Error;
-use jsonwebtoken::{DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{Algorithm, Header, Validation};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{debug, warn};
 
+// Registered claims (RFC 7519, section 4.1). Times are seconds since the epoch.
+// `jti` identifies a single token so it can be revoked on its own.
+// `sid` identifies the session the token was issued to, so a whole session can be revoked.
+// `scope` (space separated, RFC 8693) and `roles` say what the bearer may do.
+// `iat_ms` is `iat` in milliseconds, so a token can be told apart from a revocation in the same second.
+//
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
 	pub sub: String,
 	pub exp: usize,
+	pub iss: String,
+	pub aud: Vec<String>,
+	pub iat: usize,
+	pub nbf: usize,
+	pub jti: String,
+	pub sid: String,
+	#[serde(default)]
+	pub scope: String,
+	#[serde(default)]
+	pub roles: Vec<String>,
+	#[serde(default)]
+	pub iat_ms: usize,
 }
 
 //
@@ -34,74 +60,233 @@ pub enum GenerateTokenError
 	DurationTooLong(String),
 }
 
-// Create cookie with a new JWT auth token
+// Create cookie with a new JWT auth token that lives for `ttl` seconds.
+// The cookie expires together with the token.
 //
 #[tracing::instrument(name = "generate auth cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-	let token  = generate_jwt_auth_token(email)?;
-	let cookie = Cookie::build((JWT_COOKIE_NAME, token))
+pub fn generate_auth_cookie(email: &Email, session_id: &str, ttl: i64) -> Result<Cookie<'static>> {
+	let token   = generate_jwt_auth_token(email, session_id, ttl)?;
+	let max_age = time::Duration::seconds(ttl);
+	let cookie  = Cookie::build((JWT_COOKIE_NAME, token))
 		.path("/")                       // apply cookie to all URLs on the server
 		.http_only(true)                 // prevent JavaScript from accessing the cookie
 		.same_site(SameSite::Lax)        // send cookie with "same-site" requests, and with "cross-site" top-level navigations.
+		.max_age(max_age)
 		.build();
 	Ok(cookie)
 }
 
+// Create the cookie a 2FA user gets for passing the password step. It proves that step for one
+// login attempt and nothing else: its audience is ours alone, so no one accepting auth tokens takes it,
+// and its `sid` is the login attempt. Every step that answers or renews the 2FA challenge requires it,
+// and those live at different paths, so the browser sends it everywhere on this site only.
+// It lives as long as the attempt's code.
+//
+#[tracing::instrument(name = "generate pending 2fa cookie", skip_all)]
+pub fn generate_pending_2fa_cookie(email: &Email, login_attempt_id: &LoginAttemptId) -> Result<Cookie<'static>> {
+	let mut claims = make_claims(email, login_attempt_id.as_ref(), TWO_FA_CODE_TTL_SECONDS)?;
+	claims.aud     = vec![PENDING_2FA_AUDIENCE.to_owned()];
+	claims.scope   = PENDING_2FA_SCOPE.to_owned();
+	claims.roles   = vec![];
+	let token      = create_token(&claims)?;
+	let cookie     = Cookie::build((PENDING_2FA_COOKIE_NAME, token))
+		.path("/")
+		.http_only(true)
+		.same_site(SameSite::Strict)
+		.max_age(time::Duration::seconds(TWO_FA_CODE_TTL_SECONDS))
+		.build();
+	Ok(cookie)
+}
+
+// The claims of an authentic pending 2FA token. Tokens for anyone else are rejected here,
+// just as pending tokens are rejected by `validate_token`.
+//
+#[tracing::instrument(name = "validate pending 2fa token", skip_all)]
+pub fn validate_pending_2fa_token(token: &str) -> Result<Claims> {
+	let header         = jsonwebtoken::decode_header(token);
+	let kid            = header.ok().and_then(|h| h.kid).ok_or(eyre!("Token has no key id"))?;
+	let key            = KEY_RING.get(&kid).ok_or(eyre!("Token signed with unknown key {}", kid))?;
+	let mut validation = make_validation(key.algorithm);
+	validation.set_audience(&[PENDING_2FA_AUDIENCE]);
+	let data           = jsonwebtoken::decode::<Claims>(token, key.decoding_key(), &validation)
+		.wrap_err("Failed to decode pending 2FA token")?;
+	Ok(data.claims)
+}
+
+// Create the token in the link that verifies a new user's email address. Like the pending 2FA token,
+// its audience is ours alone, so it is good for nothing else. Its `jti` is banned once the link
+// is used, so each link works once.
+//
+#[tracing::instrument(name = "generate email verification token", skip_all)]
+pub fn generate_email_verification_token(email: &Email) -> Result<String> {
+	let mut claims = make_claims(email, "", EMAIL_VERIFICATION_TTL_SECONDS)?;
+	claims.aud     = vec![EMAIL_VERIFICATION_AUDIENCE.to_owned()];
+	claims.scope   = EMAIL_VERIFICATION_SCOPE.to_owned();
+	claims.roles   = vec![];
+	create_token(&claims)
+}
+
+// The claims of an authentic email verification token that has not been used yet.
+//
+#[tracing::instrument(name = "validate email verification token", skip_all)]
+pub async fn validate_email_verification_token(token: &str, banned_tokens: TokenStoreType) -> Result<Claims> {
+	let header         = jsonwebtoken::decode_header(token);
+	let kid            = header.ok().and_then(|h| h.kid).ok_or(eyre!("Token has no key id"))?;
+	let key            = KEY_RING.get(&kid).ok_or(eyre!("Token signed with unknown key {}", kid))?;
+	let mut validation = make_validation(key.algorithm);
+	validation.set_audience(&[EMAIL_VERIFICATION_AUDIENCE]);
+	let data           = jsonwebtoken::decode::<Claims>(token, key.decoding_key(), &validation)
+		.wrap_err("Failed to decode email verification token")?;
+	if banned_tokens.read().await.contains_token(&data.claims.jti).await? {
+		return Err(eyre!("Email verification token was already used"));
+	}
+	Ok(data.claims)
+}
+
+// Create cookie with a new refresh token and record the token in the refresh token store.
+// The token family is the session: a login starts a new one, a rotation passes the existing one along.
+//
+#[tracing::instrument(name = "generate refresh cookie", skip_all)]
+pub async fn generate_refresh_cookie(
+	email:          &Email,
+	family_id:      &str,
+	remember_me:    bool,
+	refresh_tokens: RefreshTokenStoreType,
+	) -> Result<Cookie<'static>> {
+	let token     = generate_refresh_token();
+	let record    = RefreshTokenRecord::new(email.clone(), family_id.to_owned(), remember_me);
+	refresh_tokens.write().await
+		.add_token(&token, record).await
+		.wrap_err("Failed to store refresh token")?;
+
+	let max_age   = time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS);
+	let cookie    = Cookie::build((REFRESH_COOKIE_NAME, token.expose_secret().to_owned()))
+		.path("/")
+		.http_only(true)
+		.same_site(SameSite::Strict)     // the refresh token is only ever sent to this service
+		.max_age(max_age)
+		.build();
+	Ok(cookie)
+}
+
+// Refresh tokens are opaque. 32 random bytes, base64 (url safe) encoded.
+//
+pub fn generate_refresh_token() -> Secret<String> {
+	let mut bytes = [0u8; 32];
+	rand::thread_rng().fill_bytes(&mut bytes);
+	Secret::new(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+// Password reset tokens are opaque too, and only ever stored as a digest.
+//
+pub fn generate_password_reset_token() -> Secret<String> {
+	generate_refresh_token()
+}
+
+// As are the tokens that confirm or cancel a change of email address.
+//
+pub fn generate_email_change_token() -> Secret<String> {
+	generate_refresh_token()
+}
+
 #[tracing::instrument(name = "generate JWT token", skip_all)]
-pub fn generate_jwt_auth_token(email: &Email) -> Result<String> {
-	let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS);
-	let delta = delta.ok_or(eyre!("Too long to live"))
+pub fn generate_jwt_auth_token(email: &Email, session_id: &str, ttl: i64) -> Result<String> {
+	let claims = make_claims(email, session_id, ttl)?;
+	create_token(&claims)
+}
+
+// Claims of an auth token for the session, living `ttl` seconds from now.
+//
+fn make_claims(email: &Email, session_id: &str, ttl: i64) -> Result<Claims> {
+	let delta  = chrono::Duration::try_seconds(ttl);
+	let delta  = delta.ok_or(eyre!("Too long to live"))
 		.map_err(|e| GenerateTokenError::DurationTooLong(format!("{}", e)))?;
 
-	let exp = Utc::now()                                    // Create JWT expiration time
+	let issued = Utc::now();
+	let exp    = issued                                     // Create JWT expiration time
 		.checked_add_signed(delta)
 		.ok_or(GenerateTokenError::UnexpectedError(eyre!("Bad time")))?
 		.timestamp();
 
-	let exp = (Utc::now() + Duration::seconds(3600)).timestamp(); // expires in 1 hour
-	
 	let exp    = exp as usize;                              // Cast exp to usize, (what Claims expects)
-	let sub    = email.expose_secret().to_owned();
-	let claims = Claims {sub, exp};
-	create_token(&claims)
+	let now    = issued.timestamp() as usize;
+	let now_ms = issued.timestamp_millis() as usize;
+	let claims = Claims {
+		sub: email.expose_secret().to_owned(),
+		exp,
+		iss: JWT_ISSUER.clone(),
+		aud: JWT_AUDIENCES.clone(),
+		iat: now,
+		nbf: now,
+		jti: uuid::Uuid::new_v4().to_string(),
+		sid: session_id.to_owned(),
+		scope: DEFAULT_TOKEN_SCOPE.to_owned(),
+		roles: vec![DEFAULT_USER_ROLE.to_owned()],
+		iat_ms: now_ms,
+	};
+	Ok(claims)
 }
 
-// Check if JWT auth-token is valid by decoding it using the JWT secret
+// Check if JWT auth-token is valid by verifying it against the key named in its `kid` header.
+// Only an authentic token is looked up in the banned token store, by its `jti` and its `sid`.
 //
 #[tracing::instrument(name = "validate token", skip_all)]
 pub async fn validate_token(
 	token:           &str,
 	banned_tokens:   TokenStoreType,
 	) -> Result<Claims> {
-	let token = Secret::new(token.to_owned());
-	match banned_tokens.read().await.contains_token(&token).await {
-		true => { return Err(eyre!("Token is banned")) },
-		_    => {}
-	}
-
-	debug!("Token is not banned, decoding it");
-	let bytes      = JWT_SECRET.expose_secret().as_bytes();
-	let key        = DecodingKey::from_secret(bytes);
-	let validation = &Validation::default();
-	let token_data = token.expose_secret();
-	let data       = jsonwebtoken::decode::<Claims>(token_data, &key, &validation);
+	let header     = jsonwebtoken::decode_header(token);
+	let kid        = header.ok().and_then(|h| h.kid).ok_or(eyre!("Token has no key id"))?;
+	let key        = KEY_RING.get(&kid).ok_or(eyre!("Token signed with unknown key {}", kid))?;
+	let validation = make_validation(key.algorithm);
+	let data       = jsonwebtoken::decode::<Claims>(token, key.decoding_key(), &validation);
 	if let Err(e) = data {
 		warn!("Failed to decode token: {:?}", e);
 		return Err(eyre!("Failed to decode token"));
 	}
-	let claims     = data.map(|v| v.claims).wrap_err("Failed to decode token");
+	let claims     = data.map(|v| v.claims).wrap_err("Failed to decode token")?;
 	debug!("\t{:?}", claims);
-	claims
+
+	debug!("Token is authentic, checking whether it was revoked");
+	let store      = banned_tokens.read().await;
+	match store.contains_token(&claims.jti).await? {
+		true => { return Err(eyre!("Token is banned")) },
+		_    => {}
+	}
+	if store.contains_token(&claims.sid).await? {
+		return Err(eyre!("Token belongs to a revoked session"));
+	}
+	let epoch      = store.get_not_before(&claims.sub).await?;
+	if is_before_epoch(claims.iat_ms, epoch) {
+		return Err(eyre!("Token was issued before the subject's revocation epoch"));
+	}
+	Ok(claims)
+}
+
+// Both times are milliseconds since the Unix epoch. Seconds are too coarse: a credential issued
+// right after a revocation would often share its second, and be treated as revoked.
+//
+pub fn is_before_epoch(issued_at: usize, epoch: Option<usize>) -> bool {
+	epoch.is_some_and(|epoch| issued_at <= epoch)
+}
+
+// Only accept tokens we issued, for one of our audiences, that are already valid.
+//
+fn make_validation(algorithm: Algorithm) -> Validation {
+	let mut validation = Validation::new(algorithm);
+	validation.set_issuer(&[JWT_ISSUER.as_str()]);
+	validation.set_audience(&JWT_AUDIENCES);
+	validation.set_required_spec_claims(&["sub", "exp", "iss", "aud", "iat", "nbf"]);
+	validation.validate_nbf = true;
+	validation
 }
 
-// Create JWT auth token by encoding claims using the JWT secret
+// Create JWT auth token by signing claims with the active key of the key ring
 //
 #[tracing::instrument(name = "encode claims into token", skip_all)]
-fn create_token(claims: &Claims) -> Result<String> {
-	let bytes  = JWT_SECRET.expose_secret().as_bytes();
-	jsonwebtoken::encode(
-		&jsonwebtoken::Header::default(),
-		&claims,
-		&EncodingKey::from_secret(bytes),
-	).wrap_err("Failed to create token")
+pub(crate) fn create_token(claims: &Claims) -> Result<String> {
+	let key        = KEY_RING.active();
+	let mut header = Header::new(key.algorithm);
+	header.kid     = Some(key.kid.clone());
+	jsonwebtoken::encode(&header, &claims, key.encoding_key()).wrap_err("Failed to create token")
 }
@@ -1,6 +1,7 @@
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Utc;
 use crate::domain::email::Email;
+use crate::domain::{LoginAttemptId, TokenStore};
 use crate::services::data_stores::hashset_token_store::HashSetTokenStore;
 use crate::utils::auth::*;
 use crate::utils::constants::*;
@@ -19,7 +20,7 @@ fn assert_basic_cookie_properties(c: &Cookie) {
 async fn test_generate_auth_cookie() {
 	let email   = Secret::new("test@example.com".to_owned());
 	let email  = Email::parse(email).unwrap();
-	let cookie = generate_auth_cookie(&email).unwrap();
+	let cookie = generate_auth_cookie(&email, "session-1", TOKEN_POLICY.access_ttl).unwrap();
 	assert_basic_cookie_properties(&cookie);
 	assert_eq!(cookie.value().split('.').count(), 3);
 }
@@ -28,7 +29,7 @@ async fn test_generate_auth_cookie() {
 async fn test_generate_auth_token_result_has_3_parts() {
 	let email   = Secret::new("test@example.com".to_owned());
 	let email  = Email::parse(email).unwrap();
-	let result = generate_jwt_auth_token(&email).unwrap();
+	let result = generate_jwt_auth_token(&email, "session-1", TOKEN_POLICY.access_ttl).unwrap();
 	assert_eq!(result.split('.').count(), 3);
 }
 
@@ -36,7 +37,7 @@ async fn test_generate_auth_token_result_has_3_parts() {
 async fn test_valid_token_passes_validation() {
 	let email   = Secret::new("a@b.com".to_owned());
 	let email         = Email::parse(email).unwrap();
-	let token         = generate_jwt_auth_token(&email).unwrap();
+	let token         = generate_jwt_auth_token(&email, "session-1", TOKEN_POLICY.access_ttl).unwrap();
 	let banned_tokens =  Arc::new(RwLock::new(HashSetTokenStore::new()));
 	let result        = validate_token(token.as_str(), banned_tokens).await.unwrap();
 	assert_eq!(result.sub, "a@b.com");
@@ -55,3 +56,144 @@ async fn test_invalid_token_fails_validation() {
 	let result        = validate_token(&token, banned_tokens).await;
 	assert!(result.is_err());
 }
+
+fn claims_for(email: &str) -> Claims {
+	let now = Utc::now().timestamp() as usize;
+	Claims {
+		sub: email.to_owned(),
+		exp: now + 600,
+		iss: JWT_ISSUER.clone(),
+		aud: JWT_AUDIENCES.clone(),
+		iat: now,
+		nbf: now,
+		jti: uuid::Uuid::new_v4().to_string(),
+		sid: "session-1".to_owned(),
+		scope: DEFAULT_TOKEN_SCOPE.to_owned(),
+		roles: vec![DEFAULT_USER_ROLE.to_owned()],
+		iat_ms: now * 1000,
+	}
+}
+
+async fn validate(claims: &Claims) -> color_eyre::Result<Claims> {
+	let token         = create_token(claims).unwrap();
+	let banned_tokens = Arc::new(RwLock::new(HashSetTokenStore::new()));
+	validate_token(&token, banned_tokens).await
+}
+
+#[tokio::test]
+async fn test_token_carries_standard_claims() {
+	let email   = Secret::new("a@b.com".to_owned());
+	let email   = Email::parse(email).unwrap();
+	let token   = generate_jwt_auth_token(&email, "session-1", TOKEN_POLICY.access_ttl).unwrap();
+	let banned  = Arc::new(RwLock::new(HashSetTokenStore::new()));
+	let claims  = validate_token(&token, banned).await.unwrap();
+	assert_eq!(claims.iss, *JWT_ISSUER);
+	assert_eq!(claims.aud, *JWT_AUDIENCES);
+	assert!(claims.iat <= claims.nbf && claims.nbf < claims.exp);
+	assert!(uuid::Uuid::parse_str(&claims.jti).is_ok());
+}
+
+#[tokio::test]
+async fn test_every_token_has_a_unique_jti() {
+	let email   = Secret::new("a@b.com".to_owned());
+	let email   = Email::parse(email).unwrap();
+	let first   = generate_jwt_auth_token(&email, "session-1", TOKEN_POLICY.access_ttl).unwrap();
+	let second  = generate_jwt_auth_token(&email, "session-1", TOKEN_POLICY.access_ttl).unwrap();
+	let banned  = Arc::new(RwLock::new(HashSetTokenStore::new()));
+	let first   = validate_token(&first,  banned.clone()).await.unwrap();
+	let second  = validate_token(&second, banned).await.unwrap();
+	assert_ne!(first.jti, second.jti);
+}
+
+#[tokio::test]
+async fn test_token_from_another_issuer_fails_validation() {
+	let mut claims = claims_for("a@b.com");
+	claims.iss     = "someone-else".to_owned();
+	assert!(validate(&claims).await.is_err());
+}
+
+#[tokio::test]
+async fn test_token_for_another_audience_fails_validation() {
+	let mut claims = claims_for("a@b.com");
+	claims.aud     = vec!["some-other-service".to_owned()];
+	assert!(validate(&claims).await.is_err());
+}
+
+#[tokio::test]
+async fn test_token_not_yet_valid_fails_validation() {
+	let mut claims = claims_for("a@b.com");
+	claims.nbf     = claims.iat + 300;                     // Beyond the default leeway of 60 seconds
+	assert!(validate(&claims).await.is_err());
+}
+
+#[tokio::test]
+async fn test_only_the_banned_token_fails_validation() {
+	let banned_claims = claims_for("a@b.com");
+	let other_claims  = claims_for("a@b.com");
+	let banned_token  = create_token(&banned_claims).unwrap();
+	let other_token   = create_token(&other_claims).unwrap();
+	let banned_tokens = Arc::new(RwLock::new(HashSetTokenStore::new()));
+	banned_tokens.write().await.add_token(&banned_claims.jti, banned_claims.exp).await.unwrap();
+	assert!(validate_token(&banned_token, banned_tokens.clone()).await.is_err());
+	assert!(validate_token(&other_token,  banned_tokens).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_tokens_issued_up_to_the_epoch_fail_validation() {
+	let mut claims    = claims_for("a@b.com");
+	claims.iat_ms     -= 10;
+	let token         = create_token(&claims).unwrap();
+	let banned_tokens = Arc::new(RwLock::new(HashSetTokenStore::new()));
+	banned_tokens.write().await.set_not_before("a@b.com", claims.iat_ms).await.unwrap();
+	assert!(validate_token(&token, banned_tokens).await.is_err());
+}
+
+#[tokio::test]
+async fn test_tokens_issued_after_the_epoch_pass_validation() {
+	let claims        = claims_for("a@b.com");
+	let token         = create_token(&claims).unwrap();
+	let banned_tokens = Arc::new(RwLock::new(HashSetTokenStore::new()));
+	banned_tokens.write().await.set_not_before("a@b.com", claims.iat_ms - 1).await.unwrap();
+	assert!(validate_token(&token, banned_tokens).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_tokens_of_a_revoked_session_fail_validation() {
+	let claims        = claims_for("a@b.com");
+	let token         = create_token(&claims).unwrap();
+	let banned_tokens = Arc::new(RwLock::new(HashSetTokenStore::new()));
+	banned_tokens.write().await.add_token(&claims.sid, claims.exp).await.unwrap();
+	assert!(validate_token(&token, banned_tokens).await.is_err());
+}
+
+#[tokio::test]
+async fn test_pending_2fa_cookie_is_strict_and_names_the_login_attempt() {
+	let email   = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let attempt = LoginAttemptId::default();
+	let cookie  = generate_pending_2fa_cookie(&email, &attempt).unwrap();
+	assert_eq!(cookie.name(),      PENDING_2FA_COOKIE_NAME);
+	assert_eq!(cookie.path(),      Some("/"));
+	assert_eq!(cookie.http_only(), Some(true));
+	assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+
+	let claims  = validate_pending_2fa_token(cookie.value()).unwrap();
+	assert_eq!(claims.sub,   "a@b.com");
+	assert_eq!(claims.sid,   attempt.as_ref().to_owned());
+	assert_eq!(claims.scope, PENDING_2FA_SCOPE);
+	assert!(claims.roles.is_empty());
+}
+
+#[tokio::test]
+async fn test_pending_2fa_token_is_not_an_auth_token() {
+	let email   = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let cookie  = generate_pending_2fa_cookie(&email, &LoginAttemptId::default()).unwrap();
+	let banned  = Arc::new(RwLock::new(HashSetTokenStore::new()));
+	assert!(validate_token(cookie.value(), banned).await.is_err());
+}
+
+#[tokio::test]
+async fn test_auth_token_is_not_a_pending_2fa_token() {
+	let email   = Email::parse(Secret::new("a@b.com".to_owned())).unwrap();
+	let token   = generate_jwt_auth_token(&email, "session-1", TOKEN_POLICY.access_ttl).unwrap();
+	assert!(validate_pending_2fa_token(&token).is_err());
+}
@@ -0,0 +1,52 @@
+use crate::domain::{TwoFACode, TwoFACodeHash};
+use color_eyre::eyre::{eyre, Result};
+use data_encoding::HEXLOWER;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+
+const MIN_KEY_BYTES: usize = 32;
+
+// HMAC-SHA256 of 2FA codes, so stores never hold a code they could give away.
+// A code has only a million values, so a plain hash would be reversed in no time.
+// The context, e.g. the login attempt id, binds a hash to what the code was sent for,
+// so the same code sent twice hashes differently, and a hash copied elsewhere does not match.
+//
+pub struct CodeHasher {
+	key: Secret<Vec<u8>>,
+}
+
+impl CodeHasher {
+	pub fn new(key: &[u8]) -> Result<Self> {
+		if key.len() < MIN_KEY_BYTES {
+			return Err(eyre!("Code hash key must be at least {} bytes, not {}", MIN_KEY_BYTES, key.len()));
+		}
+		Ok(Self {key: Secret::new(key.to_vec())})
+	}
+
+	// A freshly generated key. Codes hashed with it cannot be checked after a restart,
+	// so this is only suitable for development and tests.
+	//
+	pub fn ephemeral() -> Self {
+		let mut key = vec![0u8; MIN_KEY_BYTES];
+		rand::thread_rng().fill_bytes(&mut key);
+		Self {key: Secret::new(key)}
+	}
+
+	pub fn hash(&self, code: &TwoFACode, context: &str) -> TwoFACodeHash {
+		let digest  = HEXLOWER.encode(&self.tag(code.as_ref(), context));
+		TwoFACodeHash::parse(digest).expect("A hex encoded SHA-256 digest is a valid code hash")
+	}
+
+	// The raw HMAC of any value. Only someone with the key can tell which value a tag belongs to,
+	// and the context keeps tags made for one purpose from matching those made for another.
+	//
+	pub fn tag(&self, value: &str, context: &str) -> Vec<u8> {
+		let mut mac = Hmac::<Sha256>::new_from_slice(self.key.expose_secret()).expect("HMAC accepts keys of any length");
+		mac.update(context.as_bytes());
+		mac.update(&[0]);
+		mac.update(value.as_bytes());
+		mac.finalize().into_bytes().to_vec()
+	}
+}
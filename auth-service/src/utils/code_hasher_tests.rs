@@ -0,0 +1,56 @@
+use super::code_hasher::CodeHasher;
+use crate::domain::{TwoFACode, TwoFACodeHash};
+
+#[test]
+fn the_same_code_and_context_give_a_matching_hash() {
+	let hasher = CodeHasher::ephemeral();
+	let code   = TwoFACode::parse("123456".to_owned()).unwrap();
+	let first  = hasher.hash(&code, "attempt");
+	let second = hasher.hash(&code, "attempt");
+	assert!(first.is_match(&second));
+	assert!(!first.as_ref().contains("123456"));
+}
+
+#[test]
+fn another_code_gives_another_hash() {
+	let hasher = CodeHasher::ephemeral();
+	let first  = hasher.hash(&TwoFACode::parse("123456".to_owned()).unwrap(), "attempt");
+	let second = hasher.hash(&TwoFACode::parse("123457".to_owned()).unwrap(), "attempt");
+	assert!(!first.is_match(&second));
+}
+
+#[test]
+fn another_context_gives_another_hash() {
+	let hasher = CodeHasher::ephemeral();
+	let code   = TwoFACode::parse("123456".to_owned()).unwrap();
+	assert!(!hasher.hash(&code, "attempt").is_match(&hasher.hash(&code, "another attempt")));
+}
+
+#[test]
+fn another_key_gives_another_hash() {
+	let code   = TwoFACode::parse("123456".to_owned()).unwrap();
+	let first  = CodeHasher::ephemeral().hash(&code, "attempt");
+	let second = CodeHasher::ephemeral().hash(&code, "attempt");
+	assert!(!first.is_match(&second));
+}
+
+#[test]
+fn a_known_key_gives_a_stable_hash() {
+	let hasher = CodeHasher::new(&[7u8; 32]).unwrap();
+	let code   = TwoFACode::parse("123456".to_owned()).unwrap();
+	let stored = TwoFACodeHash::parse(hasher.hash(&code, "attempt").as_ref().to_owned()).unwrap();
+	assert!(CodeHasher::new(&[7u8; 32]).unwrap().hash(&code, "attempt").is_match(&stored));
+}
+
+#[test]
+fn tags_are_stable_and_depend_on_the_context() {
+	let hasher = CodeHasher::new(&[7u8; 32]).unwrap();
+	assert_eq!(hasher.tag("a@b.com", "decoy"), hasher.tag("a@b.com", "decoy"));
+	assert_ne!(hasher.tag("a@b.com", "decoy"), hasher.tag("a@b.com", "another purpose"));
+	assert_ne!(hasher.tag("a@b.com", "decoy"), CodeHasher::ephemeral().tag("a@b.com", "decoy"));
+}
+
+#[test]
+fn short_keys_are_rejected() {
+	assert!(CodeHasher::new(&[7u8; 16]).is_err());
+}
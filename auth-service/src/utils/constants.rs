@@ -1,21 +1,78 @@
 use dotenvy::dotenv;
 use lazy_static::lazy_static;
+use std::collections::HashMap;
 use std::env as std_env;
+use std::path::Path;
 use log::warn;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use secrecy::Secret;
+use super::code_hasher::CodeHasher;
+use super::encryption::SecretCipher;
+use super::key_ring::KeyRing;
+use super::token_policy::TokenPolicy;
+use url::Url;
+use webauthn_rs::{Webauthn, WebauthnBuilder};
 
-pub const DEFAULT_REDIS_HOSTNAME:  &str = "127.0.0.1";
-pub const JWT_COOKIE_NAME:         &str = "jwt";
-pub const ACTIVE_TOKEN_KEY_PREFIX: &str = "2FA:Tokens:Active";
-pub const BANNED_TOKEN_KEY_PREFIX: &str = "2FA:Tokens:Banned";
-pub const TOKEN_TTL_SECONDS:       i64  = 600; // 10 minutes
+pub const DEFAULT_REDIS_HOSTNAME:    &str = "127.0.0.1";
+pub const DEFAULT_JWT_ISSUER:        &str = "auth-service";
+pub const DEFAULT_JWT_AUDIENCE:      &str = "app-service";
+pub const DEFAULT_TOKEN_SCOPE:       &str = "app";
+pub const DEFAULT_USER_ROLE:         &str = "user";
+pub const TOTP_ISSUER:               &str = "auth-service";
+pub const JWT_COOKIE_NAME:           &str = "jwt";
+pub const PENDING_2FA_COOKIE_NAME:   &str = "pending_2fa";
+pub const PENDING_2FA_AUDIENCE:      &str = "auth-service:verify-2fa";   // Never one of JWT_AUDIENCES
+pub const PENDING_2FA_SCOPE:         &str = "2fa";
+pub const EMAIL_VERIFICATION_AUDIENCE: &str = "auth-service:verify-email"; // Never one of JWT_AUDIENCES
+pub const EMAIL_VERIFICATION_SCOPE:  &str = "verify-email";
+pub const EMAIL_VERIFICATION_TTL_SECONDS: i64 = 60 * 60 * 24; // 1 day to open the link
+pub const EMAIL_CHANGE_TTL_SECONDS:  i64  = 60 * 60 * 24; // 1 day to confirm or cancel a change of address
+pub const ACTIVE_TOKEN_KEY_PREFIX:   &str = "2FA:Tokens:Active";
+pub const BANNED_TOKEN_KEY_PREFIX:   &str = "2FA:Tokens:Banned";
+pub const NOT_BEFORE_KEY_PREFIX:     &str = "2FA:Tokens:NotBefore";
+pub const TOKEN_TTL_SECONDS:         i64  = 600; // 10 minutes
+pub const REMEMBER_ME_TTL_SECONDS:   i64  = 60 * 60 * 24; // 1 day
+pub const REFRESH_COOKIE_NAME:       &str = "refresh";
+pub const REFRESH_TOKEN_KEY_PREFIX:  &str = "2FA:Tokens:Refresh";
+pub const REFRESH_FAMILY_KEY_PREFIX: &str = "2FA:Tokens:RefreshFamily";
+pub const REFRESH_TOKEN_TTL_SECONDS: i64  = 60 * 60 * 24 * 14; // 14 days
+pub const SESSION_KEY_PREFIX:        &str = "2FA:Sessions";
+pub const PASSWORD_RESET_TOKEN_PREFIX: &str = "2FA:PasswordReset:Token";
+pub const PASSWORD_RESET_USER_PREFIX:  &str = "2FA:PasswordReset:User";
+pub const PASSWORD_RESET_TTL_SECONDS:  i64  = 60 * 30; // 30 minutes to use the emailed token
+pub const TWO_FA_CODE_TTL_SECONDS:   i64  = 600; // 10 minutes
+pub const DEFAULT_MAX_2FA_ATTEMPTS:  u32  = 5;   // Wrong answers before a login attempt is invalidated
+pub const TWO_FA_RESEND_COOLDOWN_SECONDS: i64 = 30; // Between two resends of the emailed code
+pub const MAX_2FA_RESENDS:           u32  = 3;   // Resends per login attempt
+pub const WEBAUTHN_CHALLENGE_PREFIX: &str = "2FA:WebAuthn:Challenge";
+pub const WEBAUTHN_CHALLENGE_TTL_SECONDS: i64 = 300; // 5 minutes, as long as a browser waits for the authenticator
+pub const WEBAUTHN_RP_NAME:          &str = "auth-service";
+pub const STORE_BACKEND_POSTGRES:    &str = "postgres";
+pub const STORE_BACKEND_REDIS:       &str = "redis";
+pub const EXPIRED_ROW_PURGE_PERIOD:  std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+pub type ClientSecrets = HashMap<String, Secret<String>>;     // client id -> client secret
 
 lazy_static! {
-	pub static ref JWT_SECRET:          Secret<String> = set_token();
-	pub static ref DATABASE_URL:        Secret<String> = set_db_url();
+	pub static ref KEY_RING:              KeyRing        = set_key_ring();
+	pub static ref JWT_ISSUER:            String         = set_jwt_issuer();
+	pub static ref JWT_AUDIENCES:         Vec<String>    = set_jwt_audiences();
+	pub static ref AUTH_SERVICE_URL:      String         = set_auth_service_url();
+	pub static ref TOKEN_POLICY:          TokenPolicy    = set_token_policy();
+	pub static ref TOTP_CIPHER:           SecretCipher   = set_totp_cipher();
+	pub static ref TWO_FA_CODE_HASHER:    CodeHasher     = set_two_fa_code_hasher();
+	pub static ref INTROSPECTION_CLIENTS: ClientSecrets  = set_introspection_clients();
+	pub static ref DATABASE_URL:          Secret<String> = set_db_url();
 //	pub static ref POSTGRES_PASSWORD: String           = set_pg_password();
-	pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();	
-	pub static ref REDIS_HOST_NAME:     String         = set_redis_host();
+	pub static ref POSTMARK_AUTH_TOKEN:   Secret<String> = set_postmark_auth_token();	
+	pub static ref TWILIO_ACCOUNT_SID:    String         = set_twilio_account_sid();
+	pub static ref TWILIO_AUTH_TOKEN:     Secret<String> = set_twilio_auth_token();
+	pub static ref TWILIO_SENDER:         String         = set_twilio_sender();
+	pub static ref REDIS_HOST_NAME:       String         = set_redis_host();
+	pub static ref STORE_BACKEND:         String         = set_store_backend();
+	pub static ref WEBAUTHN:              Webauthn       = set_webauthn();
+	pub static ref MAX_2FA_ATTEMPTS:      u32            = set_max_2fa_attempts();
 }
 
 fn set_postmark_auth_token() -> Secret<String> {
@@ -24,6 +81,24 @@ fn set_postmark_auth_token() -> Secret<String> {
 	Secret::new(token)
 }
 
+fn set_twilio_account_sid() -> String {
+	dotenv().ok();
+	std_env::var(env::TWILIO_ACCOUNT_SID_ENV_VAR).expect("TWILIO_ACCOUNT_SID not set")
+}
+
+fn set_twilio_auth_token() -> Secret<String> {
+	dotenv().ok();
+	let token = std_env::var(env::TWILIO_AUTH_TOKEN_ENV_VAR).expect("TWILIO_AUTH_TOKEN not set");
+	Secret::new(token)
+}
+
+// The number 2FA codes are sent from, in E.164 form.
+//
+fn set_twilio_sender() -> String {
+	dotenv().ok();
+	std_env::var(env::TWILIO_SENDER_ENV_VAR).expect("TWILIO_SENDER not set")
+}
+
 /*
 fn set_pg_password() -> String {
 	dotenv().ok();
@@ -38,16 +113,118 @@ fn set_pg_password() -> String {
 }
 */
 
-fn set_token() -> Secret<String> {
+// JWT signing keys are read from the directory named by JWT_KEYS_DIR.
+// Every instance must sign with the same keys, and keep them across restarts.
+//
+fn set_key_ring() -> KeyRing {
+	dotenv().ok();
+	let active_kid = std_env::var(env::JWT_ACTIVE_KID_ENV_VAR).ok();
+	let dir        = std_env::var(env::JWT_KEYS_DIR_ENV_VAR).expect("JWT_KEYS_DIR not set");
+	if dir.trim().is_empty() {
+		panic!("JWT_KEYS_DIR must not be empty. Set it in the .env file or in environment variables.");
+	}
+	KeyRing::load(Path::new(dir.trim()), active_kid.as_deref()).expect("Failed to load JWT signing keys")
+}
+
+// Where users reach this service, for links in the emails it sends.
+//
+fn set_auth_service_url() -> String {
+	dotenv().ok();
+	let url = std_env::var(env::AUTH_SERVICE_URL_ENV_VAR).ok().filter(|url| !url.trim().is_empty());
+	let url = url.unwrap_or(prod::URI_AUTH.to_owned());
+	url.trim().trim_end_matches('/').to_owned()
+}
+
+fn set_jwt_issuer() -> String {
+	dotenv().ok();
+	std_env::var(env::JWT_ISSUER_ENV_VAR).unwrap_or(DEFAULT_JWT_ISSUER.to_owned())
+}
+
+// Comma separated. Tokens are minted for every listed audience,
+// and a token is accepted if it was minted for at least one of them.
+//
+fn set_jwt_audiences() -> Vec<String> {
+	dotenv().ok();
+	let audiences = std_env::var(env::JWT_AUDIENCE_ENV_VAR).unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned());
+	parse_jwt_audiences(&audiences)
+}
+
+// The audiences of pending 2FA and email verification tokens are reserved.
+// Listing one would let such a token pass as an access token.
+//
+pub(crate) fn parse_jwt_audiences(audiences: &str) -> Vec<String> {
+	let audiences: Vec<String> = audiences
+		.split(',')
+		.map(str::trim)
+		.filter(|a| !a.is_empty())
+		.map(str::to_owned)
+		.collect();
+	if audiences.is_empty() {
+		panic!("JWT_AUDIENCE must name at least one audience.");
+	}
+	if audiences.iter().any(|a| a == PENDING_2FA_AUDIENCE) {
+		panic!("JWT_AUDIENCE must not name {}, it is reserved for pending 2FA tokens.", PENDING_2FA_AUDIENCE);
+	}
+	if audiences.iter().any(|a| a == EMAIL_VERIFICATION_AUDIENCE) {
+		panic!("JWT_AUDIENCE must not name {}, it is reserved for email verification tokens.", EMAIL_VERIFICATION_AUDIENCE);
+	}
+	audiences
+}
+
+// Token lifetimes default to TOKEN_TTL_SECONDS and REMEMBER_ME_TTL_SECONDS.
+// Sliding expiry is off unless TOKEN_SLIDING_WINDOW_SECONDS is set.
+//
+fn set_token_policy() -> TokenPolicy {
+	dotenv().ok();
+	let seconds = |name: &str| std_env::var(name).ok().map(|v| v.parse::<i64>().unwrap_or_else(|_| panic!("{} must be a number of seconds", name)));
+	let access  = seconds(env::TOKEN_TTL_ENV_VAR).unwrap_or(TOKEN_TTL_SECONDS);
+	let longer  = seconds(env::REMEMBER_ME_TTL_ENV_VAR).unwrap_or(REMEMBER_ME_TTL_SECONDS.max(access));
+	let window  = seconds(env::TOKEN_SLIDING_WINDOW_ENV_VAR);
+	TokenPolicy::new(access, longer, window).expect("Invalid token policy")
+}
+
+// TOTP secrets are encrypted with the base64 encoded 32 byte key in TOTP_ENCRYPTION_KEY.
+// It must stay the same across restarts, or enrolled authenticators can no longer be read.
+//
+fn set_totp_cipher() -> SecretCipher {
+	dotenv().ok();
+	let key = std_env::var(env::TOTP_ENCRYPTION_KEY_ENV_VAR).expect("TOTP_ENCRYPTION_KEY not set");
+	if key.trim().is_empty() {
+		panic!("TOTP_ENCRYPTION_KEY must not be empty. Set it in the .env file or in environment variables.");
+	}
+	let key = STANDARD.decode(key.trim()).expect("TOTP_ENCRYPTION_KEY must be base64 encoded");
+	SecretCipher::new(&key).expect("Invalid TOTP_ENCRYPTION_KEY")
+}
+
+// 2FA codes are stored as HMACs keyed with the base64 encoded key in TWO_FA_CODE_HMAC_KEY.
+// Every instance must use the same key, or codes sent by one no longer match on another.
+//
+fn set_two_fa_code_hasher() -> CodeHasher {
 	dotenv().ok();
-	let jwt_secret = std_env::var("JWT_SECRET").expect("JWT_SECRET not set");
-	if jwt_secret.is_empty() {
-		panic!("JWT_SECRET must not be empty. Set it in the .env file or in environment variables.");
+	let key = std_env::var(env::TWO_FA_CODE_HMAC_KEY_ENV_VAR).expect("TWO_FA_CODE_HMAC_KEY not set");
+	if key.trim().is_empty() {
+		panic!("TWO_FA_CODE_HMAC_KEY must not be empty. Set it in the .env file or in environment variables.");
 	}
-	if jwt_secret.len() < 16 {
-		warn!("JWT_SECRET is too short. It should be at least 16 characters long.")
+	let key = STANDARD.decode(key.trim()).expect("TWO_FA_CODE_HMAC_KEY must be base64 encoded");
+	CodeHasher::new(&key).expect("Invalid TWO_FA_CODE_HMAC_KEY")
+}
+
+// Clients allowed to call /introspect, as comma separated id:secret pairs.
+// Without any, introspection is refused to everyone.
+//
+fn set_introspection_clients() -> ClientSecrets {
+	dotenv().ok();
+	let clients = std_env::var(env::INTROSPECTION_CLIENTS_ENV_VAR).unwrap_or_default();
+	let clients: ClientSecrets = clients
+		.split(',')
+		.filter_map(|pair| pair.trim().split_once(':'))
+		.filter(|(id, secret)| !id.is_empty() && !secret.is_empty())
+		.map(|(id, secret)| (id.to_owned(), Secret::new(secret.to_owned())))
+		.collect();
+	if clients.is_empty() {
+		warn!("INTROSPECTION_CLIENTS not set. Token introspection is disabled.");
 	}
-	Secret::new(jwt_secret)
+	clients
 }
 
 fn set_db_url() -> Secret<String> {
@@ -63,11 +240,68 @@ fn set_redis_host() -> String {
 	std_env::var(envar_name).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
 }
 
+// Where banned tokens and 2FA codes are kept: "redis" (the default) or "postgres".
+// Refresh tokens, WebAuthn challenges and password reset tokens are always kept in Redis,
+// so Redis is required whichever backend is selected.
+//
+fn set_store_backend() -> String {
+	dotenv().ok();
+	let backend = std_env::var(env::STORE_BACKEND_ENV_VAR).unwrap_or(STORE_BACKEND_REDIS.to_owned());
+	match backend.as_str() {
+		STORE_BACKEND_POSTGRES | STORE_BACKEND_REDIS => backend,
+		_ => panic!("STORE_BACKEND must be either {} or {}", STORE_BACKEND_REDIS, STORE_BACKEND_POSTGRES),
+	}
+}
+
+// Wrong 2FA answers allowed per login attempt, DEFAULT_MAX_2FA_ATTEMPTS unless MAX_2FA_ATTEMPTS is set.
+//
+fn set_max_2fa_attempts() -> u32 {
+	dotenv().ok();
+	match std_env::var(env::MAX_2FA_ATTEMPTS_ENV_VAR).ok().filter(|v| !v.trim().is_empty()) {
+		Some(v) => v.trim().parse::<u32>().ok().filter(|n| *n > 0).expect("MAX_2FA_ATTEMPTS must be a positive number"),
+		None    => DEFAULT_MAX_2FA_ATTEMPTS,
+	}
+}
+
+// Passkeys are bound to the origin of the app that uses them, so WEBAUTHN_RP_ORIGIN is required.
+// WEBAUTHN_RP_ID defaults to the origin's domain, and must be a domain, not an IP.
+//
+fn set_webauthn() -> Webauthn {
+	dotenv().ok();
+	let set    = |name: &str| std_env::var(name).ok().filter(|v| !v.trim().is_empty());
+	let origin = set(env::WEBAUTHN_RP_ORIGIN_ENV_VAR)
+		.expect("WEBAUTHN_RP_ORIGIN must be set. Set it in the .env file or in environment variables.");
+	let origin = Url::parse(&origin).expect("WEBAUTHN_RP_ORIGIN must be a URL");
+	let rp_id  = set(env::WEBAUTHN_RP_ID_ENV_VAR)
+		.or_else(|| origin.domain().map(str::to_owned))
+		.expect("WEBAUTHN_RP_ID must be set when WEBAUTHN_RP_ORIGIN has no domain");
+	WebauthnBuilder::new(&rp_id, &origin)
+		.and_then(|builder| builder.rp_name(WEBAUTHN_RP_NAME).build())
+		.expect("Invalid WebAuthn relying party")
+}
+
 pub mod env {
-	pub const DATABASE_URL_ENV_VAR:    &str = "DATABASE_URL";
-	pub const JWT_SECRENT_ENV_VAR:     &str = "JWT_SECRET";
-	pub const POSTMARK_AUTH_TOKEN:     &str = "POSTMARK_AUTH_TOKEN";
-	pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
+	pub const AUTH_SERVICE_URL_ENV_VAR:      &str = "AUTH_SERVICE_URL";
+	pub const DATABASE_URL_ENV_VAR:          &str = "DATABASE_URL";
+	pub const INTROSPECTION_CLIENTS_ENV_VAR: &str = "INTROSPECTION_CLIENTS";
+	pub const JWT_ACTIVE_KID_ENV_VAR:        &str = "JWT_ACTIVE_KID";
+	pub const JWT_AUDIENCE_ENV_VAR:          &str = "JWT_AUDIENCE";
+	pub const JWT_ISSUER_ENV_VAR:            &str = "JWT_ISSUER";
+	pub const JWT_KEYS_DIR_ENV_VAR:          &str = "JWT_KEYS_DIR";
+	pub const MAX_2FA_ATTEMPTS_ENV_VAR:      &str = "MAX_2FA_ATTEMPTS";
+	pub const POSTMARK_AUTH_TOKEN:           &str = "POSTMARK_AUTH_TOKEN";
+	pub const REDIS_HOST_NAME_ENV_VAR:       &str = "REDIS_HOST_NAME";
+	pub const REMEMBER_ME_TTL_ENV_VAR:       &str = "REMEMBER_ME_TTL_SECONDS";
+	pub const STORE_BACKEND_ENV_VAR:         &str = "STORE_BACKEND";
+	pub const TOKEN_SLIDING_WINDOW_ENV_VAR:  &str = "TOKEN_SLIDING_WINDOW_SECONDS";
+	pub const TOKEN_TTL_ENV_VAR:             &str = "TOKEN_TTL_SECONDS";
+	pub const TOTP_ENCRYPTION_KEY_ENV_VAR:   &str = "TOTP_ENCRYPTION_KEY";
+	pub const TWILIO_ACCOUNT_SID_ENV_VAR:    &str = "TWILIO_ACCOUNT_SID";
+	pub const TWILIO_AUTH_TOKEN_ENV_VAR:     &str = "TWILIO_AUTH_TOKEN";
+	pub const TWILIO_SENDER_ENV_VAR:         &str = "TWILIO_SENDER";
+	pub const TWO_FA_CODE_HMAC_KEY_ENV_VAR:  &str = "TWO_FA_CODE_HMAC_KEY";
+	pub const WEBAUTHN_RP_ID_ENV_VAR:        &str = "WEBAUTHN_RP_ID";
+	pub const WEBAUTHN_RP_ORIGIN_ENV_VAR:    &str = "WEBAUTHN_RP_ORIGIN";
 }
 
 pub mod prod {
@@ -80,6 +314,11 @@ pub mod prod {
 		pub const SENDER:    &str     = "crt@rivvit.io";
 		pub const TIMEOUT:   Duration = Duration::from_secs(10);
 	}
+	pub mod sms_client {
+		use std::time::Duration;
+		pub const BASE_URL:  &str     = "https://api.twilio.com";
+		pub const TIMEOUT:   Duration = Duration::from_secs(10);
+	}
 }
 
 pub mod test {
@@ -91,4 +330,8 @@ pub mod test {
 		pub const SENDER: &str = "test@email.com";
 		pub const TIMEOUT: Duration = Duration::from_millis(200);
 	}
+	pub mod sms_client {
+		use std::time::Duration;
+		pub const TIMEOUT: Duration = Duration::from_millis(200);
+	}
 }
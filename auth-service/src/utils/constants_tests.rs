@@ -0,0 +1,25 @@
+use super::constants::{parse_jwt_audiences, EMAIL_VERIFICATION_AUDIENCE, PENDING_2FA_AUDIENCE};
+
+#[test]
+fn audiences_are_split_on_commas_and_trimmed() {
+	let audiences = parse_jwt_audiences(" app, admin ,,");
+	assert_eq!(audiences, vec!["app".to_owned(), "admin".to_owned()]);
+}
+
+#[test]
+#[should_panic(expected = "at least one audience")]
+fn an_empty_audience_list_is_rejected() {
+	parse_jwt_audiences(" , ");
+}
+
+#[test]
+#[should_panic(expected = "reserved for pending 2FA tokens")]
+fn the_pending_2fa_audience_is_rejected() {
+	parse_jwt_audiences(&format!("app,{}", PENDING_2FA_AUDIENCE));
+}
+
+#[test]
+#[should_panic(expected = "reserved for email verification tokens")]
+fn the_email_verification_audience_is_rejected() {
+	parse_jwt_audiences(&format!("app,{}", EMAIL_VERIFICATION_AUDIENCE));
+}
@@ -0,0 +1,53 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::Secret;
+
+const NONCE_BYTES: usize = 12;
+
+// AES-256-GCM for secrets we have to store, but must be able to read back (unlike passwords).
+// A sealed value is the random nonce followed by the ciphertext. The associated data binds
+// a value to its owner, so a value copied to another row fails to open.
+//
+pub struct SecretCipher {
+	cipher: Aes256Gcm,
+}
+
+impl SecretCipher {
+	pub fn new(key: &[u8]) -> Result<Self> {
+		if key.len() != 32 {
+			return Err(eyre!("Encryption key must be 32 bytes, not {}", key.len()));
+		}
+		let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+		Ok(Self {cipher})
+	}
+
+	// A freshly generated key. Nothing sealed with it survives a restart,
+	// so this is only suitable for development and tests.
+	//
+	pub fn ephemeral() -> Self {
+		let cipher = Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng));
+		Self {cipher}
+	}
+
+	pub fn seal(&self, plaintext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>> {
+		let nonce      = Aes256Gcm::generate_nonce(&mut OsRng);
+		let payload    = Payload {msg: plaintext, aad: associated_data};
+		let ciphertext = self.cipher.encrypt(&nonce, payload).map_err(|_| eyre!("Failed to encrypt secret"))?;
+		let mut sealed = nonce.to_vec();
+		sealed.extend(ciphertext);
+		Ok(sealed)
+	}
+
+	pub fn open(&self, sealed: &[u8], associated_data: &[u8]) -> Result<Secret<Vec<u8>>> {
+		if sealed.len() < NONCE_BYTES {
+			return Err(eyre!("Sealed secret is too short"));
+		}
+		let (nonce, ciphertext) = sealed.split_at(NONCE_BYTES);
+		let payload   = Payload {msg: ciphertext, aad: associated_data};
+		let plaintext = self.cipher
+			.decrypt(Nonce::from_slice(nonce), payload)
+			.map_err(|_| eyre!("Failed to decrypt secret"))?;
+		Ok(Secret::new(plaintext))
+	}
+}
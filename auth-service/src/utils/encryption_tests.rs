@@ -0,0 +1,39 @@
+use super::encryption::SecretCipher;
+use secrecy::ExposeSecret;
+
+#[test]
+fn a_sealed_secret_opens_to_the_original() {
+	let cipher = SecretCipher::ephemeral();
+	let sealed = cipher.seal(b"totp secret", b"a@b.com").unwrap();
+	let opened = cipher.open(&sealed, b"a@b.com").unwrap();
+	assert_eq!(opened.expose_secret().as_slice(), b"totp secret");
+	assert!(!sealed.windows(11).any(|w| w == b"totp secret"));
+}
+
+#[test]
+fn sealing_twice_gives_different_ciphertexts() {
+	let cipher = SecretCipher::ephemeral();
+	let first  = cipher.seal(b"totp secret", b"a@b.com").unwrap();
+	let second = cipher.seal(b"totp secret", b"a@b.com").unwrap();
+	assert_ne!(first, second);
+}
+
+#[test]
+fn a_secret_does_not_open_for_another_owner() {
+	let cipher = SecretCipher::ephemeral();
+	let sealed = cipher.seal(b"totp secret", b"a@b.com").unwrap();
+	assert!(cipher.open(&sealed, b"c@d.com").is_err());
+}
+
+#[test]
+fn a_secret_does_not_open_with_another_key() {
+	let sealed = SecretCipher::ephemeral().seal(b"totp secret", b"a@b.com").unwrap();
+	let other  = SecretCipher::new(&[7u8; 32]).unwrap();
+	assert!(other.open(&sealed, b"a@b.com").is_err());
+}
+
+#[test]
+fn keys_must_be_32_bytes() {
+	assert!(SecretCipher::new(&[0u8; 16]).is_err());
+	assert!(SecretCipher::new(&[0u8; 32]).is_ok());
+}
@@ -0,0 +1,152 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use color_eyre::eyre::{eyre, Context, Result};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, EncodePrivateKey};
+use jsonwebtoken::jwk::{
+	AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+	OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+// One asymmetric key pair of the key ring. The private half signs tokens,
+// the public half verifies them and is published as a JWK.
+//
+pub struct SigningKey {
+	pub kid:       String,
+	pub algorithm: Algorithm,
+	encoding:      EncodingKey,
+	decoding:      DecodingKey,
+	jwk:           Jwk,
+}
+
+impl SigningKey {
+	// Accepts a PKCS#8 Ed25519 key (EdDSA), or a PKCS#8 / PKCS#1 RSA key (RS256).
+	//
+	pub fn from_pem(kid: &str, pem: &str) -> Result<Self> {
+		if let Ok(key) = ed25519_dalek::SigningKey::from_pkcs8_pem(pem) {
+			let encoding = EncodingKey::from_ed_pem(pem.as_bytes()).wrap_err("Invalid Ed25519 key")?;
+			return Self::from_ed25519(kid, &key, encoding);
+		}
+
+		let key = RsaPrivateKey::from_pkcs8_pem(pem)
+			.or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+			.map_err(|_| eyre!("Key {} is neither an Ed25519 nor an RSA private key", kid))?;
+		let encoding = EncodingKey::from_rsa_pem(pem.as_bytes()).wrap_err("Invalid RSA key")?;
+		Self::from_rsa(kid, &key, encoding)
+	}
+
+	pub fn generate_ed25519(kid: &str) -> Result<Self> {
+		let key      = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+		let der      = key.to_pkcs8_der().wrap_err("Failed to encode Ed25519 key")?;
+		let encoding = EncodingKey::from_ed_der(der.as_bytes());
+		Self::from_ed25519(kid, &key, encoding)
+	}
+
+	pub fn encoding_key(&self) -> &EncodingKey { &self.encoding }
+	pub fn decoding_key(&self) -> &DecodingKey { &self.decoding }
+	pub fn jwk(&self)          -> &Jwk         { &self.jwk      }
+
+	fn from_ed25519(kid: &str, key: &ed25519_dalek::SigningKey, encoding: EncodingKey) -> Result<Self> {
+		let x         = URL_SAFE_NO_PAD.encode(key.verifying_key().as_bytes());
+		let params    = OctetKeyPairParameters {key_type: OctetKeyPairType::OctetKeyPair, curve: EllipticCurve::Ed25519, x};
+		let algorithm = AlgorithmParameters::OctetKeyPair(params);
+		Self::assemble(kid, Algorithm::EdDSA, KeyAlgorithm::EdDSA, algorithm, encoding)
+	}
+
+	fn from_rsa(kid: &str, key: &RsaPrivateKey, encoding: EncodingKey) -> Result<Self> {
+		let n         = URL_SAFE_NO_PAD.encode(key.n().to_bytes_be());
+		let e         = URL_SAFE_NO_PAD.encode(key.e().to_bytes_be());
+		let params    = RSAKeyParameters {key_type: RSAKeyType::RSA, n, e};
+		let algorithm = AlgorithmParameters::RSA(params);
+		Self::assemble(kid, Algorithm::RS256, KeyAlgorithm::RS256, algorithm, encoding)
+	}
+
+	fn assemble(
+		kid:           &str,
+		alg:           Algorithm,
+		key_algorithm: KeyAlgorithm,
+		algorithm:     AlgorithmParameters,
+		encoding:      EncodingKey,
+	) -> Result<Self> {
+		let common   = CommonParameters {
+			public_key_use: Some(PublicKeyUse::Signature),
+			key_algorithm:  Some(key_algorithm),
+			key_id:         Some(kid.to_owned()),
+			..Default::default()
+		};
+		let jwk      = Jwk {common, algorithm};
+		let decoding = DecodingKey::from_jwk(&jwk).wrap_err("Failed to derive public key")?;
+		let kid      = kid.to_owned();
+		Ok(Self {kid, algorithm: alg, encoding, decoding, jwk})
+	}
+}
+
+// All keys the service accepts tokens from. New tokens are signed with the active key only.
+// Retired keys stay in the ring until every token they signed has expired, then they are removed.
+//
+pub struct KeyRing {
+	active: usize,
+	keys:   Vec<SigningKey>,
+}
+
+impl KeyRing {
+	pub fn new(keys: Vec<SigningKey>, active_kid: &str) -> Result<Self> {
+		let active = keys.iter()
+			.position(|k| k.kid == active_kid)
+			.ok_or(eyre!("Active key {} is not in the key ring", active_kid))?;
+		Ok(Self {active, keys})
+	}
+
+	// Load every *.pem file in a directory. The file stem is the key id.
+	// Without an explicit active key id, the last key id in sort order signs,
+	// so naming keys by date (2025-01, 2025-06, ...) rotates naturally.
+	//
+	pub fn load(dir: &Path, active_kid: Option<&str>) -> Result<Self> {
+		let mut paths: Vec<_> = fs::read_dir(dir)
+			.wrap_err_with(|| format!("Failed to read key directory {:?}", dir))?
+			.filter_map(|entry| entry.ok().map(|e| e.path()))
+			.filter(|path| path.extension().is_some_and(|ext| ext == "pem"))
+			.collect();
+		paths.sort();
+
+		let mut keys = Vec::with_capacity(paths.len());
+		for path in paths {
+			let kid = path.file_stem().and_then(|s| s.to_str()).ok_or(eyre!("Bad key file name {:?}", path))?;
+			let pem = fs::read_to_string(&path).wrap_err_with(|| format!("Failed to read key {:?}", path))?;
+			keys.push(SigningKey::from_pem(kid, &pem)?);
+			info!(kid, "Loaded JWT signing key");
+		}
+
+		let last   = keys.last().map(|k| k.kid.clone()).ok_or(eyre!("No keys found in {:?}", dir))?;
+		let active = active_kid.map(str::to_owned).unwrap_or(last);
+		Self::new(keys, &active)
+	}
+
+	// A single, freshly generated Ed25519 key. Tokens do not survive a restart,
+	// so this is only suitable for development and tests.
+	//
+	pub fn ephemeral() -> Result<Self> {
+		let kid = format!("ephemeral-{}", uuid::Uuid::new_v4());
+		let key = SigningKey::generate_ed25519(&kid)?;
+		Self::new(vec![key], &kid)
+	}
+
+	pub fn active(&self) -> &SigningKey {
+		&self.keys[self.active]
+	}
+
+	pub fn get(&self, kid: &str) -> Option<&SigningKey> {
+		self.keys.iter().find(|k| k.kid == kid)
+	}
+
+	pub fn jwks(&self) -> JwkSet {
+		let keys = self.keys.iter().map(|k| k.jwk.clone()).collect();
+		JwkSet {keys}
+	}
+}
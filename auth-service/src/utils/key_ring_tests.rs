@@ -0,0 +1,69 @@
+use super::key_ring::{KeyRing, SigningKey};
+use jsonwebtoken::{Algorithm, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const FIXTURE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/jwt_keys");
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct TestClaims {
+	sub: String,
+	exp: usize,
+}
+
+fn sign_and_verify(key: &SigningKey) -> TestClaims {
+	let claims     = TestClaims {sub: "a@b.com".to_owned(), exp: usize::MAX / 2};
+	let header     = Header::new(key.algorithm);
+	let token      = jsonwebtoken::encode(&header, &claims, key.encoding_key()).unwrap();
+	let validation = Validation::new(key.algorithm);
+	jsonwebtoken::decode::<TestClaims>(&token, key.decoding_key(), &validation).unwrap().claims
+}
+
+#[test]
+fn load_reads_every_key_and_activates_the_last_one() {
+	let ring = KeyRing::load(Path::new(FIXTURE_DIR), None).unwrap();
+	assert_eq!(ring.active().kid, "2025-06-ed25519");
+	assert_eq!(ring.active().algorithm, Algorithm::EdDSA);
+	assert_eq!(ring.get("2025-01-rsa").unwrap().algorithm, Algorithm::RS256);
+	assert!(ring.get("unknown").is_none());
+}
+
+#[test]
+fn load_honours_an_explicit_active_key() {
+	let ring = KeyRing::load(Path::new(FIXTURE_DIR), Some("2025-01-rsa")).unwrap();
+	assert_eq!(ring.active().kid, "2025-01-rsa");
+}
+
+#[test]
+fn load_rejects_an_unknown_active_key() {
+	let ring = KeyRing::load(Path::new(FIXTURE_DIR), Some("2024-12-rsa"));
+	assert!(ring.is_err());
+}
+
+#[test]
+fn tokens_signed_by_each_key_verify_with_its_public_half() {
+	let ring = KeyRing::load(Path::new(FIXTURE_DIR), None).unwrap();
+	for kid in ["2025-01-rsa", "2025-06-ed25519"] {
+		let claims = sign_and_verify(ring.get(kid).unwrap());
+		assert_eq!(claims.sub, "a@b.com");
+	}
+}
+
+#[test]
+fn jwks_publishes_only_public_parameters() {
+	let ring = KeyRing::load(Path::new(FIXTURE_DIR), None).unwrap();
+	let jwks = serde_json::to_value(ring.jwks()).unwrap();
+	let keys = jwks["keys"].as_array().unwrap();
+	assert_eq!(keys.len(), 2);
+	for key in keys {
+		assert!(key["kid"].is_string());
+		assert!(key.get("d").is_none(), "Private key material leaked: {}", key);
+	}
+}
+
+#[test]
+fn ephemeral_key_ring_can_sign_and_verify() {
+	let ring   = KeyRing::ephemeral().unwrap();
+	let claims = sign_and_verify(ring.active());
+	assert_eq!(claims.sub, "a@b.com");
+}
@@ -0,0 +1,38 @@
+use rand::Rng;
+use secrecy::Secret;
+
+// Single use codes that stand in for a second factor once the user has lost it.
+// Each code is 10 characters, shown as two groups of five, from an alphabet without
+// look-alikes (0/o, 1/l/i), so they survive being written down and typed back in.
+//
+pub const RECOVERY_CODE_COUNT:  usize = 10;
+pub const RECOVERY_CODE_LENGTH: usize = 10;
+const ALPHABET:                 &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const GROUP_LENGTH:             usize = RECOVERY_CODE_LENGTH / 2;
+
+pub fn generate_codes() -> Vec<Secret<String>> {
+	(0..RECOVERY_CODE_COUNT).map(|_| generate_code()).collect()
+}
+
+fn generate_code() -> Secret<String> {
+	let mut rng = rand::thread_rng();
+	let code: String = (0..RECOVERY_CODE_LENGTH)
+		.map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char)
+		.collect();
+	Secret::new(format!("{}-{}", &code[..GROUP_LENGTH], &code[GROUP_LENGTH..]))
+}
+
+// The canonical form of a code as typed by a user: case, spaces and dashes do not matter.
+// None if it cannot be a recovery code at all.
+//
+pub fn normalize(code: &str) -> Option<String> {
+	let code: String = code
+		.chars()
+		.filter(|c| !c.is_whitespace() && *c != '-')
+		.map(|c| c.to_ascii_lowercase())
+		.collect();
+	if code.len() != RECOVERY_CODE_LENGTH || !code.bytes().all(|c| ALPHABET.contains(&c)) {
+		return None;
+	}
+	Some(format!("{}-{}", &code[..GROUP_LENGTH], &code[GROUP_LENGTH..]))
+}
@@ -0,0 +1,36 @@
+use super::recovery_codes::*;
+use secrecy::ExposeSecret;
+use std::collections::HashSet;
+
+#[test]
+fn a_full_set_of_distinct_codes_is_generated() {
+	let codes    = generate_codes();
+	let distinct = codes.iter().map(|c| c.expose_secret().clone()).collect::<HashSet<_>>();
+	assert_eq!(codes.len(),    RECOVERY_CODE_COUNT);
+	assert_eq!(distinct.len(), RECOVERY_CODE_COUNT);
+}
+
+#[test]
+fn generated_codes_are_already_normalized() {
+	for code in generate_codes() {
+		let code = code.expose_secret();
+		assert_eq!(code.len(), RECOVERY_CODE_LENGTH + 1, "{}", code);
+		assert_eq!(normalize(code).as_ref(), Some(code));
+	}
+}
+
+#[test]
+fn case_spaces_and_dashes_are_ignored() {
+	let expected = Some("abcde-fghjk".to_owned());
+	assert_eq!(normalize("abcde-fghjk"),   expected);
+	assert_eq!(normalize("ABCDEFGHJK"),    expected);
+	assert_eq!(normalize(" abcde fghjk "), expected);
+}
+
+#[test]
+fn anything_else_is_not_a_recovery_code() {
+	assert_eq!(normalize("123456"),       None);    // A 2FA code
+	assert_eq!(normalize("abcde-fghjkm"), None);    // Too long
+	assert_eq!(normalize("abcde-fghj0"),  None);    // Not in the alphabet
+	assert_eq!(normalize(""),             None);
+}
@@ -0,0 +1,49 @@
+use color_eyre::eyre::{eyre, Result};
+
+// How long JWT auth tokens live. Times are in seconds.
+//
+// `access_ttl` is the lifetime of a regular login, `remember_me_ttl` the lifetime of
+// a login that asked to be remembered. With a `sliding_window`, a token that is used
+// within that many seconds of its expiry is re-issued with its full lifetime, so a token
+// only expires once its holder has been idle for a while.
+//
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenPolicy {
+	pub access_ttl:      i64,
+	pub remember_me_ttl: i64,
+	pub sliding_window:  Option<i64>,
+}
+
+impl TokenPolicy {
+	pub fn new(access_ttl: i64, remember_me_ttl: i64, sliding_window: Option<i64>) -> Result<Self> {
+		if access_ttl <= 0 {
+			return Err(eyre!("Token lifetime must be positive. Value({})", access_ttl));
+		}
+		if remember_me_ttl < access_ttl {
+			return Err(eyre!("Remember me lifetime must not be shorter than the token lifetime. Value({})", remember_me_ttl));
+		}
+		if let Some(window) = sliding_window {
+			if window <= 0 || window >= access_ttl {
+				return Err(eyre!("Sliding window must be positive and shorter than the token lifetime. Value({})", window));
+			}
+		}
+		Ok(Self {access_ttl, remember_me_ttl, sliding_window})
+	}
+
+	pub fn ttl(&self, remember_me: bool) -> i64 {
+		match remember_me {
+			true  => self.remember_me_ttl,
+			false => self.access_ttl,
+		}
+	}
+
+	// A token is renewed when sliding expiry is on and it expires within the window.
+	// An already expired token is never renewed.
+	//
+	pub fn needs_renewal(&self, exp: usize, now: usize) -> bool {
+		match self.sliding_window {
+			Some(window) => now < exp && exp - now <= window as usize,
+			None         => false,
+		}
+	}
+}
@@ -0,0 +1,33 @@
+use super::token_policy::TokenPolicy;
+
+#[test]
+fn remember_me_selects_the_longer_lifetime() {
+	let policy = TokenPolicy::new(600, 86_400, None).unwrap();
+	assert_eq!(policy.ttl(false), 600);
+	assert_eq!(policy.ttl(true),  86_400);
+}
+
+#[test]
+fn invalid_lifetimes_are_rejected() {
+	assert!(TokenPolicy::new(0,   600, None      ).is_err());
+	assert!(TokenPolicy::new(600, 300, None      ).is_err());
+	assert!(TokenPolicy::new(600, 600, Some(0)  ).is_err());
+	assert!(TokenPolicy::new(600, 600, Some(600)).is_err());
+	assert!(TokenPolicy::new(600, 600, Some(60) ).is_ok());
+}
+
+#[test]
+fn without_a_sliding_window_tokens_are_never_renewed() {
+	let policy = TokenPolicy::new(600, 600, None).unwrap();
+	assert!(!policy.needs_renewal(1_000, 999));
+}
+
+#[test]
+fn tokens_are_renewed_only_inside_the_sliding_window() {
+	let policy = TokenPolicy::new(600, 600, Some(60)).unwrap();
+	assert!(!policy.needs_renewal(1_000, 900));     // Plenty of time left
+	assert!( policy.needs_renewal(1_000, 940));     // At the edge of the window
+	assert!( policy.needs_renewal(1_000, 999));
+	assert!(!policy.needs_renewal(1_000, 1_000));   // Expired
+	assert!(!policy.needs_renewal(1_000, 1_100));
+}
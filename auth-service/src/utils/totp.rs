@@ -0,0 +1,68 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use secrecy::Secret;
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+use url::Url;
+
+// RFC 6238 time based one time passwords, with the defaults every authenticator app
+// understands: HMAC-SHA1, 6 digits, a new code every 30 seconds.
+//
+pub const TOTP_DIGITS:         u32   = 6;
+pub const TOTP_PERIOD_SECONDS: u64   = 30;
+pub const TOTP_SECRET_BYTES:   usize = 20;
+pub const TOTP_SKEW_STEPS:     u64   = 1;      // Accept the previous and next code, for clock drift
+
+pub fn generate_secret() -> Secret<Vec<u8>> {
+	let mut secret = vec![0u8; TOTP_SECRET_BYTES];
+	rand::thread_rng().fill_bytes(&mut secret);
+	Secret::new(secret)
+}
+
+// Authenticator apps take the secret base32 encoded, without padding.
+//
+pub fn encode_secret(secret: &[u8]) -> String {
+	BASE32_NOPAD.encode(secret)
+}
+
+pub fn time_step(unix_time: u64) -> u64 {
+	unix_time / TOTP_PERIOD_SECONDS
+}
+
+// HOTP (RFC 4226, section 5.3) of one time step.
+//
+pub fn code_for_step(secret: &[u8], step: u64) -> String {
+	let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+	mac.update(&step.to_be_bytes());
+	let hash    = mac.finalize().into_bytes();
+	let offset  = (hash[hash.len() - 1] & 0x0f) as usize;
+	let binary  = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]) & 0x7fff_ffff;
+	let code    = binary % 10u32.pow(TOTP_DIGITS);
+	format!("{:0width$}", code, width = TOTP_DIGITS as usize)
+}
+
+// The time step the code belongs to, if it is valid around the given time.
+// Callers record the step so the same code cannot be used twice.
+// Codes are compared in constant time, so timing does not tell how much of a guess was right.
+//
+pub fn verify_code(secret: &[u8], code: &str, unix_time: u64) -> Option<u64> {
+	let now = time_step(unix_time);
+	(now.saturating_sub(TOTP_SKEW_STEPS)..=now + TOTP_SKEW_STEPS)
+		.find(|step| code_for_step(secret, *step).as_bytes().ct_eq(code.as_bytes()).into())
+}
+
+// Key URI understood by authenticator apps, usually shown as a QR code.
+// See https://github.com/google/google-authenticator/wiki/Key-Uri-Format
+//
+pub fn provisioning_uri(secret: &[u8], account: &str, issuer: &str) -> String {
+	let mut uri = Url::parse("otpauth://totp/").expect("Valid base URI");
+	uri.set_path(&format!("{}:{}", issuer, account));
+	uri.query_pairs_mut()
+		.append_pair("secret",    &encode_secret(secret))
+		.append_pair("issuer",    issuer)
+		.append_pair("algorithm", "SHA1")
+		.append_pair("digits",    &TOTP_DIGITS.to_string())
+		.append_pair("period",    &TOTP_PERIOD_SECONDS.to_string());
+	uri.to_string()
+}
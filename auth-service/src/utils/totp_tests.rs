@@ -0,0 +1,57 @@
+use super::totp::*;
+
+// RFC 6238, appendix B. The SHA1 seed, truncated to our 6 digits.
+//
+const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+#[test]
+fn codes_match_the_rfc_6238_test_vectors() {
+	let vectors = [
+		(59,          "287082"),
+		(1111111109,  "081804"),
+		(1111111111,  "050471"),
+		(1234567890,  "005924"),
+		(2000000000,  "279037"),
+		(20000000000, "353130"),
+	];
+	for (time, expected) in vectors {
+		assert_eq!(code_for_step(RFC_SECRET, time_step(time)), expected, "time {}", time);
+	}
+}
+
+#[test]
+fn a_code_is_accepted_within_one_step_of_clock_drift() {
+	let time = 1234567890;
+	let step = time_step(time);
+	let code = code_for_step(RFC_SECRET, step);
+	assert_eq!(verify_code(RFC_SECRET, &code, time),                       Some(step));
+	assert_eq!(verify_code(RFC_SECRET, &code, time - TOTP_PERIOD_SECONDS), Some(step));
+	assert_eq!(verify_code(RFC_SECRET, &code, time + TOTP_PERIOD_SECONDS), Some(step));
+}
+
+#[test]
+fn a_code_is_rejected_outside_the_drift_window() {
+	let time = 1234567890;
+	let code = code_for_step(RFC_SECRET, time_step(time));
+	assert_eq!(verify_code(RFC_SECRET, &code, time + 3 * TOTP_PERIOD_SECONDS), None);
+	assert_eq!(verify_code(RFC_SECRET, "000000", time), None);
+}
+
+#[test]
+fn provisioning_uri_carries_the_secret_and_issuer() {
+	let uri = provisioning_uri(RFC_SECRET, "a@b.com", "auth-service");
+	assert!(uri.starts_with("otpauth://totp/auth-service:a@b.com?"), "{}", uri);
+	assert!(uri.contains(&format!("secret={}", encode_secret(RFC_SECRET))));
+	assert!(uri.contains("issuer=auth-service"));
+	assert!(uri.contains("digits=6"));
+	assert!(uri.contains("period=30"));
+}
+
+#[test]
+fn generated_secrets_are_random() {
+	use secrecy::ExposeSecret;
+	let first  = generate_secret();
+	let second = generate_secret();
+	assert_eq!(first.expose_secret().len(), TOTP_SECRET_BYTES);
+	assert_ne!(first.expose_secret(), second.expose_secret());
+}
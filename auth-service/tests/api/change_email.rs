@@ -0,0 +1,252 @@
+use crate::helpers_arrange::*;
+use crate::helpers_assert::{assert_has_auth_cookie, assert_status};
+use crate::helpers_harness::{get_random_email, TestApp};
+use auth_service::domain::Email;
+use auth_service::routes::{ChangeEmailResponse, TwoFactorAuthResponse};
+use auth_service::utils::constants::REFRESH_COOKIE_NAME;
+use secrecy::Secret;
+use serde_json::json;
+
+// Ask to move a logged in user to a fresh address, and return the address
+//
+async fn request_change(app: &TestApp, user: &TestUser) -> String {
+    let new_email = get_random_email();
+    let response  = app.post_change_email(&new_email, &user.password).await;
+    assert_status(&response, 200, None);
+    new_email
+}
+
+fn moved(user: &TestUser, new_email: &str) -> TestUser {
+    TestUser::with_attributes(Some(new_email), Some(&user.password), user.requires_2fa)
+}
+
+async fn last_email_to(app: &TestApp, email: &str) -> Option<String> {
+    let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+    app.email_client.read().await.last_email_to(&email)
+}
+
+#[tokio::test]
+async fn change_email_should_send_a_confirm_link_to_the_new_address_and_a_cancel_link_to_the_old_one() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    let new_email     = request_change(&app, &user).await;
+
+    let confirm       = last_email_change_token(&app, &new_email, "confirm").await;
+    let cancel        = last_email_change_token(&app, &user.email, "cancel").await;
+    assert_ne!(confirm, cancel);
+    assert!(last_email_to(&app, &user.email).await.unwrap().contains(&new_email));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn the_email_should_not_change_before_confirmation() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    let new_email     = request_change(&app, &user).await;
+
+    assert_status(&app.post_login(&user.login_payload()).await, 200, Some("The old address still logs in"));
+    assert_status(&app.post_login(&moved(&user, &new_email).login_payload()).await, 401, Some("The new address does not yet"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirming_should_move_the_account_to_the_new_address() {
+    // Arrange
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+    let new_email     = request_change(&app, &user).await;
+    let confirm       = last_email_change_token(&app, &new_email, "confirm").await;
+
+    // Act
+    let response      = app.get_email_change("confirm", &confirm).await;
+
+    // Assert
+    assert_status(&response, 200, None);
+    let body          = response.json::<ChangeEmailResponse>().await.unwrap();
+    assert_eq!(body.message, "Email address changed");
+    let login         = app.post_login(&moved(&user, &new_email).login_payload()).await;
+    assert_status(&login, 200, Some("The new address logs in"));
+    assert_has_auth_cookie(&login);
+    assert_status(&app.post_login(&user.login_payload()).await, 401, Some("The old address no longer does"));
+    assert!(last_email_to(&app, &user.email).await.unwrap().contains("was changed to"), "The old address is told");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirming_should_revoke_the_tokens_of_the_old_address() {
+    let mut app      = TestApp::new().await;
+    let (user, auth) = setup_logged_in_user(&app).await;
+    let login        = app.post_login(&user.login_payload()).await;
+    let refresh      = get_cookie_value(&login, REFRESH_COOKIE_NAME);
+    let new_email    = request_change(&app, &user).await;
+    let confirm      = last_email_change_token(&app, &new_email, "confirm").await;
+
+    let response     = app.another_device().get_email_change("confirm", &confirm).await;
+
+    assert_status(&response, 200, None);
+    let verified     = app.post_verify_token(&json!({"token": auth})).await;
+    assert_status(&verified, 401, Some("Auth tokens of the old address are revoked"));
+    add_refresh_token_to_cookie_jar(&app, &refresh);
+    assert_status(&app.post_refresh().await, 401, Some("Refresh tokens of the old address are revoked"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirming_should_drop_pending_2fa_logins_of_the_old_address() {
+    let mut app       = TestApp::new().await;
+    let user          = setup_2fa_user_logged_in(&app).await;
+    let device        = app.another_device();
+    let attempt_id    = start_2fa_login(&device, &user).await;
+    let code          = get_2fa_code(&app, &attempt_id).await;
+    let new_email     = request_change(&app, &user).await;
+    let confirm       = last_email_change_token(&app, &new_email, "confirm").await;
+
+    assert_status(&app.get_email_change("confirm", &confirm).await, 200, None);
+
+    let data          = TwoFAData {login_attempt_id: attempt_id, two_fa_code: code};
+    let verified      = device.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+    assert_status(&verified, 401, Some("A login started for the old address cannot finish"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn the_authenticator_app_should_keep_working_after_the_change() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+    let secret        = setup_confirmed_totp(&app).await;
+    let new_email     = request_change(&app, &user).await;
+    let confirm       = last_email_change_token(&app, &new_email, "confirm").await;
+    assert_status(&app.get_email_change("confirm", &confirm).await, 200, None);
+
+    let user          = moved(&user, &new_email);
+    let login         = app.post_login(&user.login_payload()).await;
+    assert_status(&login, 206, Some("The authenticator is still required"));
+    let attempt       = login.json::<TwoFactorAuthResponse>().await.unwrap();
+    let code          = totp_code_steps_ahead(&secret, 1);
+    let data          = TwoFAData {login_attempt_id: attempt.login_attempt_id, two_fa_code: code};
+    let verified      = app.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+
+    assert_status(&verified, 200, Some("The authenticator code is accepted"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn the_confirm_link_should_only_work_once() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+    let new_email     = request_change(&app, &user).await;
+    let confirm       = last_email_change_token(&app, &new_email, "confirm").await;
+    assert_status(&app.get_email_change("confirm", &confirm).await, 200, None);
+
+    let response      = app.get_email_change("confirm", &confirm).await;
+
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn cancelling_should_drop_the_change() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+    let new_email     = request_change(&app, &user).await;
+    let confirm       = last_email_change_token(&app, &new_email, "confirm").await;
+    let cancel        = last_email_change_token(&app, &user.email, "cancel").await;
+
+    let response      = app.get_email_change("cancel", &cancel).await;
+
+    assert_status(&response, 200, None);
+    let body          = response.json::<ChangeEmailResponse>().await.unwrap();
+    assert_eq!(body.message, "Email change cancelled");
+    assert_status(&app.get_email_change("confirm", &confirm).await, 401, Some("A cancelled change cannot be confirmed"));
+    assert_status(&app.post_login(&user.login_payload()).await, 200, Some("The old address still logs in"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn a_new_request_should_replace_the_pending_change() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+    let first_email   = request_change(&app, &user).await;
+    let first         = last_email_change_token(&app, &first_email, "confirm").await;
+    let second_email  = request_change(&app, &user).await;
+    let second        = last_email_change_token(&app, &second_email, "confirm").await;
+
+    assert_status(&app.get_email_change("confirm", &first).await, 401, Some("Only the latest change can be confirmed"));
+    assert_status(&app.get_email_change("confirm", &second).await, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_email_should_return_401_for_a_wrong_password() {
+    let mut app       = TestApp::new().await;
+    let (_user, _)    = setup_logged_in_user(&app).await;
+
+    let response      = app.post_change_email(&get_random_email(), "Wrong1234**").await;
+
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_email_should_return_409_for_an_address_in_use() {
+    let mut app       = TestApp::new().await;
+    let other         = TestUser::new();
+    setup_registered_user(&app, &other).await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    let response      = app.post_change_email(&other.email, &user.password).await;
+
+    assert_status(&response, 409, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_email_should_return_400_for_the_current_or_a_malformed_address() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    let same          = app.post_change_email(&user.email, &user.password).await;
+    let malformed     = app.post_change_email("not-an-email", &user.password).await;
+
+    assert_status(&same, 400, None);
+    assert_status(&malformed, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_email_should_return_400_without_an_auth_cookie() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+
+    let response = app.post_change_email(&get_random_email(), &user.password).await;
+
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirming_should_return_409_if_the_new_address_was_taken_meanwhile() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+    let new_email     = request_change(&app, &user).await;
+    let confirm       = last_email_change_token(&app, &new_email, "confirm").await;
+    let squatter      = TestUser::with_attributes(Some(&new_email), None, false);
+    assert_status(&app.post_signup(&squatter.signup_payload()).await, 201, None);
+
+    let response      = app.get_email_change("confirm", &confirm).await;
+
+    assert_status(&response, 409, None);
+    assert_status(&app.post_login(&user.login_payload()).await, 200, Some("The account keeps its address"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn email_change_links_should_return_401_for_an_unknown_token() {
+    let mut app  = TestApp::new().await;
+    assert_status(&app.get_email_change("confirm", "not-a-token").await, 401, None);
+    assert_status(&app.get_email_change("cancel",  "not-a-token").await, 401, None);
+    app.clean_up().await;
+}
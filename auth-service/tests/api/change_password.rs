@@ -0,0 +1,131 @@
+use crate::helpers_arrange::*;
+use crate::helpers_assert::assert_status;
+use crate::helpers_harness::TestApp;
+use auth_service::domain::Email;
+use auth_service::routes::{PasswordResponse, SessionsResponse};
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use secrecy::Secret;
+use serde_json::json;
+
+const NEW_PASSWORD: &str = "Battery5678!";
+
+fn change_payload(current: &str, new: &str) -> serde_json::Value {
+    json!({"currentPassword": current, "newPassword": new})
+}
+
+#[tokio::test]
+async fn change_password_should_replace_the_password() {
+    // Arrange
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    // Act
+    let response      = app.post_password("change", &change_payload(&user.password, NEW_PASSWORD)).await;
+
+    // Assert
+    assert_status(&response, 200, None);
+    let body          = response.json::<PasswordResponse>().await.unwrap();
+    assert_eq!(body.message, "Password has been changed");
+    let old           = app.post_login(&user.login_payload()).await;
+    assert_status(&old, 401, Some("The old password no longer works"));
+    let renewed       = TestUser::with_attributes(Some(&user.email), Some(NEW_PASSWORD), false);
+    let new           = app.post_login(&renewed.login_payload()).await;
+    assert_status(&new, 200, Some("The new password works"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_password_should_keep_the_current_session() {
+    let mut app      = TestApp::new().await;
+    let (user, auth) = setup_logged_in_user(&app).await;
+
+    let response     = app.post_password("change", &change_payload(&user.password, NEW_PASSWORD)).await;
+
+    assert_status(&response, 200, None);
+    let verified     = app.post_verify_token(&json!({"token": auth})).await;
+    assert_status(&verified, 200, Some("The session making the change stays logged in"));
+    let sessions     = app.get_sessions().await.json::<SessionsResponse>().await.unwrap();
+    assert_eq!(sessions.sessions.len(), 1);
+    assert!(sessions.sessions[0].current);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_password_should_revoke_every_other_session() {
+    let mut app      = TestApp::new().await;
+    let (user, _)    = setup_logged_in_user(&app).await;
+    let device       = app.another_device();
+    let login        = device.post_login(&user.login_payload()).await;
+    let other_auth   = get_cookie_value(&login, JWT_COOKIE_NAME);
+
+    let response     = app.post_password("change", &change_payload(&user.password, NEW_PASSWORD)).await;
+
+    assert_status(&response, 200, None);
+    let verified     = app.post_verify_token(&json!({"token": other_auth})).await;
+    assert_status(&verified, 401, Some("Auth tokens of other sessions are revoked"));
+    assert_status(&device.post_refresh().await, 401, Some("Refresh tokens of other sessions are revoked"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_password_should_keep_the_current_refresh_token_working() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    let response      = app.post_password("change", &change_payload(&user.password, NEW_PASSWORD)).await;
+
+    assert_status(&response, 200, None);
+    let refreshed     = app.post_refresh().await;
+    assert_status(&refreshed, 200, None);
+    assert!(!get_cookie_value(&refreshed, REFRESH_COOKIE_NAME).is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_password_should_return_401_for_a_wrong_current_password() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    let response      = app.post_password("change", &change_payload("Wrong1234**", NEW_PASSWORD)).await;
+
+    assert_status(&response, 401, None);
+    assert_status(&app.post_login(&user.login_payload()).await, 200, Some("The password is unchanged"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_password_should_return_400_for_an_invalid_new_password() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    let response      = app.post_password("change", &change_payload(&user.password, "")).await;
+
+    assert_status(&response, 400, None);
+    assert_status(&app.post_login(&user.login_payload()).await, 200, Some("The password is unchanged"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_password_should_return_400_without_an_auth_cookie() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+
+    let response = app.post_password("change", &change_payload(&user.password, NEW_PASSWORD)).await;
+
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn change_password_should_notify_the_user() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    assert_status(&app.post_password("change", &change_payload(&user.password, NEW_PASSWORD)).await, 200, None);
+
+    let email         = Email::parse(Secret::new(user.email.clone())).unwrap();
+    let content       = app.email_client.read().await.last_email_to(&email).unwrap();
+    assert!(content.contains("password of your account was changed"));
+    app.clean_up().await;
+}
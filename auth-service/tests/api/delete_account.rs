@@ -0,0 +1,127 @@
+use crate::helpers_arrange::*;
+use crate::helpers_assert::assert_status;
+use crate::helpers_harness::TestApp;
+use auth_service::domain::Email;
+use auth_service::routes::DeleteAccountResponse;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use secrecy::Secret;
+use serde_json::json;
+
+#[tokio::test]
+async fn delete_account_should_remove_the_user_and_clear_the_cookies() {
+    // Arrange
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    // Act
+    let response      = app.delete_account(&user.password).await;
+
+    // Assert
+    assert_status(&response, 200, None);
+    let cleared       = response.cookies().filter(|c| c.name() == JWT_COOKIE_NAME || c.name() == REFRESH_COOKIE_NAME).all(|c| c.value().is_empty());
+    assert!(cleared, "The session cookies are removed");
+    let body          = response.json::<DeleteAccountResponse>().await.unwrap();
+    assert_eq!(body.message, "Account has been deleted");
+    assert_status(&app.post_login(&user.login_payload()).await, 401, Some("The account is gone"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn delete_account_should_notify_the_user() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    assert_status(&app.delete_account(&user.password).await, 200, None);
+
+    let email         = Email::parse(Secret::new(user.email.clone())).unwrap();
+    let content       = app.email_client.read().await.last_email_to(&email).unwrap();
+    assert!(content.contains("were deleted"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn delete_account_should_ban_the_current_token() {
+    let mut app      = TestApp::new().await;
+    let (user, auth) = setup_logged_in_user(&app).await;
+
+    assert_status(&app.delete_account(&user.password).await, 200, None);
+
+    assert_status(&app.post_verify_token(&json!({"token": auth})).await, 401, None);
+    add_token_to_cookie_jar(&app, &auth);
+    assert_status(&app.delete_account(&user.password).await, 401, Some("The banned token cannot be used again"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn delete_account_should_revoke_the_other_sessions() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+    let device        = app.another_device();
+    let login         = device.post_login(&user.login_payload()).await;
+    let other_auth    = get_cookie_value(&login, JWT_COOKIE_NAME);
+
+    assert_status(&app.delete_account(&user.password).await, 200, None);
+
+    assert_status(&app.post_verify_token(&json!({"token": other_auth})).await, 401, Some("Auth token of the other device"));
+    assert_status(&device.post_refresh().await, 401, Some("Refresh token of the other device"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn delete_account_should_drop_pending_2fa_logins() {
+    let mut app    = TestApp::new().await;
+    let user       = setup_2fa_user_logged_in(&app).await;
+    let device     = app.another_device();
+    let attempt_id = start_2fa_login(&device, &user).await;
+    let code       = get_2fa_code(&app, &attempt_id).await;
+
+    assert_status(&app.delete_account(&user.password).await, 200, None);
+
+    let data       = TwoFAData {login_attempt_id: attempt_id, two_fa_code: code};
+    let verified   = device.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+    assert_status(&verified, 401, Some("A login started before the deletion cannot finish"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn delete_account_should_erase_the_second_factors() {
+    // Arrange
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+    let _secret       = setup_confirmed_totp(&app).await;
+
+    // Act
+    assert_status(&app.delete_account(&user.password).await, 200, None);
+
+    // Assert: the address can sign up again, and the new account starts without an authenticator
+    let response      = app.post_signup(&user.signup_payload()).await;
+    assert_status(&response, 201, Some("The address is free again"));
+    setup_verified_email(&app, &user.email).await;
+    assert_status(&app.post_login(&user.login_payload()).await, 200, Some("No authenticator is asked for"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn delete_account_should_return_401_for_a_wrong_password() {
+    let mut app       = TestApp::new().await;
+    let (user, _auth) = setup_logged_in_user(&app).await;
+
+    let response      = app.delete_account("Wrong1234**").await;
+
+    assert_status(&response, 401, None);
+    assert_status(&app.post_login(&user.login_payload()).await, 200, Some("The account is kept"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn delete_account_should_return_400_without_an_auth_cookie() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+
+    let response = app.delete_account(&user.password).await;
+
+    assert_status(&response, 400, None);
+    assert_status(&app.post_login(&user.login_payload()).await, 200, Some("The account is kept"));
+    app.clean_up().await;
+}
@@ -0,0 +1,204 @@
+use crate::helpers_arrange::*;
+use crate::helpers_assert::{assert_error_message, assert_status};
+use crate::helpers_harness::TestApp;
+use auth_service::routes::Email2FAEnableResponse;
+use auth_service::utils::constants::MAX_2FA_ATTEMPTS;
+
+// Start turning on email 2FA for the logged in user. Returns the attempt id and its code.
+//
+async fn start_enable(app: &TestApp, user: &TestUser) -> (String, String) {
+    let response   = app.post_email_2fa_enable(&user.password).await;
+    assert_status(&response, 200, Some("Enabling 2FA failed"));
+    let attempt_id = response
+        .json::<Email2FAEnableResponse>()
+        .await
+        .expect("Could not deserialize response body to Email2FAEnableResponse")
+        .attempt_id;
+    let code       = last_emailed_code(app, &user.email).await;
+    (attempt_id, code)
+}
+
+#[tokio::test]
+async fn enable_should_require_2fa_at_login_once_confirmed() {
+    // Arrange
+    let mut app             = TestApp::new().await;
+    let (user, _)           = setup_logged_in_user(&app).await;
+    let (attempt_id, code)  = start_enable(&app, &user).await;
+
+    // Act
+    let response            = app.post_email_2fa_confirm(&attempt_id, &code).await;
+
+    // Assert
+    assert_status(&response, 200, None);
+    let response            = app.another_device().post_login(&user.login_payload()).await;
+    assert_status(&response, 206, Some("Login must ask for a code once 2FA is on"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn enable_should_not_change_login_until_confirmed() {
+    let mut app   = TestApp::new().await;
+    let (user, _) = setup_logged_in_user(&app).await;
+    start_enable(&app, &user).await;
+
+    let response  = app.another_device().post_login(&user.login_payload()).await;
+
+    assert_status(&response, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn enable_should_return_400_without_an_auth_cookie() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_email_2fa_enable("Password123!").await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn enable_should_return_401_for_a_wrong_password() {
+    let mut app   = TestApp::new().await;
+    let (user, _) = setup_logged_in_user(&app).await;
+    let response  = app.post_email_2fa_enable(&format!("{}x", user.password)).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn enable_should_return_409_if_already_enabled() {
+    let mut app  = TestApp::new().await;
+    let user     = setup_2fa_user_logged_in(&app).await;
+    let response = app.post_email_2fa_enable(&user.password).await;
+    assert_status(&response, 409, None);
+    assert_error_message(response, "Two-factor authentication already enabled").await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn enable_should_return_422_if_malformed_input() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let response = app.http_client
+        .post(format!("{}/2fa/email/enable", &app.address))
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .expect("Failed to execute email 2fa enable request.");
+    assert_status(&response, 422, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirm_should_return_401_for_a_wrong_code() {
+    let mut app            = TestApp::new().await;
+    let (user, _)          = setup_logged_in_user(&app).await;
+    let (attempt_id, code) = start_enable(&app, &user).await;
+    let response           = app.post_email_2fa_confirm(&attempt_id, wrong_code(&code)).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirm_should_return_429_and_drop_the_code_after_too_many_wrong_codes() {
+    let mut app            = TestApp::new().await;
+    let (user, _)          = setup_logged_in_user(&app).await;
+    let (attempt_id, code) = start_enable(&app, &user).await;
+    for _ in 1..*MAX_2FA_ATTEMPTS {
+        let response = app.post_email_2fa_confirm(&attempt_id, wrong_code(&code)).await;
+        assert_status(&response, 401, None);
+    }
+
+    let response           = app.post_email_2fa_confirm(&attempt_id, wrong_code(&code)).await;
+
+    assert_status(&response, 429, None);
+    let response           = app.post_email_2fa_confirm(&attempt_id, &code).await;
+    assert_status(&response, 401, Some("The code must be gone after too many wrong ones"));
+    app.clean_up().await;
+}
+
+// The confirmation waits among the user's pending login attempts, and finishing a login
+// with a second factor drops those.
+//
+#[tokio::test]
+async fn confirm_should_return_401_after_a_2fa_login_elsewhere() {
+    // Arrange
+    let mut app            = TestApp::new().await;
+    let (user, _)          = setup_logged_in_user(&app).await;
+    let secret             = setup_confirmed_totp(&app).await;
+    let (attempt_id, code) = start_enable(&app, &user).await;
+    let device             = app.another_device();
+    let login_attempt_id   = start_2fa_login(&device, &user).await;
+    let data               = TwoFAData {login_attempt_id, two_fa_code: totp_code_steps_ahead(&secret, 1)};
+    let verified           = device.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+    assert_status(&verified, 200, Some("Login on the other device"));
+
+    // Act
+    let response           = app.post_email_2fa_confirm(&attempt_id, &code).await;
+
+    // Assert
+    assert_status(&response, 401, Some("The confirmation went with the pending login attempts"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirm_should_return_401_for_someone_elses_attempt() {
+    let mut app            = TestApp::new().await;
+    let (other, _)         = setup_logged_in_user(&app).await;
+    let (attempt_id, code) = start_enable(&app, &other).await;
+    let device             = app.another_device();
+    let (user, _)          = setup_logged_in_user(&device).await;
+
+    let response           = device.post_email_2fa_confirm(&attempt_id, &code).await;
+
+    assert_status(&response, 401, None);
+    let response           = device.post_login(&user.login_payload()).await;
+    assert_status(&response, 200, Some("2FA must stay off for the user"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirm_should_return_400_if_invalid_input() {
+    let mut app            = TestApp::new().await;
+    let (user, _)          = setup_logged_in_user(&app).await;
+    let (attempt_id, code) = start_enable(&app, &user).await;
+    let test_cases         = [("not-a-uuid", code.as_str()), (attempt_id.as_str(), "12345")];
+    for (attempt_id, code) in test_cases {
+        let response = app.post_email_2fa_confirm(attempt_id, code).await;
+        assert_status(&response, 400, Some(&format!("Failed for input: {:?}", (attempt_id, code))));
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn disable_should_stop_asking_for_a_code_at_login() {
+    let mut app  = TestApp::new().await;
+    let user     = setup_2fa_user_logged_in(&app).await;
+
+    let response = app.post_email_2fa_disable(&user.password).await;
+
+    assert_status(&response, 200, None);
+    let response = app.another_device().post_login(&user.login_payload()).await;
+    assert_status(&response, 200, Some("Login must not ask for a code once 2FA is off"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn disable_should_return_401_for_a_wrong_password() {
+    let mut app  = TestApp::new().await;
+    let user     = setup_2fa_user_logged_in(&app).await;
+    let response = app.post_email_2fa_disable(&format!("{}x", user.password)).await;
+    assert_status(&response, 401, None);
+    let response = app.another_device().post_login(&user.login_payload()).await;
+    assert_status(&response, 206, Some("2FA must stay on"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn disable_should_return_400_if_not_enabled() {
+    let mut app   = TestApp::new().await;
+    let (user, _) = setup_logged_in_user(&app).await;
+    let response  = app.post_email_2fa_disable(&user.password).await;
+    assert_status(&response, 400, None);
+    assert_error_message(response, "Two-factor authentication not enabled").await;
+    app.clean_up().await;
+}
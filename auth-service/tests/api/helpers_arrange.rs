@@ -1,9 +1,15 @@
 use crate::helpers_harness::{get_random_email, TestApp};
-use auth_service::domain::Email;
-use auth_service::routes::TwoFactorAuthResponse;
-use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::domain::{Email, LoginAttemptId, PhoneNumber, TwoFACode};
+use auth_service::routes::{PasskeyLoginStartResponse, SignupResponse, TotpEnrollmentResponse, TwoFactorAuthResponse};
+use auth_service::utils::auth::Claims;
+use auth_service::utils::constants::{test, JWT_COOKIE_NAME, REFRESH_COOKIE_NAME, TWO_FA_CODE_HASHER};
+use auth_service::utils::totp;
 use reqwest::Url;
 use secrecy::Secret;
+use std::time::Duration;
+use webauthn_authenticator_rs::softpasskey::SoftPasskey;
+use webauthn_authenticator_rs::WebauthnAuthenticator;
+use webauthn_rs::prelude::{CreationChallengeResponse, PublicKeyCredential};
 
 /// Represents a test user's credentials
 #[derive(Clone)]
@@ -46,6 +52,15 @@ impl TestUser {
             "password": self.password,
         })
 	}
+
+	/// Get a login JSON payload for this user that asks to be remembered
+	pub fn remembered_login_payload(&self) -> serde_json::Value {
+		serde_json::json!({
+            "email":      self.email,
+            "password":   self.password,
+            "rememberMe": true,
+        })
+	}
 }
 
 /// Struct to hold 2FA verification data
@@ -64,9 +79,102 @@ pub async fn setup_registered_user(app: &TestApp, user: &TestUser) -> TestUser {
 	let status = response.status().as_u16();
 	println!("Signup response {} .", status);
 	assert_eq!(status, 201, "Failed to register a user with email: {}", user.email );
+	setup_verified_email(app, &user.email).await;
 	user.clone()
 }
 
+/// Open the latest verification link emailed to the address
+/// (Use this in the 'arrange' phase only, not act)
+pub async fn setup_verified_email(app: &TestApp, email: &str) {
+	let token    = last_verification_token(app, email).await;
+	let response = app.get_verify_email(&token).await;
+	assert_eq!(response.status().as_u16(), 200, "Failed to verify email: {}", email);
+}
+
+/// Get the token in the latest verification link emailed to the address
+pub async fn last_verification_token(app: &TestApp, email: &str) -> String {
+	let email   = Email::parse(Secret::new(email.to_owned())).unwrap();
+	let content = app.email_client.read().await
+		.last_email_to(&email)
+		.expect("No email was sent to the address");
+	content
+		.split_once("/verify-email?token=")
+		.map(|(_, token)| token.trim().to_owned())
+		.expect("Email has no verification link")
+}
+
+/// Get the token in the latest email change link of the given kind ("confirm" or "cancel") sent to the address
+pub async fn last_email_change_token(app: &TestApp, email: &str, action: &str) -> String {
+	let email   = Email::parse(Secret::new(email.to_owned())).unwrap();
+	let content = app.email_client.read().await
+		.last_email_to(&email)
+		.expect("No email was sent to the address");
+	content
+		.split_once(&format!("/email/change/{}?token=", action))
+		.map(|(_, token)| token.trim().to_owned())
+		.expect("Email has no email change link")
+}
+
+/// Get the token in the latest password reset email sent to the address
+pub async fn last_password_reset_token(app: &TestApp, email: &str) -> String {
+	let email   = Email::parse(Secret::new(email.to_owned())).unwrap();
+	let content = app.email_client.read().await
+		.last_email_to(&email)
+		.expect("No email was sent to the address");
+	content
+		.split_once("reset your password within ")
+		.and_then(|(_, rest)| rest.lines().next())
+		.and_then(|line| line.split_once(": "))
+		.map(|(_, token)| token.trim().to_owned())
+		.expect("Email has no password reset token")
+}
+
+/// How many emails went to the address so far
+pub async fn emails_sent_to(app: &TestApp, email: &str) -> usize {
+	let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+	app.email_client.read().await.count_emails_to(&email)
+}
+
+/// Wait until more than `sent` emails went to the address. Some emails are only sent
+/// after the response, so they can arrive a moment later.
+pub async fn await_email_to(app: &TestApp, email: &str, sent: usize) {
+	for _ in 0..200 {
+		if emails_sent_to(app, email).await > sent {
+			return;
+		}
+		tokio::time::sleep(Duration::from_millis(10)).await;
+	}
+	panic!("No email was sent to the address");
+}
+
+/// Register a user with 2FA and return the recovery codes handed out at signup
+/// (Use this in the 'arrange' phase only, not act)
+pub async fn setup_2fa_user_with_recovery_codes(app: &TestApp) -> (TestUser, Vec<String>) {
+	let user     = TestUser::new_with_2fa();
+	let response = app.post_signup(&user.signup_payload()).await;
+	assert_eq!(response.status().as_u16(), 201, "Failed to register a user with email: {}", user.email);
+	setup_verified_email(app, &user.email).await;
+	let codes    = response
+		.json::<SignupResponse>()
+		.await
+		.expect("Could not deserialize response body to SignupResponse")
+		.recovery_codes
+		.expect("No recovery codes in the signup response");
+	(user, codes)
+}
+
+/// Start a login for a user with 2FA and return its login attempt id
+/// (Use this in the 'arrange' phase only, not act)
+pub async fn start_2fa_login(app: &TestApp, user: &TestUser) -> String {
+	let response = app.post_login(&user.login_payload()).await;
+	assert_eq!(response.status().as_u16(), 206, "Expected 206 for 2FA login");
+	response
+		.json::<TwoFactorAuthResponse>()
+		.await
+		.expect("Could not deserialize response body to TwoFactorAuthResponse")
+		.login_attempt_id
+}
+
 /// Setup a user with standard login (no 2FA)
 /// (Use this in the 'arrange' phase only, not act)
 pub async fn setup_logged_in_user(app: &TestApp) -> (TestUser, String) {
@@ -111,31 +219,69 @@ pub async fn setup_2fa_login_started(app: &TestApp) -> (TestUser, TwoFAData) {
 		.expect("Could not deserialize response body to TwoFactorAuthResponse");
 	assert_eq!(response_body.message, "2FA required".to_owned());
 
-	// Get the 2FA code
-	let (login_attempt_id, two_fa_code) = get_2fa_code_tuple(app, &registered_user.email).await;
-
-	// Verify login_attempt_id matches what's in the response
-	assert_eq!(login_attempt_id, response_body.login_attempt_id);
+	// Get the 2FA code of the login attempt in the response
+	let login_attempt_id = response_body.login_attempt_id;
+	let two_fa_code      = get_2fa_code(app, &login_attempt_id).await;
 
 	let two_fa_data = TwoFAData {login_attempt_id, two_fa_code};
 	(registered_user, two_fa_data)
 }
 
-/// Get the 2FA code tuple for a user
+/// Setup a user with 2FA, logged in through the second factor
+/// (Use this in the 'arrange' phase only, not act)
+pub async fn setup_2fa_user_logged_in(app: &TestApp) -> TestUser {
+	let (user, two_fa_data) = setup_2fa_login_started(app).await;
+	let response            = app.post_verify_2fa(&create_2fa_payload(&user.email, &two_fa_data)).await;
+	assert_eq!(response.status().as_u16(), 200, "2FA login failed");
+	user
+}
+
+/// Get the 2FA code of a login attempt from the latest email sent for it.
+/// The store only has a hash of the code, so the email is the only place to read it.
 /// (Use this in the arrange phase only, not act)
-pub async fn get_2fa_code_tuple(app: &TestApp, email: &str) -> (String, String) {
-	let email      = Secret::new(email.to_string());
-	let email      = Email::parse(email).unwrap();
-	let code_tuple = app
-		.two_fa_code_store
-		.read()
-		.await
-		.get_code(&email)
-		.await
-		.expect("Failed to get 2FA code");
-	let id   = code_tuple.0.as_ref().to_owned();
-	let code = code_tuple.1.as_ref().to_owned();
-	(id, code)
+pub async fn get_2fa_code(app: &TestApp, login_attempt_id: &str) -> String {
+	let content = app.email_client.read().await
+		.last_email_containing(login_attempt_id)
+		.expect("No 2FA code was emailed for the login attempt");
+	code_in_email(&content)
+}
+
+/// Get the code in the latest email sent to the address
+/// (Use this in the arrange phase only, not act)
+pub async fn last_emailed_code(app: &TestApp, email: &str) -> String {
+	let email   = Email::parse(Secret::new(email.to_owned())).unwrap();
+	let content = app.email_client.read().await
+		.last_email_to(&email)
+		.expect("No email was sent to the address");
+	code_in_email(&content)
+}
+
+fn code_in_email(content: &str) -> String {
+	content
+		.split_once("Code [")
+		.and_then(|(_, rest)| rest.split_once(']'))
+		.map(|(code, _)| code.to_owned())
+		.expect("Email has no code")
+}
+
+/// Replace the code of a login attempt with one the test knows, e.g. when no code was sent
+/// (Use this in the arrange phase only, not act)
+pub async fn set_2fa_code(app: &TestApp, email: &str, login_attempt_id: &str, code: &str) {
+	let email     = Email::parse(Secret::new(email.to_owned())).unwrap();
+	let id        = LoginAttemptId::parse(login_attempt_id.to_owned()).unwrap();
+	let code      = TwoFACode::parse(code.to_owned()).unwrap();
+	let hash      = TWO_FA_CODE_HASHER.hash(&code, id.as_ref());
+	let mut store = app.two_fa_code_store.write().await;
+	store.remove_code(&id).await.expect("Failed to remove 2FA code");
+	store.add_code(email, id, hash).await.expect("Failed to set 2FA code");
+}
+
+/// A well formed code that is not the given one
+pub fn wrong_code(code: &str) -> &'static str {
+	match code {
+		"000000" => "111111",
+		_        => "000000",
+	}
 }
 
 /// Create 2FA verification JSON payload
@@ -153,3 +299,138 @@ pub fn add_token_to_cookie_jar(app: &TestApp, token: &str) {
 	let url    = Url::parse("http://127.0.0.1").expect("Failed to parse URL");
 	app.cookie_jar.add_cookie_str(&cookie, &url);
 }
+
+/// Add an arbitrary refresh token to the cookie jar
+pub fn add_refresh_token_to_cookie_jar(app: &TestApp, token: &str) {
+	let cookie = format!("{}={}", REFRESH_COOKIE_NAME, token);
+	let url    = Url::parse("http://127.0.0.1").expect("Failed to parse URL");
+	app.cookie_jar.add_cookie_str(&cookie, &url);
+}
+
+/// Read the claims of a token without verifying it
+pub fn get_token_claims(token: &str) -> Claims {
+	let mut validation = jsonwebtoken::Validation::default();
+	validation.insecure_disable_signature_validation();
+	validation.validate_aud = false;
+	let key            = jsonwebtoken::DecodingKey::from_secret(&[]);
+	jsonwebtoken::decode::<Claims>(token, &key, &validation)
+		.expect("Failed to read token claims")
+		.claims
+}
+
+/// Get the value of a cookie set by a response
+pub fn get_cookie_value(response: &reqwest::Response, name: &str) -> String {
+	response
+		.cookies()
+		.find(|cookie| cookie.name() == name)
+		.map(|cookie| cookie.value().to_string())
+		.unwrap_or_else(|| panic!("No {} cookie found", name))
+}
+
+/// Get the Max-Age, in seconds, of a cookie set by a response
+pub fn get_cookie_max_age(response: &reqwest::Response, name: &str) -> Option<u64> {
+	response
+		.cookies()
+		.find(|cookie| cookie.name() == name)
+		.unwrap_or_else(|| panic!("No {} cookie found", name))
+		.max_age()
+		.map(|age| age.as_secs())
+}
+
+/// The current code of an authenticator app holding the base32 encoded secret
+pub fn current_totp_code(secret: &str) -> String {
+	totp_code_steps_ahead(secret, 0)
+}
+
+/// The code an authenticator app will show a number of time steps from now.
+/// Codes up to TOTP_SKEW_STEPS ahead are accepted, which lets a test log in
+/// again without waiting for the clock after the current code was used.
+pub fn totp_code_steps_ahead(secret: &str, steps: u64) -> String {
+	let secret = data_encoding::BASE32_NOPAD.decode(secret.as_bytes()).expect("Secret is not base32");
+	let now    = chrono::Utc::now().timestamp() as u64;
+	totp::code_for_step(&secret, totp::time_step(now) + steps)
+}
+
+/// Enroll and confirm an authenticator app for the logged in user. Returns its secret.
+/// (Use this in the arrange phase only, not act)
+pub async fn setup_confirmed_totp(app: &TestApp) -> String {
+	let enrolled = app.post_totp_enroll().await;
+	assert_eq!(enrolled.status().as_u16(), 200, "Enrollment failed");
+	let body     = enrolled
+		.json::<TotpEnrollmentResponse>()
+		.await
+		.expect("Could not deserialize response body to TotpEnrollmentResponse");
+	let code      = current_totp_code(&body.secret);
+	let confirmed = app.post_totp_confirm(&code).await;
+	assert_eq!(confirmed.status().as_u16(), 200, "Confirmation failed");
+	body.secret
+}
+
+/// The phone number tests register. Every test app has a mock SMS client of its own.
+pub const TEST_PHONE_NUMBER: &str = "+15550109999";
+
+/// The last code the mock SMS client sent to a phone number
+pub async fn last_sms_code(app: &TestApp, phone_number: &str) -> String {
+	let phone_number = PhoneNumber::parse(Secret::new(phone_number.to_owned())).unwrap();
+	let message      = app.sms_client.read().await
+		.last_message_to(&phone_number)
+		.expect("No SMS was sent to the phone number");
+	message
+		.split_whitespace()
+		.last()
+		.expect("SMS is empty")
+		.to_owned()
+}
+
+/// Register and verify TEST_PHONE_NUMBER for the logged in user
+/// (Use this in the arrange phase only, not act)
+pub async fn setup_verified_phone(app: &TestApp) {
+	let registered = app.post_phone(TEST_PHONE_NUMBER).await;
+	assert_eq!(registered.status().as_u16(), 200, "Phone registration failed");
+	let code       = last_sms_code(app, TEST_PHONE_NUMBER).await;
+	let verified   = app.post_phone_verify(&code).await;
+	assert_eq!(verified.status().as_u16(), 200, "Phone verification failed");
+}
+
+/// A software authenticator, standing in for a browser and a security key
+pub type TestAuthenticator = WebauthnAuthenticator<SoftPasskey>;
+
+pub fn new_test_authenticator() -> TestAuthenticator {
+	WebauthnAuthenticator::new(SoftPasskey::new(true))
+}
+
+/// The origin of the app our passkeys belong to
+pub fn passkey_origin() -> Url {
+	Url::parse(test::URI_APP).expect("Invalid app URI")
+}
+
+/// Register a passkey on the authenticator for the logged in user
+/// (Use this in the arrange phase only, not act)
+pub async fn setup_registered_passkey(app: &TestApp, authenticator: &mut TestAuthenticator) {
+	let started    = app.post_passkey("register/start", &serde_json::json!({})).await;
+	assert_eq!(started.status().as_u16(), 200, "Passkey registration did not start");
+	let options    = started
+		.json::<CreationChallengeResponse>()
+		.await
+		.expect("Could not deserialize response body to CreationChallengeResponse");
+	let credential = authenticator
+		.do_registration(passkey_origin(), options)
+		.expect("Authenticator failed to register");
+	let finished   = app.post_passkey("register/finish", &credential).await;
+	assert_eq!(finished.status().as_u16(), 201, "Passkey registration did not finish");
+}
+
+/// Start a passkey login and sign its challenge. Returns the ceremony id and the signed credential.
+/// (Use this in the arrange phase only, not act)
+pub async fn sign_passkey_login(app: &TestApp, email: &str, authenticator: &mut TestAuthenticator) -> (String, PublicKeyCredential) {
+	let started    = app.post_passkey("login/start", &serde_json::json!({"email": email})).await;
+	assert_eq!(started.status().as_u16(), 200, "Passkey login did not start");
+	let started    = started
+		.json::<PasskeyLoginStartResponse>()
+		.await
+		.expect("Could not deserialize response body to PasskeyLoginStartResponse");
+	let credential = authenticator
+		.do_authentication(passkey_origin(), started.options)
+		.expect("Authenticator failed to sign");
+	(started.ceremony_id, credential)
+}
@@ -1,9 +1,21 @@
 use auth_service::app_state::{AppState, TokenStoreType, TwoFactorCodeStoreType};
+use auth_service::services::data_stores::postgres_2fa_code_store::PostgresTwoFACodeStore;
+use auth_service::services::data_stores::postgres_banned_token_store::PostgresBannedTokenStore;
+use auth_service::services::data_stores::postgres_email_change_store::PostgresEmailChangeStore;
+use auth_service::services::data_stores::postgres_passkey_store::PostgresPasskeyStore;
+use auth_service::services::data_stores::postgres_phone_store::PostgresPhoneStore;
+use auth_service::services::data_stores::postgres_recovery_code_store::PostgresRecoveryCodeStore;
+use auth_service::services::data_stores::postgres_session_store::PostgresSessionStore;
+use auth_service::services::data_stores::postgres_totp_store::PostgresTotpStore;
 use auth_service::services::data_stores::postgres_user_store::PostgresUserStore;
 use auth_service::services::data_stores::redis_2fa_code_store::RedisTwoFACodeStore;
 use auth_service::services::data_stores::redis_banned_token_store::RedisBannedTokenStore;
+use auth_service::services::data_stores::redis_password_reset_token_store::RedisPasswordResetTokenStore;
+use auth_service::services::data_stores::redis_refresh_token_store::RedisRefreshTokenStore;
+use auth_service::services::data_stores::redis_webauthn_challenge_store::RedisWebAuthnChallengeStore;
 use auth_service::services::mock_email_client::MockEmailClient;
-use auth_service::utils::constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME};
+use auth_service::services::mock_sms_client::MockSmsClient;
+use auth_service::utils::constants::{env, test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME, STORE_BACKEND, STORE_BACKEND_POSTGRES};
 use auth_service::{create_redis_client, Application};
 use reqwest::cookie::Jar;
 use secrecy::ExposeSecret;
@@ -15,12 +27,18 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+pub const TEST_USER_AGENT:            &str = "auth-service-tests";
+pub const TEST_INTROSPECTION_CLIENT: &str = "test-client";
+pub const TEST_INTROSPECTION_SECRET: &str = "test-client-secret";
+
 pub struct TestApp
 {
 	pub address:           String,
 	pub banned_tokens:     TokenStoreType,
 	pub cookie_jar:        Arc<Jar>,
 	pub two_fa_code_store: TwoFactorCodeStoreType,
+	pub email_client:      Arc<RwLock<MockEmailClient>>,
+	pub sms_client:        Arc<RwLock<MockSmsClient>>,
 	pub http_client:       reqwest::Client,
 	pub db_name:           String,
 	pub clean_up_called:   bool,
@@ -28,14 +46,26 @@ pub struct TestApp
 
 impl TestApp {
 	pub async fn new() -> Self {
+		let clients            = format!("{}:{}", TEST_INTROSPECTION_CLIENT, TEST_INTROSPECTION_SECRET);
+		std::env::set_var(env::INTROSPECTION_CLIENTS_ENV_VAR, clients);
+		std::env::set_var(env::WEBAUTHN_RP_ORIGIN_ENV_VAR, test::URI_APP);
 		let (pg_pool, db_name) = configure_postgresql().await;
-		let user_store         = PostgresUserStore::new(pg_pool);
+		let user_store         = PostgresUserStore::new(pg_pool.clone());
 		let user_store         = Arc::new(RwLock::new(user_store));
-		let redis_cx           = Arc::new(RwLock::new(configure_redis()));
-		let banned_tokens      = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_cx.clone())));
-		let two_fa_code_store  = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_cx)));
+		let sessions           = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+		let totp_store         = Arc::new(RwLock::new(PostgresTotpStore::new(pg_pool.clone())));
+		let recovery_codes     = Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+		let passkeys           = Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.clone())));
+		let phones             = Arc::new(RwLock::new(PostgresPhoneStore::new(pg_pool.clone())));
+		let email_changes      = Arc::new(RwLock::new(PostgresEmailChangeStore::new(pg_pool.clone())));
+		let redis_cx           = configure_redis().await;
+		let refresh_tokens     = Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_cx.clone())));
+		let challenges         = Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(redis_cx.clone())));
+		let password_resets    = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_cx.clone())));
+		let (banned_tokens, two_fa_code_store) = configure_token_stores(pg_pool, redis_cx);
 		let email_client       = Arc::new(RwLock::new(MockEmailClient::new()));
-		let app_state          = AppState::new(user_store, banned_tokens.clone(), two_fa_code_store.clone(), email_client);
+		let sms_client         = Arc::new(RwLock::new(MockSmsClient::new()));
+		let app_state          = AppState::new(user_store, banned_tokens.clone(), refresh_tokens, sessions, two_fa_code_store.clone(), totp_store, recovery_codes, passkeys, challenges, phones, password_resets, email_changes, email_client.clone(), sms_client.clone());
 		let app                = Application::build(app_state, test::APP_ADDRESS)
 			.await
 			.expect("Failed to build app");
@@ -51,6 +81,7 @@ impl TestApp {
 		let cookie_jar    = Arc::new(Jar::default());
 		let http_client   = reqwest::Client::builder()
 			.cookie_provider(cookie_jar.clone())
+			.user_agent(TEST_USER_AGENT)
 			.build()
 			.expect("Failed to build an http client");
 		let clean_up_called = false;
@@ -59,12 +90,36 @@ impl TestApp {
 			banned_tokens,
 			cookie_jar,
 			two_fa_code_store,
+			email_client,
+			sms_client,
 			http_client,
 			db_name,
 			clean_up_called,
 		}
 	}
 
+	/// A second client of the same app, with a cookie jar of its own.
+	/// The app it came from owns the database, and cleans it up.
+	pub fn another_device(&self) -> Self {
+		let cookie_jar  = Arc::new(Jar::default());
+		let http_client = reqwest::Client::builder()
+			.cookie_provider(cookie_jar.clone())
+			.user_agent(TEST_USER_AGENT)
+			.build()
+			.expect("Failed to build an http client");
+		Self{
+			address:           self.address.clone(),
+			banned_tokens:     self.banned_tokens.clone(),
+			cookie_jar,
+			two_fa_code_store: self.two_fa_code_store.clone(),
+			email_client:      self.email_client.clone(),
+			sms_client:        self.sms_client.clone(),
+			http_client,
+			db_name:           self.db_name.clone(),
+			clean_up_called:   true,
+		}
+	}
+
 	pub async fn clean_up(&mut self) {
 		if self.clean_up_called {
 			println!("Cleanup already called.");
@@ -83,6 +138,43 @@ impl TestApp {
 			.expect("Failed to execute root request.")
 	}
 
+	pub async fn get_jwks(&self) -> reqwest::Response {
+		let url = format!("{}/.well-known/jwks.json", &self.address);
+		self.http_client
+			.get(url)
+			.send()
+			.await
+			.expect("Failed to execute jwks request.")
+	}
+
+	pub async fn get_sessions(&self) -> reqwest::Response {
+		let url = format!("{}/sessions", &self.address);
+		self.http_client
+			.get(url)
+			.send()
+			.await
+			.expect("Failed to execute sessions request.")
+	}
+
+	pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+		let url = format!("{}/sessions/{}", &self.address, id);
+		self.http_client
+			.delete(url)
+			.send()
+			.await
+			.expect("Failed to execute delete session request.")
+	}
+
+	pub async fn delete_account(&self, password: &str) -> reqwest::Response {
+		let url = format!("{}/account", &self.address);
+		self.http_client
+			.delete(url)
+			.json(&serde_json::json!({"password": password}))
+			.send()
+			.await
+			.expect("Failed to execute delete account request.")
+	}
+
 	pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response 
 	where
 		Body: Serialize,
@@ -113,6 +205,21 @@ impl TestApp {
 		rv
 	}
 
+	pub async fn post_introspect(&self, token: &str, client: Option<(&str, &str)>) -> reqwest::Response {
+		let url     = format!("{}/introspect", &self.address);
+		let request = self.http_client
+			.post(url)
+			.form(&[("token", token)]);
+		let request = match client {
+			Some((id, secret)) => request.basic_auth(id, Some(secret)),
+			None               => request,
+		};
+		request
+			.send()
+			.await
+			.expect("Failed to execute introspect request.")
+	}
+
 	pub async fn post_logout(&self) -> reqwest::Response {
 		let url = format!("{}/logout", &self.address);
 		self.http_client
@@ -122,6 +229,168 @@ impl TestApp {
 			.expect("Failed to execute logout request.")
 	}
 
+	pub async fn post_logout_all(&self) -> reqwest::Response {
+		let url = format!("{}/logout-all", &self.address);
+		self.http_client
+			.post(url)
+			.send()
+			.await
+			.expect("Failed to execute logout-all request.")
+	}
+
+	pub async fn post_refresh(&self) -> reqwest::Response {
+		let url = format!("{}/refresh", &self.address);
+		self.http_client
+			.post(url)
+			.send()
+			.await
+			.expect("Failed to execute refresh request.")
+	}
+
+	pub async fn post_passkey<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+	where
+		Body: Serialize,
+	{
+		let url = format!("{}/passkeys/{}", &self.address, path);
+		self.http_client
+			.post(url)
+			.json(body)
+			.send()
+			.await
+			.expect("Failed to execute passkey request.")
+	}
+
+	pub async fn post_recovery_codes(&self) -> reqwest::Response {
+		let url = format!("{}/2fa/recovery-codes", &self.address);
+		self.http_client
+			.post(url)
+			.send()
+			.await
+			.expect("Failed to execute recovery codes request.")
+	}
+
+	pub async fn post_totp_enroll(&self) -> reqwest::Response {
+		let url = format!("{}/2fa/totp/enroll", &self.address);
+		self.http_client
+			.post(url)
+			.send()
+			.await
+			.expect("Failed to execute totp enroll request.")
+	}
+
+	pub async fn post_totp_confirm(&self, code: &str) -> reqwest::Response {
+		let url = format!("{}/2fa/totp/confirm", &self.address);
+		self.http_client
+			.post(url)
+			.json(&serde_json::json!({"code": code}))
+			.send()
+			.await
+			.expect("Failed to execute totp confirm request.")
+	}
+
+	pub async fn post_email_2fa_enable(&self, password: &str) -> reqwest::Response {
+		let url = format!("{}/2fa/email/enable", &self.address);
+		self.http_client
+			.post(url)
+			.json(&serde_json::json!({"password": password}))
+			.send()
+			.await
+			.expect("Failed to execute email 2fa enable request.")
+	}
+
+	pub async fn post_email_2fa_confirm(&self, attempt_id: &str, code: &str) -> reqwest::Response {
+		let url = format!("{}/2fa/email/confirm", &self.address);
+		self.http_client
+			.post(url)
+			.json(&serde_json::json!({"attemptId": attempt_id, "code": code}))
+			.send()
+			.await
+			.expect("Failed to execute email 2fa confirm request.")
+	}
+
+	pub async fn post_email_2fa_disable(&self, password: &str) -> reqwest::Response {
+		let url = format!("{}/2fa/email/disable", &self.address);
+		self.http_client
+			.post(url)
+			.json(&serde_json::json!({"password": password}))
+			.send()
+			.await
+			.expect("Failed to execute email 2fa disable request.")
+	}
+
+	pub async fn post_phone(&self, phone_number: &str) -> reqwest::Response {
+		let url = format!("{}/2fa/phone", &self.address);
+		self.http_client
+			.post(url)
+			.json(&serde_json::json!({"phoneNumber": phone_number}))
+			.send()
+			.await
+			.expect("Failed to execute phone request.")
+	}
+
+	pub async fn post_phone_verify(&self, code: &str) -> reqwest::Response {
+		let url = format!("{}/2fa/phone/verify", &self.address);
+		self.http_client
+			.post(url)
+			.json(&serde_json::json!({"code": code}))
+			.send()
+			.await
+			.expect("Failed to execute phone verify request.")
+	}
+
+	pub async fn post_2fa_channel(&self, channel: &str) -> reqwest::Response {
+		let url = format!("{}/2fa/channel", &self.address);
+		self.http_client
+			.post(url)
+			.json(&serde_json::json!({"channel": channel}))
+			.send()
+			.await
+			.expect("Failed to execute 2fa channel request.")
+	}
+
+	pub async fn post_password<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+	where
+		Body: Serialize,
+	{
+		let url = format!("{}/password/{}", &self.address, path);
+		self.http_client
+			.post(url)
+			.json(body)
+			.send()
+			.await
+			.expect("Failed to execute password request.")
+	}
+
+	pub async fn post_change_email(&self, new_email: &str, password: &str) -> reqwest::Response {
+		let url = format!("{}/email/change", &self.address);
+		self.http_client
+			.post(url)
+			.json(&serde_json::json!({"newEmail": new_email, "password": password}))
+			.send()
+			.await
+			.expect("Failed to execute change email request.")
+	}
+
+	pub async fn get_email_change(&self, action: &str, token: &str) -> reqwest::Response {
+		let url = format!("{}/email/change/{}", &self.address, action);
+		self.http_client
+			.get(url)
+			.query(&[("token", token)])
+			.send()
+			.await
+			.expect("Failed to execute email change request.")
+	}
+
+	pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+		let url = format!("{}/verify-email", &self.address);
+		self.http_client
+			.get(url)
+			.query(&[("token", token)])
+			.send()
+			.await
+			.expect("Failed to execute verify_email request.")
+	}
+
 	pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
 		where Body: Serialize
 	{
@@ -134,6 +403,18 @@ impl TestApp {
 			.expect("Failed to execute verify_2fa request.")
 	}
 
+	pub async fn post_resend_2fa<Body>(&self, body: &Body) -> reqwest::Response
+		where Body: Serialize
+	{
+		let url = format!("{}/resend-2fa", &self.address);
+		self.http_client
+			.post(url)
+			.json(body)
+			.send()
+			.await
+			.expect("Failed to execute resend_2fa request.")
+	}
+
 	pub async fn post_verify_token<Body>(&self, body: &Body) -> reqwest::Response 
 		where Body: Serialize
 	{
@@ -154,6 +435,28 @@ impl Drop for TestApp {
 		}
 	}
 }
+/// A database of its own and the shared Redis connection, to run the stores directly
+/// without an app in front of them.
+pub struct TestBackends
+{
+	pub pg_pool:  PgPool,
+	pub redis_cx: redis::aio::ConnectionManager,
+	db_name:      String,
+}
+
+impl TestBackends {
+	pub async fn new() -> Self {
+		let (pg_pool, db_name) = configure_postgresql().await;
+		let redis_cx           = configure_redis().await;
+		Self {pg_pool, redis_cx, db_name}
+	}
+
+	pub async fn clean_up(self) {
+		self.pg_pool.close().await;
+		delete_database(self.db_name.as_str()).await;
+	}
+}
+
 pub fn get_random_email() -> String { format!("{}@example.com", Uuid::new_v4()) }
 
 async fn configure_postgresql() -> (PgPool, String) {
@@ -228,12 +531,30 @@ async fn delete_database(db_name: &str) {
 }
 
 
-fn configure_redis() -> redis::Connection {
+// Run the suite with STORE_BACKEND=postgres to exercise the Postgres token and 2FA code stores.
+//
+fn configure_token_stores(pg_pool: PgPool, redis_cx: redis::aio::ConnectionManager) -> (TokenStoreType, TwoFactorCodeStoreType) {
+	match STORE_BACKEND.as_str() {
+		STORE_BACKEND_POSTGRES => {
+			let banned_tokens     = Arc::new(RwLock::new(PostgresBannedTokenStore::new(pg_pool.clone())));
+			let two_fa_code_store = Arc::new(RwLock::new(PostgresTwoFACodeStore::new(pg_pool)));
+			(banned_tokens, two_fa_code_store)
+		},
+		_ => {
+			let banned_tokens     = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_cx.clone())));
+			let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_cx)));
+			(banned_tokens, two_fa_code_store)
+		},
+	}
+}
+
+async fn configure_redis() -> redis::aio::ConnectionManager {
 	let e_client = "Failed to get Redis client.";
 	let e_cx     = "Failed to get Redis connection.";
 	let redis_hostname = DEFAULT_REDIS_HOSTNAME.to_owned();
 	create_redis_client(redis_hostname)
 		.expect(e_client)
-		.get_connection()
+		.get_connection_manager()
+		.await
 		.expect(e_cx)
 }
\ No newline at end of file
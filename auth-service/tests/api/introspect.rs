@@ -0,0 +1,70 @@
+use crate::helpers_arrange::setup_logged_in_user;
+use crate::helpers_assert::assert_status;
+use crate::helpers_harness::{TestApp, TEST_INTROSPECTION_CLIENT, TEST_INTROSPECTION_SECRET};
+use auth_service::routes::IntrospectResponse;
+
+const CLIENT: Option<(&str, &str)> = Some((TEST_INTROSPECTION_CLIENT, TEST_INTROSPECTION_SECRET));
+
+#[tokio::test]
+async fn should_describe_an_active_token() {
+    let mut app       = TestApp::new().await;                      // Arrange
+    let (user, token) = setup_logged_in_user(&app).await;
+
+    let response      = app.post_introspect(&token, CLIENT).await; // Act
+
+    assert_status(&response, 200, None);                           // Assert
+    let body          = response.json::<IntrospectResponse>().await.expect("Could not deserialize response");
+    assert!(body.active);
+    assert_eq!(body.sub.as_deref(),        Some(user.email.as_str()));
+    assert_eq!(body.token_type.as_deref(), Some("Bearer"));
+    assert!(body.scope.is_some_and(|s| !s.is_empty()));
+    assert!(body.roles.is_some_and(|r| !r.is_empty()));
+    assert!(body.iat.unwrap() < body.exp.unwrap());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn a_banned_token_should_be_inactive() {
+    let mut app        = TestApp::new().await;                     // Arrange
+    let (_user, token) = setup_logged_in_user(&app).await;
+    let logout         = app.post_logout().await;
+    assert_status(&logout, 200, None);
+
+    let response       = app.post_introspect(&token, CLIENT).await; // Act
+
+    assert_status(&response, 200, None);                           // Assert
+    let body           = response.json::<serde_json::Value>().await.expect("Could not deserialize response");
+    assert_eq!(body, serde_json::json!({"active": false}));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn a_malformed_token_should_be_inactive() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_introspect("not-a-token", CLIENT).await;
+    assert_status(&response, 200, None);
+    let body     = response.json::<IntrospectResponse>().await.expect("Could not deserialize response");
+    assert!(!body.active);
+    assert!(body.sub.is_none());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_without_client_credentials() {
+    let mut app        = TestApp::new().await;
+    let (_user, token) = setup_logged_in_user(&app).await;
+    let response       = app.post_introspect(&token, None).await;
+    assert_status(&response, 401, None);
+    assert!(response.headers().contains_key("www-authenticate"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_with_a_wrong_client_secret() {
+    let mut app        = TestApp::new().await;
+    let (_user, token) = setup_logged_in_user(&app).await;
+    let client         = Some((TEST_INTROSPECTION_CLIENT, "wrong-secret"));
+    let response       = app.post_introspect(&token, client).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
@@ -0,0 +1,40 @@
+use crate::helpers_arrange::{get_cookie_value, setup_registered_user, TestUser};
+use crate::helpers_assert::assert_status;
+use crate::helpers_harness::TestApp;
+use auth_service::utils::auth::Claims;
+use auth_service::utils::constants::{JWT_AUDIENCES, JWT_COOKIE_NAME};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{DecodingKey, Validation};
+
+#[tokio::test]
+async fn should_return_200_with_a_public_key_set() {
+    let mut app  = TestApp::new().await;
+    let response = app.get_jwks().await;                          // Act
+    assert_status(&response, 200, None);                          // Assert
+    let jwks     = response.json::<JwkSet>().await.expect("Could not deserialize JWKS");
+    assert!(!jwks.keys.is_empty());
+    assert!(jwks.keys.iter().all(|k| k.common.key_id.is_some()));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn auth_token_should_verify_against_the_published_key() {
+    let mut app    = TestApp::new().await;                        // Arrange
+    let user       = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let login      = app.post_login(&user.login_payload()).await;
+    let token      = get_cookie_value(&login, JWT_COOKIE_NAME);
+
+    let response   = app.get_jwks().await;                        // Act
+    let jwks       = response.json::<JwkSet>().await.expect("Could not deserialize JWKS");
+
+    let header     = jsonwebtoken::decode_header(&token).expect("Token header is invalid");  // Assert
+    let kid        = header.kid.expect("Token has no key id");
+    let jwk        = jwks.find(&kid).expect("Signing key is not published");
+    let key        = DecodingKey::from_jwk(jwk).expect("Published key is unusable");
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&JWT_AUDIENCES);
+    let claims         = jsonwebtoken::decode::<Claims>(&token, &key, &validation).expect("Token does not verify").claims;
+    assert_eq!(claims.sub, user.email);
+    app.clean_up().await;
+}
@@ -1,7 +1,8 @@
-use crate::helpers_arrange::{get_2fa_code_tuple, setup_registered_user, TestUser};
+use crate::helpers_arrange::{get_2fa_code, get_cookie_max_age, get_cookie_value, get_token_claims, setup_registered_user, setup_verified_email, TestUser};
 use crate::helpers_assert::{assert_has_auth_cookie, assert_status};
 use crate::helpers_harness::TestApp;
 use auth_service::routes::TwoFactorAuthResponse;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, PENDING_2FA_AUDIENCE, PENDING_2FA_COOKIE_NAME, TOKEN_POLICY, TWO_FA_CODE_TTL_SECONDS};
 use serde_json::json;
 use tracing::debug;
 
@@ -19,6 +20,36 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn auth_cookie_and_token_should_expire_after_the_token_lifetime() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let response = app.post_login(&user.login_payload()).await;
+    assert_status(&response, 200, None);
+
+    let ttl      = TOKEN_POLICY.access_ttl as u64;
+    let claims   = get_token_claims(&get_cookie_value(&response, JWT_COOKIE_NAME));
+    assert_eq!(get_cookie_max_age(&response, JWT_COOKIE_NAME), Some(ttl));
+    assert_eq!((claims.exp - claims.iat) as u64, ttl);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn remember_me_should_select_the_longer_lifetime() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let response = app.post_login(&user.remembered_login_payload()).await;
+    assert_status(&response, 200, None);
+
+    let ttl      = TOKEN_POLICY.remember_me_ttl as u64;
+    let claims   = get_token_claims(&get_cookie_value(&response, JWT_COOKIE_NAME));
+    assert_eq!(get_cookie_max_age(&response, JWT_COOKIE_NAME), Some(ttl));
+    assert_eq!((claims.exp - claims.iat) as u64, ttl);
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     let mut app      = TestApp::new().await;
@@ -35,9 +66,31 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     assert_eq!(json_body.message, "2FA required".to_owned());
     println!("JSON body: {:?}", json_body);
 
-    // Verify 2FA code was generated and matches the login attempt ID
-    let (login_attempt_id, _) = get_2fa_code_tuple(&app, &user.email).await;
-    assert_eq!(login_attempt_id, json_body.login_attempt_id);
+    // Verify a 2FA code was generated for the login attempt ID
+    let code = get_2fa_code(&app, &json_body.login_attempt_id).await;
+    assert_eq!(code.len(), 6);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_set_a_pending_2fa_cookie_if_2fa_enabled() {
+    let mut app   = TestApp::new().await;
+    let user      = TestUser::new_with_2fa();
+    setup_registered_user(&app, &user).await;
+
+    let response  = app.post_login(&user.login_payload()).await;
+
+    assert_status(&response, 206, None);
+    assert!(response.cookies().all(|c| c.name() != JWT_COOKIE_NAME), "No auth cookie before the second factor");
+    let pending   = get_cookie_value(&response, PENDING_2FA_COOKIE_NAME);
+    let claims    = get_token_claims(&pending);
+    assert_eq!(claims.aud, vec![PENDING_2FA_AUDIENCE.to_owned()]);
+    assert_eq!(get_cookie_max_age(&response, PENDING_2FA_COOKIE_NAME), Some(TWO_FA_CODE_TTL_SECONDS as u64));
+    let attempt   = response.json::<TwoFactorAuthResponse>().await.unwrap();
+    assert_eq!(claims.sid, attempt.login_attempt_id, "The pending token is bound to the login attempt");
+
+    let verified  = app.post_verify_token(&json!({"token": pending})).await;
+    assert_status(&verified, 401, Some("A pending 2FA token is not an auth token"));
     app.clean_up().await;
 }
 
@@ -128,6 +181,7 @@ pub async fn should_return_200_after_successful_signup()
     let mut app  = TestApp::new().await;
     let user     = TestUser::new();
     let _        = app.post_signup(&user.signup_payload()).await;
+    setup_verified_email(&app, &user.email).await;
     let response = app.post_login(&user.signup_payload()).await;
     assert_eq!(response.status().as_u16(), 200);
     app.clean_up().await;
@@ -0,0 +1,90 @@
+use crate::helpers_arrange::{add_refresh_token_to_cookie_jar, add_token_to_cookie_jar, create_2fa_payload, get_2fa_code, get_cookie_value, setup_2fa_user_logged_in, setup_logged_in_user, setup_registered_user, start_2fa_login, TestUser, TwoFAData};
+use crate::helpers_assert::assert_status;
+use crate::helpers_harness::TestApp;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use serde_json::json;
+
+#[tokio::test]
+async fn should_return_200_and_revoke_tokens_of_every_device() {
+    let mut app        = TestApp::new().await;                     // Arrange
+    let (user, first)  = setup_logged_in_user(&app).await;         // First device
+    let login          = app.post_login(&user.login_payload()).await;
+    let second         = get_cookie_value(&login, JWT_COOKIE_NAME); // Second device
+
+    let response       = app.post_logout_all().await;              // Act
+
+    assert_status(&response, 200, None);                           // Assert
+    for token in [first, second] {
+        let body       = json!({"token": token});
+        let verified   = app.post_verify_token(&body).await;
+        assert_status(&verified, 401, Some("Token issued before logout-all"));
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_refresh_tokens_of_other_devices() {
+    let mut app      = TestApp::new().await;                       // Arrange
+    let user         = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let login        = app.post_login(&user.login_payload()).await;
+    let refresh      = get_cookie_value(&login, REFRESH_COOKIE_NAME);
+    app.post_login(&user.login_payload()).await;
+    let response     = app.post_logout_all().await;
+    assert_status(&response, 200, None);
+    add_refresh_token_to_cookie_jar(&app, &refresh);
+
+    let response     = app.post_refresh().await;                   // Act
+
+    assert_status(&response, 401, None);                           // Assert
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_drop_pending_2fa_logins() {
+    let mut app    = TestApp::new().await;                         // Arrange
+    let user       = setup_2fa_user_logged_in(&app).await;
+    let device     = app.another_device();
+    let attempt_id = start_2fa_login(&device, &user).await;
+    let code       = get_2fa_code(&app, &attempt_id).await;
+    assert_status(&app.post_logout_all().await, 200, None);
+
+    let data       = TwoFAData {login_attempt_id: attempt_id, two_fa_code: code};
+    let verified   = device.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await; // Act
+
+    assert_status(&verified, 401, Some("A login started before logout-all cannot finish")); // Assert
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn tokens_issued_after_logout_all_are_valid() {
+    let mut app        = TestApp::new().await;                     // Arrange
+    let (user, _token) = setup_logged_in_user(&app).await;
+    let response       = app.post_logout_all().await;
+    assert_status(&response, 200, None);
+
+    let login          = app.post_login(&user.login_payload()).await; // Act
+    let token          = get_cookie_value(&login, JWT_COOKIE_NAME);
+    let body           = json!({"token": token});
+    let verified       = app.post_verify_token(&body).await;
+
+    assert_status(&verified, 200, None);                           // Assert
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_logout_all().await;
+    assert_status(&response, 400, Some("Missing JWT Cookie"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app  = TestApp::new().await;
+    add_token_to_cookie_jar(&app, "invalid");
+    let response = app.post_logout_all().await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
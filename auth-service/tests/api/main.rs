@@ -1,11 +1,28 @@
+mod change_password;
+mod change_email;
+mod delete_account;
+mod email_2fa;
 mod helpers_harness;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod passkeys;
+mod password_reset;
+mod phone;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
 mod root;
+mod sessions;
 mod signup;
+mod store_suites;
+mod totp;
 
 #[cfg(test)]
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
 mod helpers_assert;
 mod helpers_arrange;
\ No newline at end of file
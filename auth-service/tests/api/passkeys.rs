@@ -0,0 +1,179 @@
+use crate::helpers_arrange::{
+    create_2fa_payload, get_2fa_code, new_test_authenticator, passkey_origin, setup_2fa_login_started,
+    setup_logged_in_user, setup_registered_passkey, setup_registered_user, sign_passkey_login, start_2fa_login,
+    TestUser, TwoFAData,
+};
+use crate::helpers_assert::{assert_error_message, assert_has_auth_cookie, assert_status};
+use crate::helpers_harness::TestApp;
+use serde_json::{json, Value};
+
+async fn start_login(app: &TestApp, email: &str) -> Value {
+    let response = app.post_passkey("login/start", &json!({"email": email})).await;
+    assert_status(&response, 200, None);
+    response.json().await.unwrap()
+}
+
+fn shape(value: &Value) -> Vec<String> {
+    let mut keys = value.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+    keys.sort();
+    keys
+}
+
+#[tokio::test]
+async fn register_should_return_400_without_an_auth_cookie() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_passkey("register/start", &json!({})).await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn register_should_not_finish_a_ceremony_twice() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let started  = app.post_passkey("register/start", &json!({})).await;
+    let options  = started.json().await.unwrap();
+    let signed   = new_test_authenticator().do_registration(passkey_origin(), options).unwrap();
+    let first    = app.post_passkey("register/finish", &signed).await;
+    let second   = app.post_passkey("register/finish", &signed).await;
+    assert_status(&first,  201, None);
+    assert_status(&second, 401, Some("A registration ceremony finishes once"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_succeed_with_a_registered_passkey_alone() {
+    let mut app           = TestApp::new().await;                            // Arrange
+    let mut authenticator = new_test_authenticator();
+    let (user, _)         = setup_logged_in_user(&app).await;
+    setup_registered_passkey(&app, &mut authenticator).await;
+    app.post_logout().await;
+    let (ceremony_id, credential) = sign_passkey_login(&app, &user.email, &mut authenticator).await;
+
+    let body     = json!({"ceremonyId": ceremony_id, "credential": credential});
+    let response = app.post_passkey("login/finish", &body).await;            // Act
+
+    assert_status(&response, 200, None);                                     // Assert
+    assert_has_auth_cookie(&response);
+    let sessions = app.get_sessions().await;
+    assert_status(&sessions, 200, Some("The passkey login should be a full session"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_not_finish_a_ceremony_twice() {
+    let mut app           = TestApp::new().await;
+    let mut authenticator = new_test_authenticator();
+    let (user, _)         = setup_logged_in_user(&app).await;
+    setup_registered_passkey(&app, &mut authenticator).await;
+    let (ceremony_id, credential) = sign_passkey_login(&app, &user.email, &mut authenticator).await;
+    let body     = json!({"ceremonyId": ceremony_id, "credential": credential});
+    let first    = app.post_passkey("login/finish", &body).await;
+    let replayed = app.post_passkey("login/finish", &body).await;
+    assert_status(&first,    200, None);
+    assert_status(&replayed, 401, Some("A signed challenge must not be accepted twice"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_reject_a_passkey_signed_for_another_user() {
+    let mut app        = TestApp::new().await;
+    let mut theirs     = new_test_authenticator();
+    let (them, _)      = setup_logged_in_user(&app).await;
+    setup_registered_passkey(&app, &mut theirs).await;
+    let mut ours       = new_test_authenticator();
+    let (us, _)        = setup_logged_in_user(&app).await;
+    setup_registered_passkey(&app, &mut ours).await;
+
+    let (their_ceremony, _)    = sign_passkey_login(&app, &them.email, &mut theirs).await;
+    let (_, our_credential)    = sign_passkey_login(&app, &us.email,   &mut ours).await;
+    let body           = json!({"ceremonyId": their_ceremony, "credential": our_credential});
+    let response       = app.post_passkey("login/finish", &body).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_start_should_not_tell_who_has_passkeys() {
+    let mut app           = TestApp::new().await;                            // Arrange
+    let mut authenticator = new_test_authenticator();
+    let (with, _)         = setup_logged_in_user(&app).await;
+    setup_registered_passkey(&app, &mut authenticator).await;
+    let without           = setup_registered_user(&app, &TestUser::new()).await;
+
+    let theirs            = start_login(&app, &with.email).await;            // Act
+    let decoy             = start_login(&app, &without.email).await;
+    let again             = start_login(&app, &without.email).await;
+
+    assert_eq!(shape(&theirs), shape(&decoy));                               // Assert
+    assert_eq!(shape(&theirs["publicKey"]), shape(&decoy["publicKey"]));
+    assert_eq!(shape(&theirs["publicKey"]["allowCredentials"][0]), shape(&decoy["publicKey"]["allowCredentials"][0]));
+    assert_eq!(decoy["publicKey"]["allowCredentials"], again["publicKey"]["allowCredentials"], "Decoy credentials must not change");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_finish_should_return_401_for_a_decoy_ceremony() {
+    let mut app         = TestApp::new().await;
+    let user            = setup_registered_user(&app, &TestUser::new()).await;
+    let started         = start_login(&app, &user.email).await;
+    let mut other       = new_test_authenticator();
+    let (someone, _)    = setup_logged_in_user(&app).await;
+    setup_registered_passkey(&app, &mut other).await;
+    let (_, credential) = sign_passkey_login(&app, &someone.email, &mut other).await;
+
+    let body            = json!({"ceremonyId": started["ceremonyId"], "credential": credential});
+    let response        = app.post_passkey("login/finish", &body).await;
+
+    assert_status(&response, 401, None);
+    assert_error_message(response, "Authorization failure").await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_start_should_return_400_for_a_malformed_email() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_passkey("login/start", &json!({"email": "not_an_email"})).await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn a_passkey_should_answer_the_2fa_challenge_of_a_password_login() {
+    let mut app           = TestApp::new().await;                            // Arrange: a 2FA user with a passkey
+    let mut authenticator = new_test_authenticator();
+    let (user, data)      = setup_2fa_login_started(&app).await;
+    let verified          = app.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+    assert_status(&verified, 200, None);
+    setup_registered_passkey(&app, &mut authenticator).await;
+    app.post_logout().await;
+
+    let attempt_id        = start_2fa_login(&app, &user).await;              // Act: password, then passkey
+    let code              = get_2fa_code(&app, &attempt_id).await;
+    let (ceremony_id, credential) = sign_passkey_login(&app, &user.email, &mut authenticator).await;
+    let body              = json!({"ceremonyId": ceremony_id, "credential": credential, "loginAttemptId": attempt_id});
+    let response          = app.post_passkey("login/finish", &body).await;
+
+    assert_status(&response, 200, None);                                     // Assert
+    assert_has_auth_cookie(&response);
+    let data              = TwoFAData {login_attempt_id: attempt_id, two_fa_code: code};
+    let emailed           = app.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+    assert_status(&emailed, 401, Some("The emailed code is spent once a passkey answered"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn a_passkey_should_not_answer_another_login_attempt() {
+    let mut app           = TestApp::new().await;
+    let mut authenticator = new_test_authenticator();
+    let (user, data)      = setup_2fa_login_started(&app).await;
+    app.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+    setup_registered_passkey(&app, &mut authenticator).await;
+    app.post_login(&user.login_payload()).await;
+
+    let (ceremony_id, credential) = sign_passkey_login(&app, &user.email, &mut authenticator).await;
+    let body     = json!({"ceremonyId": ceremony_id, "credential": credential, "loginAttemptId": data.login_attempt_id});
+    let response = app.post_passkey("login/finish", &body).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
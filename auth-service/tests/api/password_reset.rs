@@ -0,0 +1,194 @@
+use crate::helpers_arrange::*;
+use crate::helpers_assert::assert_status;
+use crate::helpers_harness::{get_random_email, TestApp};
+use auth_service::domain::Email;
+use auth_service::routes::PasswordResponse;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use secrecy::Secret;
+use serde_json::json;
+
+const NEW_PASSWORD: &str = "Battery5678!";
+
+// Ask for a reset token for a registered user, and return it
+//
+async fn request_reset_token(app: &TestApp, user: &TestUser) -> String {
+    let sent     = emails_sent_to(app, &user.email).await;
+    let response = app.post_password("forgot", &json!({"email": user.email})).await;
+    assert_status(&response, 200, None);
+    await_email_to(app, &user.email, sent).await;
+    last_password_reset_token(app, &user.email).await
+}
+
+fn reset_payload(token: &str, password: &str) -> serde_json::Value {
+    json!({"token": token, "newPassword": password})
+}
+
+#[tokio::test]
+async fn forgot_password_should_email_a_reset_token() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let sent     = emails_sent_to(&app, &user.email).await;
+
+    let response = app.post_password("forgot", &json!({"email": user.email})).await;
+
+    assert_status(&response, 200, None);
+    await_email_to(&app, &user.email, sent).await;
+    let token    = last_password_reset_token(&app, &user.email).await;
+    assert!(!token.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn forgot_password_should_not_reveal_whether_the_account_exists() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let known    = app.post_password("forgot", &json!({"email": user.email})).await;
+    let unknown  = get_random_email();
+
+    let response = app.post_password("forgot", &json!({"email": unknown})).await;
+
+    assert_status(&known, 200, None);
+    assert_status(&response, 200, None);
+    let known    = known.json::<PasswordResponse>().await.unwrap();
+    let body     = response.json::<PasswordResponse>().await.unwrap();
+    assert_eq!(body, known);
+    let unknown  = Email::parse(Secret::new(unknown)).unwrap();
+    assert!(app.email_client.read().await.last_email_to(&unknown).is_none(), "Nothing is sent to an unknown address");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn forgot_password_should_return_400_for_a_malformed_email() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_password("forgot", &json!({"email": "not-an-email"})).await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn reset_password_should_replace_the_password() {
+    // Arrange
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let token    = request_reset_token(&app, &user).await;
+
+    // Act
+    let response = app.post_password("reset", &reset_payload(&token, NEW_PASSWORD)).await;
+
+    // Assert
+    assert_status(&response, 200, None);
+    let body     = response.json::<PasswordResponse>().await.unwrap();
+    assert_eq!(body.message, "Password has been reset");
+    let old      = app.post_login(&user.login_payload()).await;
+    assert_status(&old, 401, Some("The old password no longer works"));
+    let renewed  = TestUser::with_attributes(Some(&user.email), Some(NEW_PASSWORD), false);
+    let new      = app.post_login(&renewed.login_payload()).await;
+    assert_status(&new, 200, Some("The new password works"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn reset_password_should_revoke_every_session() {
+    let mut app      = TestApp::new().await;
+    let (user, auth) = setup_logged_in_user(&app).await;
+    let login        = app.post_login(&user.login_payload()).await;
+    let refresh      = get_cookie_value(&login, REFRESH_COOKIE_NAME);
+    let token        = request_reset_token(&app, &user).await;
+    let device       = app.another_device();
+
+    let response     = device.post_password("reset", &reset_payload(&token, NEW_PASSWORD)).await;
+
+    assert_status(&response, 200, None);
+    let verified     = app.post_verify_token(&json!({"token": auth})).await;
+    assert_status(&verified, 401, Some("Auth tokens issued before the reset are revoked"));
+    add_refresh_token_to_cookie_jar(&app, &refresh);
+    assert_status(&app.post_refresh().await, 401, Some("Refresh tokens issued before the reset are revoked"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn tokens_issued_after_the_reset_are_valid() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let token    = request_reset_token(&app, &user).await;
+    assert_status(&app.post_password("reset", &reset_payload(&token, NEW_PASSWORD)).await, 200, None);
+
+    let renewed  = TestUser::with_attributes(Some(&user.email), Some(NEW_PASSWORD), false);
+    let login    = app.post_login(&renewed.login_payload()).await;
+    let auth     = get_cookie_value(&login, JWT_COOKIE_NAME);
+    let verified = app.post_verify_token(&json!({"token": auth})).await;
+
+    assert_status(&verified, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn reset_token_should_only_work_once() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let token    = request_reset_token(&app, &user).await;
+    assert_status(&app.post_password("reset", &reset_payload(&token, NEW_PASSWORD)).await, 200, None);
+
+    let response = app.post_password("reset", &reset_payload(&token, "Another9012!")).await;
+
+    assert_status(&response, 401, Some("A reset token must not be accepted twice"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn a_new_reset_token_should_replace_the_previous_one() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let first    = request_reset_token(&app, &user).await;
+    let second   = request_reset_token(&app, &user).await;
+
+    let response = app.post_password("reset", &reset_payload(&first, NEW_PASSWORD)).await;
+
+    assert_ne!(first, second);
+    assert_status(&response, 401, Some("Only the latest reset token works"));
+    assert_status(&app.post_password("reset", &reset_payload(&second, NEW_PASSWORD)).await, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn reset_password_should_return_401_for_an_unknown_token() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_password("reset", &reset_payload("not-a-token", NEW_PASSWORD)).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn reset_password_should_reject_an_invalid_password_without_using_up_the_token() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let token    = request_reset_token(&app, &user).await;
+
+    let response = app.post_password("reset", &reset_payload(&token, "")).await;
+
+    assert_status(&response, 400, None);
+    assert_status(&app.post_password("reset", &reset_payload(&token, NEW_PASSWORD)).await, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn reset_password_should_notify_the_user() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let token    = request_reset_token(&app, &user).await;
+
+    assert_status(&app.post_password("reset", &reset_payload(&token, NEW_PASSWORD)).await, 200, None);
+
+    let email    = Email::parse(Secret::new(user.email.clone())).unwrap();
+    let content  = app.email_client.read().await.last_email_to(&email).unwrap();
+    assert!(content.contains("password of your account was reset"));
+    app.clean_up().await;
+}
@@ -0,0 +1,242 @@
+use crate::helpers_arrange::*;
+use crate::helpers_assert::{assert_error_message, assert_status};
+use crate::helpers_harness::TestApp;
+use auth_service::utils::constants::MAX_2FA_ATTEMPTS;
+use serde_json::json;
+
+#[tokio::test]
+async fn register_should_return_200_and_text_a_code_to_the_phone() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+
+    let response = app.post_phone("+1 (555) 010-9999").await;
+
+    assert_status(&response, 200, None);
+    let code     = last_sms_code(&app, TEST_PHONE_NUMBER).await;
+    assert_eq!(code.len(), 6, "The SMS must carry a 6 digit code");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn register_should_return_400_without_an_auth_cookie() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_phone(TEST_PHONE_NUMBER).await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn register_should_return_400_if_invalid_phone_number() {
+    let mut app    = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let test_cases = ["", "5550109999", "+1555", "+1555010999x"];
+    for test_case in test_cases {
+        let response = app.post_phone(test_case).await;
+        assert_status(&response, 400, Some(&format!("Failed for input: {:?}", test_case)));
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_should_return_200_for_the_code_sent_to_the_phone() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    app.post_phone(TEST_PHONE_NUMBER).await;
+    let code     = last_sms_code(&app, TEST_PHONE_NUMBER).await;
+
+    let response = app.post_phone_verify(&code).await;
+
+    assert_status(&response, 200, None);
+    let response = app.post_phone_verify(&code).await;
+    assert_status(&response, 401, Some("A code verifies the number only once"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_should_return_401_for_a_wrong_code() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    app.post_phone(TEST_PHONE_NUMBER).await;
+    let code     = last_sms_code(&app, TEST_PHONE_NUMBER).await;
+
+    let response = app.post_phone_verify(wrong_code(&code)).await;
+
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_should_return_429_and_drop_the_code_after_too_many_wrong_codes() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    app.post_phone(TEST_PHONE_NUMBER).await;
+    let code     = last_sms_code(&app, TEST_PHONE_NUMBER).await;
+    for _ in 1..*MAX_2FA_ATTEMPTS {
+        let response = app.post_phone_verify(wrong_code(&code)).await;
+        assert_status(&response, 401, None);
+    }
+
+    let response = app.post_phone_verify(wrong_code(&code)).await;
+
+    assert_status(&response, 429, None);
+    let response = app.post_phone_verify(&code).await;
+    assert_status(&response, 401, Some("The code must be gone after too many wrong ones"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn register_should_return_429_if_registered_again_within_the_cooldown() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let response = app.post_phone(TEST_PHONE_NUMBER).await;
+    assert_status(&response, 200, None);
+
+    let response = app.post_phone("+15550108888").await;
+
+    assert_status(&response, 429, Some("Every registration texts a code"));
+    assert_error_message(response, "Please wait before requesting another code").await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn register_should_not_forget_too_many_wrong_codes() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    app.post_phone(TEST_PHONE_NUMBER).await;
+    let code     = last_sms_code(&app, TEST_PHONE_NUMBER).await;
+    for _ in 0..*MAX_2FA_ATTEMPTS {
+        app.post_phone_verify(wrong_code(&code)).await;
+    }
+
+    let response = app.post_phone(TEST_PHONE_NUMBER).await;
+
+    assert_status(&response, 429, Some("Registering again must not reset the wrong codes"));
+    assert_error_message(response, "Too many attempts").await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_should_return_400_if_malformed_code() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    app.post_phone(TEST_PHONE_NUMBER).await;
+    let response = app.post_phone_verify("12345").await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn channel_should_return_400_for_sms_without_a_verified_phone() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let response = app.post_2fa_channel("sms").await;
+    assert_status(&response, 400, Some("No phone number registered"));
+    assert_error_message(response, "Phone number not verified").await;
+
+    app.post_phone(TEST_PHONE_NUMBER).await;
+    let response = app.post_2fa_channel("sms").await;
+    assert_status(&response, 400, Some("Phone number not verified yet"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn channel_should_return_200_for_email_without_a_phone() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let response = app.post_2fa_channel("email").await;
+    assert_status(&response, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn channel_should_return_422_for_an_unknown_channel() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let response = app.post_2fa_channel("pigeon").await;
+    assert_status(&response, 422, None);
+    let response = app.http_client
+        .post(format!("{}/2fa/channel", &app.address))
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("Failed to execute 2fa channel request.");
+    assert_status(&response, 422, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_text_the_code_once_sms_is_chosen() {
+    // Arrange
+    let mut app          = TestApp::new().await;
+    let user             = setup_2fa_user_logged_in(&app).await;
+    setup_verified_phone(&app).await;
+    let response         = app.post_2fa_channel("sms").await;
+    assert_status(&response, 200, None);
+    let device           = app.another_device();
+
+    // Act
+    let login_attempt_id = start_2fa_login(&device, &user).await;
+
+    // Assert
+    let emailed          = app.email_client.read().await.last_email_containing(&login_attempt_id);
+    assert!(emailed.is_none(), "The code must not be emailed as well");
+    let two_fa_code      = last_sms_code(&app, TEST_PHONE_NUMBER).await;
+    let two_fa_data      = TwoFAData {login_attempt_id, two_fa_code};
+    let response         = device.post_verify_2fa(&create_2fa_payload(&user.email, &two_fa_data)).await;
+    assert_status(&response, 200, Some("The texted code must log the user in"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn resend_should_text_the_new_code_once_sms_is_chosen() {
+    let mut app          = TestApp::new().await;
+    let user             = setup_2fa_user_logged_in(&app).await;
+    setup_verified_phone(&app).await;
+    app.post_2fa_channel("sms").await;
+    let device           = app.another_device();
+    let login_attempt_id = start_2fa_login(&device, &user).await;
+
+    let response         = device.post_resend_2fa(&json!({"email": user.email, "loginAttemptId": login_attempt_id})).await;
+
+    assert_status(&response, 200, None);
+    let two_fa_code      = last_sms_code(&app, TEST_PHONE_NUMBER).await;
+    let two_fa_data      = TwoFAData {login_attempt_id, two_fa_code};
+    let response         = device.post_verify_2fa(&create_2fa_payload(&user.email, &two_fa_data)).await;
+    assert_status(&response, 200, Some("The texted code must be the current one"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_email_the_code_after_going_back_to_email() {
+    let mut app          = TestApp::new().await;
+    let user             = setup_2fa_user_logged_in(&app).await;
+    setup_verified_phone(&app).await;
+    app.post_2fa_channel("sms").await;
+    let response         = app.post_2fa_channel("email").await;
+    assert_status(&response, 200, None);
+    let verification     = last_sms_code(&app, TEST_PHONE_NUMBER).await;
+
+    start_2fa_login(&app.another_device(), &user).await;
+
+    assert_eq!(last_sms_code(&app, TEST_PHONE_NUMBER).await, verification, "No login code may be texted");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn registering_a_new_number_should_go_back_to_email_until_it_is_verified() {
+    let mut app          = TestApp::new().await;
+    let user             = setup_2fa_user_logged_in(&app).await;
+    setup_verified_phone(&app).await;
+    app.post_2fa_channel("sms").await;
+    let new_number       = "+15550108888";
+    let response         = app.post_phone(new_number).await;
+    assert_status(&response, 200, None);
+    let verification     = last_sms_code(&app, new_number).await;
+    let texted           = last_sms_code(&app, TEST_PHONE_NUMBER).await;
+
+    start_2fa_login(&app.another_device(), &user).await;
+
+    assert_eq!(last_sms_code(&app, new_number).await,        verification, "No login code may go to the unverified number");
+    assert_eq!(last_sms_code(&app, TEST_PHONE_NUMBER).await, texted,       "No login code may go to the replaced number");
+    app.clean_up().await;
+}
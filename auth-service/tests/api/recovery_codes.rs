@@ -0,0 +1,118 @@
+use crate::helpers_arrange::{create_2fa_payload, current_totp_code, setup_2fa_user_with_recovery_codes, setup_logged_in_user, start_2fa_login, TestUser, TwoFAData};
+use crate::helpers_assert::{assert_error_message, assert_has_auth_cookie, assert_status};
+use crate::helpers_harness::TestApp;
+use auth_service::routes::{RecoveryCodesResponse, SignupResponse, TotpEnrollmentResponse};
+use auth_service::utils::recovery_codes::RECOVERY_CODE_COUNT;
+use std::collections::HashSet;
+
+// Answer the 2FA challenge of a fresh login with a recovery code
+//
+async fn login_with_recovery_code(app: &TestApp, user: &TestUser, code: &str) -> reqwest::Response {
+    let login_attempt_id = start_2fa_login(app, user).await;
+    let data             = TwoFAData {login_attempt_id, two_fa_code: code.to_owned()};
+    app.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await
+}
+
+#[tokio::test]
+async fn signup_should_only_hand_out_recovery_codes_with_2fa() {
+    let mut app      = TestApp::new().await;
+    let (_, codes)   = setup_2fa_user_with_recovery_codes(&app).await;
+    let distinct     = codes.iter().collect::<HashSet<_>>();
+    assert_eq!(distinct.len(), RECOVERY_CODE_COUNT);
+
+    let user         = TestUser::new();
+    let response     = app.post_signup(&user.signup_payload()).await;
+    let body         = response.json::<SignupResponse>().await.unwrap();
+    assert_eq!(body.recovery_codes, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_2fa_should_accept_a_recovery_code_once() {
+    let mut app        = TestApp::new().await;                               // Arrange
+    let (user, codes)  = setup_2fa_user_with_recovery_codes(&app).await;
+
+    let response       = login_with_recovery_code(&app, &user, &codes[0]).await; // Act
+
+    assert_status(&response, 200, None);                                     // Assert
+    assert_has_auth_cookie(&response);
+    let replayed       = login_with_recovery_code(&app, &user, &codes[0]).await;
+    assert_status(&replayed, 401, Some("A recovery code must not be accepted twice"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_2fa_should_accept_recovery_codes_as_typed() {
+    let mut app        = TestApp::new().await;
+    let (user, codes)  = setup_2fa_user_with_recovery_codes(&app).await;
+    let typed          = codes[1].replace('-', " ").to_uppercase();
+    let response       = login_with_recovery_code(&app, &user, &typed).await;
+    assert_status(&response, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_2fa_should_return_401_for_an_unknown_recovery_code() {
+    let mut app        = TestApp::new().await;
+    let (user, codes)  = setup_2fa_user_with_recovery_codes(&app).await;
+    let unknown        = match codes.contains(&"aaaaa-aaaaa".to_owned()) {
+        true  => "bbbbb-bbbbb",
+        false => "aaaaa-aaaaa",
+    };
+    let response       = login_with_recovery_code(&app, &user, unknown).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn regenerate_should_return_400_without_an_auth_cookie() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_recovery_codes().await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn regenerate_should_return_400_without_2fa() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let response = app.post_recovery_codes().await;
+    assert_status(&response, 400, None);
+    assert_error_message(response, "Two-factor authentication not enabled").await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn regenerate_should_replace_every_earlier_code() {
+    let mut app       = TestApp::new().await;                                // Arrange
+    let (user, old)   = setup_2fa_user_with_recovery_codes(&app).await;
+    let logged_in     = login_with_recovery_code(&app, &user, &old[0]).await;
+    assert_status(&logged_in, 200, None);
+
+    let response      = app.post_recovery_codes().await;                     // Act
+
+    assert_status(&response, 200, None);                                     // Assert
+    let new           = response.json::<RecoveryCodesResponse>().await.unwrap().recovery_codes;
+    assert_eq!(new.len(), RECOVERY_CODE_COUNT);
+    let old_code      = login_with_recovery_code(&app, &user, &old[1]).await;
+    assert_status(&old_code, 401, Some("Old recovery codes must stop working"));
+    let new_code      = login_with_recovery_code(&app, &user, &new[0]).await;
+    assert_status(&new_code, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirming_an_authenticator_should_hand_out_recovery_codes() {
+    let mut app       = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let enrolled      = app.post_totp_enroll().await.json::<TotpEnrollmentResponse>().await.unwrap();
+    let code          = current_totp_code(&enrolled.secret);
+    let response      = app.post_totp_confirm(&code).await;
+    assert_status(&response, 200, None);
+    let codes         = response.json::<RecoveryCodesResponse>().await.unwrap().recovery_codes;
+    assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+    let regenerated   = app.post_recovery_codes().await;                     // Authenticator users may regenerate
+    assert_status(&regenerated, 200, None);
+    app.clean_up().await;
+}
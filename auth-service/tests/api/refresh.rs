@@ -0,0 +1,122 @@
+use crate::helpers_arrange::{add_refresh_token_to_cookie_jar, get_cookie_max_age, get_cookie_value, setup_registered_user, TestUser};
+use crate::helpers_assert::{assert_has_auth_cookie, assert_status};
+use crate::helpers_harness::TestApp;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME, TOKEN_POLICY};
+use serde_json::json;
+
+#[tokio::test]
+async fn login_should_set_a_refresh_cookie() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let response = app.post_login(&user.login_payload()).await;   // Act
+    assert_status(&response, 200, None);                          // Assert
+    let refresh  = get_cookie_value(&response, REFRESH_COOKIE_NAME);
+    assert!(!refresh.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_rotate_both_tokens() {
+    let mut app       = TestApp::new().await;                        // Arrange
+    let user          = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let login         = app.post_login(&user.login_payload()).await;
+    let old_refresh   = get_cookie_value(&login, REFRESH_COOKIE_NAME);
+
+    let response      = app.post_refresh().await;                    // Act
+
+    assert_status(&response, 200, None);                             // Assert
+    assert_has_auth_cookie(&response);
+    let new_refresh   = get_cookie_value(&response, REFRESH_COOKIE_NAME);
+    assert_ne!(new_refresh, old_refresh);
+
+    let new_token     = get_cookie_value(&response, JWT_COOKIE_NAME);
+    let body          = json!({"token": new_token});
+    let verified      = app.post_verify_token(&body).await;
+    assert_status(&verified, 200, Some("Refreshed token should be valid"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn refreshed_token_should_keep_the_remember_me_lifetime() {
+    let mut app  = TestApp::new().await;
+    let user     = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let login    = app.post_login(&user.remembered_login_payload()).await;
+    assert_status(&login, 200, None);
+
+    let response = app.post_refresh().await;
+    assert_status(&response, 200, None);
+    let ttl      = TOKEN_POLICY.remember_me_ttl as u64;
+    assert_eq!(get_cookie_max_age(&response, JWT_COOKIE_NAME), Some(ttl));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_refresh().await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_refresh_token_unknown() {
+    let mut app  = TestApp::new().await;
+    add_refresh_token_to_cookie_jar(&app, "not-a-refresh-token");
+    let response = app.post_refresh().await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_the_family_if_a_refresh_token_is_reused() {
+    let mut app       = TestApp::new().await;                        // Arrange
+    let user          = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let login         = app.post_login(&user.login_payload()).await;
+    let old_refresh   = get_cookie_value(&login, REFRESH_COOKIE_NAME);
+    let rotated       = app.post_refresh().await;
+    assert_status(&rotated, 200, None);
+    let new_refresh   = get_cookie_value(&rotated, REFRESH_COOKIE_NAME);
+
+    add_refresh_token_to_cookie_jar(&app, &old_refresh);             // Act: replay the rotated-out token
+    let replay        = app.post_refresh().await;
+    add_refresh_token_to_cookie_jar(&app, &new_refresh);             // The legitimate holder tries next
+    let legitimate    = app.post_refresh().await;
+
+    assert_status(&replay,     401, Some("Replayed refresh token"));  // Assert
+    assert_status(&legitimate, 401, Some("Token family should be revoked"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn only_one_of_two_concurrent_refreshes_with_the_same_token_should_succeed() {
+    let mut app       = TestApp::new().await;                        // Arrange
+    let user          = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    app.post_login(&user.login_payload()).await;
+
+    let (a, b)        = tokio::join!(app.post_refresh(), app.post_refresh());  // Act
+
+    let statuses      = [a.status().as_u16(), b.status().as_u16()];    // Assert
+    assert!(statuses.contains(&200) && statuses.contains(&401), "Expected one 200 and one 401, got {:?}", statuses);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_refreshing_after_logout() {
+    let mut app       = TestApp::new().await;
+    let user          = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let login         = app.post_login(&user.login_payload()).await;
+    let refresh       = get_cookie_value(&login, REFRESH_COOKIE_NAME);
+    let logout        = app.post_logout().await;
+    assert_status(&logout, 200, None);
+
+    add_refresh_token_to_cookie_jar(&app, &refresh);
+    let response      = app.post_refresh().await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
@@ -0,0 +1,127 @@
+use crate::helpers_arrange::*;
+use crate::helpers_assert::*;
+use crate::helpers_harness::TestApp;
+use serde_json::json;
+
+fn resend_payload(email: &str, login_attempt_id: &str) -> serde_json::Value {
+    json!({
+        "email":          email,
+        "loginAttemptId": login_attempt_id,
+    })
+}
+
+#[tokio::test]
+async fn should_return_200_and_send_a_new_code_for_the_same_attempt() {
+    // Arrange
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+    let payload             = resend_payload(&user.email, &two_fa_data.login_attempt_id);
+
+    // Act
+    let response            = app.post_resend_2fa(&payload).await;
+
+    // Assert
+    assert_status(&response, 200, None);
+    let two_fa_code         = get_2fa_code(&app, &two_fa_data.login_attempt_id).await;
+    let resent              = TwoFAData {login_attempt_id: two_fa_data.login_attempt_id, two_fa_code};
+    let response            = app.post_verify_2fa(&create_2fa_payload(&user.email, &resent)).await;
+    assert_status(&response, 200, Some("The new code must log the user in"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_resent_again_within_the_cooldown() {
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+    let payload             = resend_payload(&user.email, &two_fa_data.login_attempt_id);
+    let response            = app.post_resend_2fa(&payload).await;
+    assert_status(&response, 200, None);
+
+    let response            = app.post_resend_2fa(&payload).await;
+
+    assert_status(&response, 429, None);
+    assert_error_message(response, "Please wait before requesting another code").await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_once_the_login_completed() {
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+    let response            = app.post_verify_2fa(&create_2fa_payload(&user.email, &two_fa_data)).await;
+    assert_status(&response, 200, None);
+
+    let response            = app.post_resend_2fa(&resend_payload(&user.email, &two_fa_data.login_attempt_id)).await;
+
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_the_login_attempt_is_someone_elses() {
+    let mut app             = TestApp::new().await;
+    let (_, two_fa_data)    = setup_2fa_login_started(&app).await;
+    let (other, _)          = setup_2fa_login_started(&app).await;
+
+    let response            = app.post_resend_2fa(&resend_payload(&other.email, &two_fa_data.login_attempt_id)).await;
+
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_without_the_pending_2fa_cookie_of_the_login() {
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+    let payload             = resend_payload(&user.email, &two_fa_data.login_attempt_id);
+
+    let response            = app.another_device().post_resend_2fa(&payload).await;
+
+    assert_status(&response, 401, Some("Only the client that logged in may ask for a new code"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_without_a_login_in_progress() {
+    let mut app  = TestApp::new().await;
+    let user     = setup_registered_user(&app, &TestUser::new_with_2fa()).await;
+    let response = app.post_resend_2fa(&resend_payload(&user.email, "b59d20f4-dfcf-4fc5-af99-e312e0e2d2aa")).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+    let test_cases          = [
+        resend_payload("not-an-email",  &two_fa_data.login_attempt_id),
+        resend_payload(&user.email,     "not-a-uuid"),
+    ];
+    for test_case in test_cases.iter() {
+        let response = app.post_resend_2fa(test_case).await;
+        assert_status(&response, 400, Some(&format!("Failed for input: {:?}", test_case)));
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_resend_2fa(&json!({"email": "user@example.com"})).await;
+    assert_status(&response, 422, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_409_for_authenticator_users() {
+    let mut app          = TestApp::new().await;
+    let (user, _)        = setup_logged_in_user(&app).await;
+    setup_confirmed_totp(&app).await;
+    let login_attempt_id = start_2fa_login(&app, &user).await;
+
+    let response         = app.post_resend_2fa(&resend_payload(&user.email, &login_attempt_id)).await;
+
+    assert_status(&response, 409, Some("Authenticator codes cannot be resent"));
+    app.clean_up().await;
+}
@@ -0,0 +1,119 @@
+use crate::helpers_arrange::{add_refresh_token_to_cookie_jar, get_cookie_value, setup_logged_in_user, setup_registered_user, TestUser};
+use crate::helpers_assert::assert_status;
+use crate::helpers_harness::{TestApp, TEST_USER_AGENT};
+use auth_service::routes::SessionsResponse;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use serde_json::json;
+
+async fn get_sessions(app: &TestApp) -> SessionsResponse {
+    let response = app.get_sessions().await;
+    assert_status(&response, 200, None);
+    response.json::<SessionsResponse>().await.expect("Could not deserialize sessions")
+}
+
+#[tokio::test]
+async fn login_should_record_a_session() {
+    let mut app  = TestApp::new().await;                           // Arrange
+    setup_logged_in_user(&app).await;
+
+    let response = get_sessions(&app).await;                       // Act
+
+    assert_eq!(response.sessions.len(), 1);                        // Assert
+    let session  = &response.sessions[0];
+    assert!(session.current);
+    assert_eq!(session.user_agent.as_deref(), Some(TEST_USER_AGENT));
+    assert_eq!(session.ip.as_deref(),         Some("127.0.0.1"));
+    assert_eq!(session.created_at, session.last_seen);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn every_login_should_start_its_own_session() {
+    let mut app       = TestApp::new().await;                      // Arrange
+    let (user, _jwt)  = setup_logged_in_user(&app).await;
+    app.post_login(&user.login_payload()).await;
+
+    let response      = get_sessions(&app).await;                  // Act
+
+    assert_eq!(response.sessions.len(), 2);                        // Assert
+    let current       = response.sessions.iter().filter(|s| s.current).count();
+    assert_eq!(current, 1);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app  = TestApp::new().await;
+    let response = app.get_sessions().await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn deleting_a_session_revokes_its_tokens() {
+    let mut app      = TestApp::new().await;                       // Arrange
+    let user         = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let other        = app.post_login(&user.login_payload()).await; // Other device
+    let other_jwt    = get_cookie_value(&other, JWT_COOKIE_NAME);
+    let other_rt     = get_cookie_value(&other, REFRESH_COOKIE_NAME);
+    app.post_login(&user.login_payload()).await;                   // This device
+    let sessions     = get_sessions(&app).await;
+    let other_id     = sessions.sessions.iter().find(|s| !s.current).unwrap().id.clone();
+
+    let response     = app.delete_session(&other_id).await;       // Act
+
+    assert_status(&response, 204, None);                           // Assert
+    let remaining    = get_sessions(&app).await;
+    assert_eq!(remaining.sessions.len(), 1);
+    assert!(remaining.sessions[0].current);
+    let verified     = app.post_verify_token(&json!({"token": other_jwt})).await;
+    assert_status(&verified, 401, Some("Auth token of a deleted session"));
+    add_refresh_token_to_cookie_jar(&app, &other_rt);
+    let refreshed    = app.post_refresh().await;
+    assert_status(&refreshed, 401, Some("Refresh token of a deleted session"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn deleting_the_current_session_logs_out() {
+    let mut app      = TestApp::new().await;                       // Arrange
+    setup_logged_in_user(&app).await;
+    let sessions     = get_sessions(&app).await;
+    let current_id   = sessions.sessions[0].id.clone();
+
+    let response     = app.delete_session(&current_id).await;     // Act
+
+    assert_status(&response, 204, None);                           // Assert
+    let auth_cookie  = response.cookies().find(|c| c.name() == JWT_COOKIE_NAME).unwrap();
+    assert!(auth_cookie.value().is_empty());
+    let listed       = app.get_sessions().await;
+    assert_status(&listed, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_an_unknown_session() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let response = app.delete_session("no-such-session").await;
+    assert_status(&response, 404, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn logout_should_end_the_session() {
+    let mut app      = TestApp::new().await;                       // Arrange
+    let (user, _jwt) = setup_logged_in_user(&app).await;
+    let other        = app.post_login(&user.login_payload()).await;
+    let other_jwt    = get_cookie_value(&other, JWT_COOKIE_NAME);
+    let response     = app.post_logout().await;                    // Act
+    assert_status(&response, 200, None);
+    app.post_login(&user.login_payload()).await;
+
+    let sessions     = get_sessions(&app).await;                   // Assert
+    assert_eq!(sessions.sessions.len(), 2);                        // The first login and the new one
+    let verified     = app.post_verify_token(&json!({"token": other_jwt})).await;
+    assert_status(&verified, 401, None);
+    app.clean_up().await;
+}
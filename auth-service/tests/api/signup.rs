@@ -5,6 +5,7 @@ use crate::helpers_arrange::TestUser;
 use crate::helpers_assert::{assert_error_message, assert_status};
 use crate::helpers_harness::get_random_email;
 use auth_service::routes::signup::SignupResponse;
+use auth_service::utils::recovery_codes::RECOVERY_CODE_COUNT;
 use serde_json::json;
 
 #[tokio::test]
@@ -22,16 +23,14 @@ pub async fn should_succeed_with_the_expected_result() {
     let random_email = get_random_email();
     let test_case    = json!({ "email": random_email, "password": "PassWord123!", "requires2FA": true});
     let message      = "User created successfully!".to_owned();
-    let expected     = SignupResponse{message};
     let response     = app.post_signup(&test_case).await;               // Act
 
-    assert_eq!(                                // Assert matches the correct response body!
-        response
-            .json::<SignupResponse>()
-            .await
-            .expect("Could not deserialize response body to UserBody"),
-        expected
-    );
+    let body         = response                // Assert matches the correct response body!
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to UserBody");
+    assert_eq!(body.message, message);
+    assert_eq!(body.recovery_codes.map(|codes| codes.len()), Some(RECOVERY_CODE_COUNT));
     app.clean_up().await;
 }
 
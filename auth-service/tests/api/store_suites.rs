@@ -0,0 +1,270 @@
+use crate::helpers_harness::{get_random_email, TestBackends};
+use auth_service::domain::{Email, LoginAttemptId, Password, PasswordResetTokenStore, PasswordResetTokenStoreError, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError, TokenStore, TokenStoreError, TwoFACode, TwoFACodeHash, TwoFACodeStore, TwoFACodeStoreError, User, UserStore, UserStoreError};
+use auth_service::services::data_stores::hashmap_2fa_code_store::HashmapTwoFACodeStore;
+use auth_service::services::data_stores::hashmap_password_reset_token_store::HashmapPasswordResetTokenStore;
+use auth_service::services::data_stores::postgres_2fa_code_store::PostgresTwoFACodeStore;
+use auth_service::services::data_stores::postgres_banned_token_store::PostgresBannedTokenStore;
+use auth_service::services::data_stores::postgres_user_store::PostgresUserStore;
+use auth_service::services::data_stores::redis_2fa_code_store::RedisTwoFACodeStore;
+use auth_service::services::data_stores::redis_banned_token_store::RedisBannedTokenStore;
+use auth_service::services::data_stores::redis_password_reset_token_store::RedisPasswordResetTokenStore;
+use auth_service::services::data_stores::redis_refresh_token_store::RedisRefreshTokenStore;
+use auth_service::utils::code_hasher::CodeHasher;
+use auth_service::utils::constants::{PASSWORD_RESET_TOKEN_PREFIX, PASSWORD_RESET_USER_PREFIX};
+use chrono::Utc;
+use redis::AsyncCommands;
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+// The same behaviour is asked of every implementation of a store trait. Each suite runs
+// against the Redis and the Postgres store, and the 2FA code suite against the in-memory
+// one too. Password reset tokens have no Postgres store, so theirs runs against Redis and
+// the in-memory store. Redis is shared between tests running in parallel, so every suite works with
+// ids and emails of its own.
+//
+fn random_email() -> Email {
+    Email::parse(Secret::new(get_random_email())).unwrap()
+}
+
+fn hash_of(code: &str) -> TwoFACodeHash {
+    let code = TwoFACode::parse(code.to_owned()).unwrap();
+    CodeHasher::new(&[7u8; 32]).unwrap().hash(&code, "store-suite")
+}
+
+fn in_seconds(seconds: i64) -> usize {
+    (Utc::now().timestamp() + seconds) as usize
+}
+
+fn random_token() -> Secret<String> {
+    Secret::new(Uuid::new_v4().to_string())
+}
+
+async fn token_store_suite(store: &mut impl TokenStore) {
+    // A banned token is found until it is deleted
+    let jti = Uuid::new_v4().to_string();
+    assert_eq!(store.contains_token(&jti).await, Ok(false));
+    assert_eq!(store.add_token(&jti, in_seconds(60)).await, Ok(()));
+    assert_eq!(store.contains_token(&jti).await, Ok(true), "A banned token is found");
+    assert_eq!(store.delete_token(&jti).await, Ok(()));
+    assert_eq!(store.contains_token(&jti).await, Ok(false), "A deleted token is not found");
+
+    // Banning a token that has already expired is a no-op
+    let jti = Uuid::new_v4().to_string();
+    assert_eq!(store.add_token(&jti, in_seconds(-1)).await, Ok(()));
+    assert_eq!(store.contains_token(&jti).await, Ok(false), "An expired token is not banned");
+
+    // Blank ids are refused
+    assert_eq!(store.add_token("", in_seconds(60)).await, Err(TokenStoreError::BlankToken));
+    assert_eq!(store.set_not_before("", 1).await,         Err(TokenStoreError::BlankToken));
+
+    // The latest not before epoch of a subject wins
+    let subject = get_random_email();
+    assert_eq!(store.get_not_before(&subject).await, Ok(None));
+    assert_eq!(store.set_not_before(&subject, 100).await, Ok(()));
+    assert_eq!(store.get_not_before(&subject).await, Ok(Some(100)));
+    assert_eq!(store.set_not_before(&subject, 200).await, Ok(()));
+    assert_eq!(store.get_not_before(&subject).await, Ok(Some(200)));
+    assert_eq!(store.get_not_before(&get_random_email()).await, Ok(None), "Epochs are per subject");
+}
+
+async fn two_fa_code_store_suite(store: &mut impl TwoFACodeStore) {
+    // Unknown login attempts are not found
+    let unknown = LoginAttemptId::default();
+    assert_eq!(store.get_code(&unknown).await,       Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    assert_eq!(store.record_failure(&unknown).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    assert_eq!(store.resend_code(&unknown, hash_of("123456"), 0, 3).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
+    // Login attempts of one user coexist, and removing one leaves the other
+    let email  = random_email();
+    let phone  = LoginAttemptId::default();
+    let laptop = LoginAttemptId::default();
+    assert_eq!(store.add_code(email.clone(), phone.clone(),  hash_of("111111")).await, Ok(()));
+    assert_eq!(store.add_code(email.clone(), laptop.clone(), hash_of("222222")).await, Ok(()));
+    let (owner, code) = store.get_code(&phone).await.unwrap();
+    assert!(owner == email);
+    assert_eq!(code, hash_of("111111"));
+    assert_eq!(store.get_code(&laptop).await.unwrap().1, hash_of("222222"));
+    assert_eq!(store.remove_code(&laptop).await, Ok(()));
+    assert_eq!(store.get_code(&laptop).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    assert!(store.get_code(&phone).await.is_ok(), "Removing one attempt must leave the other");
+
+    // Failures are counted per login attempt, and survive a resend that replaces the code
+    let second = LoginAttemptId::default();
+    assert_eq!(store.add_code(email.clone(), second.clone(), hash_of("333333")).await, Ok(()));
+    assert_eq!(store.record_failure(&phone).await,  Ok(1));
+    assert_eq!(store.record_failure(&phone).await,  Ok(2));
+    assert_eq!(store.record_failure(&second).await, Ok(1));
+    assert_eq!(store.resend_code(&phone, hash_of("444444"), 60, 2).await, Ok(()));
+    assert_eq!(store.get_code(&phone).await.unwrap().1, hash_of("444444"));
+    assert_eq!(store.record_failure(&phone).await,  Ok(3));
+
+    // Resends respect the cooldown and the limit
+    assert_eq!(store.resend_code(&phone, hash_of("555555"), 60, 2).await, Err(TwoFACodeStoreError::ResendTooSoon));
+    assert_eq!(store.resend_code(&second, hash_of("555555"), 0, 1).await, Ok(()));
+    assert_eq!(store.resend_code(&second, hash_of("666666"), 0, 1).await, Err(TwoFACodeStoreError::ResendLimitReached));
+
+    // Removing a user's codes drops only that user's login attempts
+    let other  = random_email();
+    let others = LoginAttemptId::default();
+    assert_eq!(store.add_code(other.clone(), others.clone(), hash_of("777777")).await, Ok(()));
+    assert_eq!(store.remove_codes(&email).await, Ok(()));
+    assert_eq!(store.get_code(&phone).await,  Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    assert_eq!(store.get_code(&second).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    assert!(store.get_code(&others).await.is_ok());
+}
+
+async fn password_reset_token_store_suite(store: &mut impl PasswordResetTokenStore) {
+    // Unknown tokens are not found
+    assert_eq!(store.take_token(&random_token()).await.err(), Some(PasswordResetTokenStoreError::TokenNotFound));
+
+    // A stored token returns its email, once
+    let (token, email) = (random_token(), random_email());
+    assert_eq!(store.add_token(&token, email.clone()).await, Ok(()));
+    assert_eq!(store.take_token(&token).await.unwrap(), email);
+    assert_eq!(store.take_token(&token).await.err(), Some(PasswordResetTokenStoreError::TokenNotFound), "A token is taken only once");
+
+    // A new token replaces the previous one of the same user, and only that one
+    let (first, second, third) = (random_token(), random_token(), random_token());
+    let (user, other)          = (random_email(), random_email());
+    assert_eq!(store.add_token(&first,  user.clone()).await,  Ok(()));
+    assert_eq!(store.add_token(&second, other.clone()).await, Ok(()));
+    assert_eq!(store.add_token(&third,  user.clone()).await,  Ok(()));
+    assert_eq!(store.take_token(&first).await.err(), Some(PasswordResetTokenStoreError::TokenNotFound));
+    assert_eq!(store.take_token(&second).await.unwrap(), other);
+    assert_eq!(store.take_token(&third).await.unwrap(),  user);
+}
+
+#[tokio::test]
+async fn redis_token_store_passes_the_token_store_suite() {
+    let backends  = TestBackends::new().await;
+    let mut store = RedisBannedTokenStore::new(backends.redis_cx.clone());
+    token_store_suite(&mut store).await;
+    backends.clean_up().await;
+}
+
+#[tokio::test]
+async fn postgres_token_store_passes_the_token_store_suite() {
+    let backends  = TestBackends::new().await;
+    let mut store = PostgresBannedTokenStore::new(backends.pg_pool.clone());
+    token_store_suite(&mut store).await;
+    backends.clean_up().await;
+}
+
+// A lookup that cannot reach the store is an error, never a "not banned".
+//
+#[tokio::test]
+async fn postgres_token_store_reports_a_failed_lookup() {
+    let backends = TestBackends::new().await;
+    let store    = PostgresBannedTokenStore::new(backends.pg_pool.clone());
+    backends.pg_pool.close().await;
+
+    let found    = store.contains_token(&Uuid::new_v4().to_string()).await;
+
+    assert!(matches!(found, Err(TokenStoreError::UnexpectedError(_))));
+    backends.clean_up().await;
+}
+
+#[tokio::test]
+async fn redis_2fa_code_store_passes_the_2fa_code_store_suite() {
+    let backends  = TestBackends::new().await;
+    let mut store = RedisTwoFACodeStore::new(backends.redis_cx.clone());
+    two_fa_code_store_suite(&mut store).await;
+    backends.clean_up().await;
+}
+
+#[tokio::test]
+async fn postgres_2fa_code_store_passes_the_2fa_code_store_suite() {
+    let backends  = TestBackends::new().await;
+    let mut store = PostgresTwoFACodeStore::new(backends.pg_pool.clone());
+    two_fa_code_store_suite(&mut store).await;
+    backends.clean_up().await;
+}
+
+#[tokio::test]
+async fn hashmap_2fa_code_store_passes_the_2fa_code_store_suite() {
+    let mut store = HashmapTwoFACodeStore::new();
+    two_fa_code_store_suite(&mut store).await;
+}
+
+#[tokio::test]
+async fn redis_password_reset_token_store_passes_the_password_reset_token_store_suite() {
+    let backends  = TestBackends::new().await;
+    let mut store = RedisPasswordResetTokenStore::new(backends.redis_cx.clone());
+    password_reset_token_store_suite(&mut store).await;
+    backends.clean_up().await;
+}
+
+#[tokio::test]
+async fn hashmap_password_reset_token_store_passes_the_password_reset_token_store_suite() {
+    let mut store = HashmapPasswordResetTokenStore::new();
+    password_reset_token_store_suite(&mut store).await;
+}
+
+// A reset link sent by one app instance can be opened on another.
+//
+#[tokio::test]
+async fn redis_password_reset_token_stores_on_the_same_redis_see_the_same_tokens() {
+    let backends       = TestBackends::new().await;
+    let mut first      = RedisPasswordResetTokenStore::new(backends.redis_cx.clone());
+    let mut second     = RedisPasswordResetTokenStore::new(backends.redis_cx.clone());
+    let (token, email) = (random_token(), random_email());
+    first.add_token(&token, email.clone()).await.unwrap();
+
+    assert_eq!(second.take_token(&token).await.unwrap(), email);
+    assert_eq!(first.take_token(&token).await.err(), Some(PasswordResetTokenStoreError::TokenNotFound));
+    backends.clean_up().await;
+}
+
+#[tokio::test]
+async fn redis_password_reset_token_store_keeps_tokens_and_emails_out_of_keys() {
+    let backends       = TestBackends::new().await;
+    let mut cx         = backends.redis_cx.clone();
+    let mut store      = RedisPasswordResetTokenStore::new(cx.clone());
+    let (token, email) = (random_token(), random_email());
+    store.add_token(&token, email.clone()).await.unwrap();
+
+    let by_token: bool = cx.exists(format!("{}:{}", PASSWORD_RESET_TOKEN_PREFIX, token.expose_secret())).await.unwrap();
+    let by_email: bool = cx.exists(format!("{}:{}", PASSWORD_RESET_USER_PREFIX, email.expose_secret())).await.unwrap();
+    assert!(!by_token, "Keys hold a digest of the token, not the token");
+    assert!(!by_email, "Keys hold a digest of the email, not the email");
+    backends.clean_up().await;
+}
+
+// Two app instances can consume the same refresh token at the same moment. Only one of them
+// may rotate it; the other sees a reuse.
+//
+#[tokio::test]
+async fn redis_refresh_token_store_lets_one_of_two_concurrent_consumers_through() {
+    let backends   = TestBackends::new().await;
+    let mut first  = RedisRefreshTokenStore::new(backends.redis_cx.clone());
+    let mut second = RedisRefreshTokenStore::new(backends.redis_cx.clone());
+    let token      = Secret::new(Uuid::new_v4().to_string());
+    let record     = RefreshTokenRecord::new(random_email(), Uuid::new_v4().to_string(), false);
+    first.add_token(&token, record).await.unwrap();
+
+    let (a, b)     = tokio::join!(first.consume_token(&token), second.consume_token(&token));
+
+    assert_eq!(a.is_ok() as u8 + b.is_ok() as u8, 1, "Exactly one consumer gets the token");
+    let refused    = a.err().or(b.err()).unwrap();
+    assert_eq!(refused, RefreshTokenStoreError::TokenReused);
+    backends.clean_up().await;
+}
+
+// The tombstone is written with the deletion, and found again by its id.
+//
+#[tokio::test]
+async fn postgres_user_store_leaves_a_tombstone_for_a_deleted_user() {
+    let backends  = TestBackends::new().await;
+    let mut store = PostgresUserStore::new(backends.pg_pool.clone());
+    let email     = random_email();
+    let password  = Password::parse(Secret::new("Horse1234!".to_owned())).unwrap();
+    store.add_user(User::new(email.clone(), password, false)).await.unwrap();
+
+    let tombstone = store.delete_user(&email).await.unwrap();
+
+    assert_eq!(store.get_user(&email).await.err(), Some(UserStoreError::UserNotFound));
+    assert_eq!(store.get_tombstone(&tombstone.id).await.map(|t| t.id), Ok(tombstone.id));
+    assert_eq!(store.delete_user(&email).await.err(), Some(UserStoreError::UserNotFound));
+    assert_eq!(store.get_tombstone(&Uuid::new_v4()).await.err(), Some(UserStoreError::TombstoneNotFound));
+    backends.clean_up().await;
+}
@@ -0,0 +1,118 @@
+use crate::helpers_arrange::{create_2fa_payload, current_totp_code, set_2fa_code, setup_confirmed_totp, setup_logged_in_user, totp_code_steps_ahead, TwoFAData};
+use crate::helpers_assert::{assert_error_message, assert_has_auth_cookie, assert_status};
+use crate::helpers_harness::TestApp;
+use auth_service::routes::{TotpEnrollmentResponse, TwoFactorAuthResponse};
+use auth_service::utils::constants::TOTP_ISSUER;
+
+#[tokio::test]
+async fn enroll_should_return_400_without_an_auth_cookie() {
+    let mut app  = TestApp::new().await;
+    let response = app.post_totp_enroll().await;
+    assert_status(&response, 400, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn enroll_should_return_a_secret_and_provisioning_uri() {
+    let mut app  = TestApp::new().await;                             // Arrange
+    let (user, _) = setup_logged_in_user(&app).await;
+
+    let response = app.post_totp_enroll().await;                     // Act
+
+    assert_status(&response, 200, None);                             // Assert
+    let body     = response.json::<TotpEnrollmentResponse>().await.unwrap();
+    let prefix   = format!("otpauth://totp/{}:{}?", TOTP_ISSUER, user.email);
+    assert!(body.otpauth_uri.starts_with(&prefix), "{}", body.otpauth_uri);
+    assert!(body.otpauth_uri.contains(&format!("secret={}", body.secret)));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirm_should_return_401_for_a_wrong_code() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let enrolled = app.post_totp_enroll().await.json::<TotpEnrollmentResponse>().await.unwrap();
+    let wrong    = match current_totp_code(&enrolled.secret).as_str() {
+        "000000" => "111111",
+        _        => "000000",
+    };
+    let response = app.post_totp_confirm(wrong).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn confirm_should_return_400_without_an_enrollment() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    let response = app.post_totp_confirm("123456").await;
+    assert_status(&response, 400, None);
+    assert_error_message(response, "Authenticator not enrolled").await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn enroll_should_return_409_once_confirmed() {
+    let mut app  = TestApp::new().await;
+    setup_logged_in_user(&app).await;
+    setup_confirmed_totp(&app).await;
+    let response = app.post_totp_enroll().await;
+    assert_status(&response, 409, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_accept_an_authenticator_code_once() {
+    let mut app   = TestApp::new().await;                            // Arrange
+    let (user, _) = setup_logged_in_user(&app).await;
+    let secret    = setup_confirmed_totp(&app).await;
+
+    let login     = app.post_login(&user.login_payload()).await;     // Act: enrolling turned on 2FA
+    assert_status(&login, 206, Some("Authenticator users need a second factor"));
+    let attempt   = login.json::<TwoFactorAuthResponse>().await.unwrap();
+    let code      = totp_code_steps_ahead(&secret, 1);               // The current one was spent confirming
+    let data      = TwoFAData {login_attempt_id: attempt.login_attempt_id, two_fa_code: code.clone()};
+    let verified  = app.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+
+    assert_status(&verified, 200, None);                             // Assert
+    assert_has_auth_cookie(&verified);
+
+    let login     = app.post_login(&user.login_payload()).await;     // The same code a second time
+    let attempt   = login.json::<TwoFactorAuthResponse>().await.unwrap();
+    let data      = TwoFAData {login_attempt_id: attempt.login_attempt_id, two_fa_code: code};
+    let replayed  = app.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+    assert_status(&replayed, 401, Some("A code must not be accepted twice"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_not_accept_the_stored_code_from_authenticator_users() {
+    let mut app   = TestApp::new().await;
+    let (user, _) = setup_logged_in_user(&app).await;
+    setup_confirmed_totp(&app).await;
+    let login     = app.post_login(&user.login_payload()).await;
+    assert_status(&login, 206, None);
+
+    let login_attempt_id = login.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+    let two_fa_code      = "123456".to_owned();
+    set_2fa_code(&app, &user.email, &login_attempt_id, &two_fa_code).await;
+    let data      = TwoFAData {login_attempt_id, two_fa_code};
+    let response  = app.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_not_accept_the_code_used_to_confirm() {
+    let mut app   = TestApp::new().await;
+    let (user, _) = setup_logged_in_user(&app).await;
+    let enrolled  = app.post_totp_enroll().await.json::<TotpEnrollmentResponse>().await.unwrap();
+    let code      = current_totp_code(&enrolled.secret);
+    assert_status(&app.post_totp_confirm(&code).await, 200, None);
+    let login     = app.post_login(&user.login_payload()).await;
+    let attempt   = login.json::<TwoFactorAuthResponse>().await.unwrap();
+    let data      = TwoFAData {login_attempt_id: attempt.login_attempt_id, two_fa_code: code};
+    let response  = app.post_verify_2fa(&create_2fa_payload(&user.email, &data)).await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
@@ -1,7 +1,9 @@
 use crate::helpers_arrange::*;
 use crate::helpers_assert::*;
 use crate::helpers_harness::TestApp;
+use auth_service::domain::{LoginAttemptId, TwoFACodeHash};
 use auth_service::routes::Verify2FARequest;
+use auth_service::utils::constants::{MAX_2FA_ATTEMPTS, PENDING_2FA_COOKIE_NAME};
 use serde_json::json;
 
 
@@ -22,20 +24,142 @@ async fn should_return_200_if_correct_code() {
 }
 
 #[tokio::test]
-async fn should_return_401_if_old_code() {
+async fn the_store_should_only_hold_a_hash_of_the_code() {
+    let mut app             = TestApp::new().await;
+    let (_, two_fa_data)    = setup_2fa_login_started(&app).await;
+    let login_attempt_id    = LoginAttemptId::parse(two_fa_data.login_attempt_id.clone()).unwrap();
+
+    let (_, stored)         = app.two_fa_code_store.read().await.get_code(&login_attempt_id).await.unwrap();
+
+    assert_eq!(stored.as_ref().len(), TwoFACodeHash::HEX_LENGTH);
+    assert!(!stored.as_ref().contains(&two_fa_data.two_fa_code), "The code must not be stored in plain text");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_for_a_login_attempt_with_another_in_flight() {
     let mut app             = TestApp::new().await;
     let (user, two_fa_data) = setup_2fa_login_started(&app).await;
     let payload             = create_2fa_payload(&user.email, &two_fa_data);
 
     // Act
-    app.post_login(&user.login_payload()).await;         // Second login call (invalidates the first login attempt)
-    let response = app.post_verify_2fa(&payload).await;  // Verify with old id and code
+    start_2fa_login(&app.another_device(), &user).await; // Second login call, on another device
+    let response = app.post_verify_2fa(&payload).await;  // Verify with the first id and code
 
     // Assert
+    assert_status(&response, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_for_other_login_attempts_once_one_completed() {
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+    let laptop              = app.another_device();
+    let login_attempt_id    = start_2fa_login(&laptop, &user).await;
+    let two_fa_code         = get_2fa_code(&app, &login_attempt_id).await;
+    let second              = TwoFAData {login_attempt_id, two_fa_code};
+    let response            = laptop.post_verify_2fa(&create_2fa_payload(&user.email, &second)).await;
+    assert_status(&response, 200, None);
+
+    // Act
+    let response            = app.post_verify_2fa(&create_2fa_payload(&user.email, &two_fa_data)).await;
+
+    // Assert
+    assert_status(&response, 401, Some("Completing a login must clean up the other attempts"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_without_the_pending_2fa_cookie_of_the_login() {
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+    let payload             = create_2fa_payload(&user.email, &two_fa_data);
+
+    let response            = app.another_device().post_verify_2fa(&payload).await;
+
+    assert_status(&response, 401, Some("The code alone must not complete a login"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_replace_the_pending_2fa_cookie_with_auth_cookies() {
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+
+    let response            = app.post_verify_2fa(&create_2fa_payload(&user.email, &two_fa_data)).await;
+
+    assert_status(&response, 200, None);
+    assert_has_auth_cookie(&response);
+    let pending             = response.cookies().find(|c| c.name() == PENDING_2FA_COOKIE_NAME);
+    assert!(pending.is_some_and(|c| c.value().is_empty()), "The pending 2FA cookie must be removed");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_the_login_attempt_is_someone_elses() {
+    let mut app             = TestApp::new().await;
+    let (_, two_fa_data)    = setup_2fa_login_started(&app).await;
+    let (other, _)          = setup_2fa_login_started(&app).await;
+
+    let response            = app.post_verify_2fa(&create_2fa_payload(&other.email, &two_fa_data)).await;
+
     assert_status(&response, 401, None);
     app.clean_up().await;
 }
 
+// A well formed code that is not the one we sent
+//
+fn wrong_code(data: &TwoFAData) -> TwoFAData {
+    let two_fa_code = match data.two_fa_code.as_str() {
+        "000000" => "111111",
+        _        => "000000",
+    };
+    TwoFAData {login_attempt_id: data.login_attempt_id.clone(), two_fa_code: two_fa_code.to_owned()}
+}
+
+#[tokio::test]
+async fn should_return_429_and_invalidate_the_code_after_too_many_wrong_codes() {
+    // Arrange
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+    let wrong               = create_2fa_payload(&user.email, &wrong_code(&two_fa_data));
+    for _ in 1..*MAX_2FA_ATTEMPTS {
+        let response = app.post_verify_2fa(&wrong).await;
+        assert_status(&response, 401, None);
+    }
+
+    // Act
+    let response            = app.post_verify_2fa(&wrong).await;
+
+    // Assert
+    assert_status(&response, 429, None);
+    assert_error_message(response, "Too many attempts").await;
+    let right               = create_2fa_payload(&user.email, &two_fa_data);
+    let response            = app.post_verify_2fa(&right).await;
+    assert_status(&response, 401, Some("The code must be gone once the limit was reached"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_start_counting_again_for_a_new_login_attempt() {
+    let mut app             = TestApp::new().await;
+    let (user, two_fa_data) = setup_2fa_login_started(&app).await;
+    let wrong               = create_2fa_payload(&user.email, &wrong_code(&two_fa_data));
+    for _ in 1..*MAX_2FA_ATTEMPTS {
+        app.post_verify_2fa(&wrong).await;
+    }
+
+    let login_attempt_id    = start_2fa_login(&app, &user).await;
+    let two_fa_code         = get_2fa_code(&app, &login_attempt_id).await;
+    let fresh               = TwoFAData {login_attempt_id, two_fa_code};
+    let response            = app.post_verify_2fa(&create_2fa_payload(&user.email, &wrong_code(&fresh))).await;
+    assert_status(&response, 401, Some("A new login attempt has its own count"));
+    let response            = app.post_verify_2fa(&create_2fa_payload(&user.email, &fresh)).await;
+    assert_status(&response, 200, None);
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_401_if_same_code_twice() {
     // Arrange
@@ -66,7 +190,7 @@ async fn should_return_422_if_malformed_input() {
 #[tokio::test]
 async fn should_return_400_if_invalid_object_input() {
     let mut app  = TestApp::new().await;
-    let body     = Verify2FARequest{ email: "not_an_email".to_string(), login_attempt_id: "466b32c2-6862-4c18-ada0-7d59eaf6e004".to_string(), code: "123456".to_string()};
+    let body     = Verify2FARequest{ email: "not_an_email".to_string(), login_attempt_id: "466b32c2-6862-4c18-ada0-7d59eaf6e004".to_string(), code: "123456".to_string(), remember_me: false};
     let response = app.post_verify_2fa(&body).await;
     assert_status(&response, 400, None);
     app.clean_up().await;
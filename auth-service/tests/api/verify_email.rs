@@ -0,0 +1,133 @@
+use crate::helpers_arrange::*;
+use crate::helpers_assert::{assert_error_message, assert_has_auth_cookie, assert_status};
+use crate::helpers_harness::TestApp;
+use auth_service::routes::VerifyEmailResponse;
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use serde_json::json;
+
+// Sign up without opening the verification link
+//
+async fn signup_unverified(app: &TestApp) -> TestUser {
+    let user     = TestUser::new();
+    let response = app.post_signup(&user.signup_payload()).await;
+    assert_status(&response, 201, None);
+    user
+}
+
+#[tokio::test]
+async fn signup_should_email_a_verification_link() {
+    let mut app  = TestApp::new().await;
+
+    let user     = signup_unverified(&app).await;
+
+    let token    = last_verification_token(&app, &user.email).await;
+    assert!(!token.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_return_403_until_the_email_is_verified() {
+    let mut app  = TestApp::new().await;
+    let user     = signup_unverified(&app).await;
+
+    let response = app.post_login(&user.login_payload()).await;
+
+    assert_status(&response, 403, None);
+    assert!(response.cookies().all(|cookie| cookie.name() != JWT_COOKIE_NAME), "No auth cookie before verification");
+    assert_error_message(response, "Email address not verified").await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_with_a_wrong_password_should_not_reveal_whether_the_email_is_verified() {
+    let mut app  = TestApp::new().await;
+    let user     = signup_unverified(&app).await;
+
+    let response = app.post_login(&json!({"email": user.email, "password": "Wrong1234**"})).await;
+
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_email_should_let_the_user_log_in() {
+    // Arrange
+    let mut app  = TestApp::new().await;
+    let user     = signup_unverified(&app).await;
+    let token    = last_verification_token(&app, &user.email).await;
+
+    // Act
+    let response = app.get_verify_email(&token).await;
+
+    // Assert
+    assert_status(&response, 200, None);
+    let body     = response.json::<VerifyEmailResponse>().await.unwrap();
+    assert_eq!(body.message, "Email address verified");
+    let response = app.post_login(&user.login_payload()).await;
+    assert_status(&response, 200, None);
+    assert_has_auth_cookie(&response);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_email_link_should_only_work_once() {
+    let mut app  = TestApp::new().await;
+    let user     = signup_unverified(&app).await;
+    let token    = last_verification_token(&app, &user.email).await;
+    assert_status(&app.get_verify_email(&token).await, 200, None);
+
+    let response = app.get_verify_email(&token).await;
+
+    assert_status(&response, 401, Some("A verification link must not be accepted twice"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_before_verification_should_email_a_new_link() {
+    let mut app  = TestApp::new().await;
+    let user     = signup_unverified(&app).await;
+    let first    = last_verification_token(&app, &user.email).await;
+    assert_status(&app.post_login(&user.login_payload()).await, 403, None);
+
+    let second   = last_verification_token(&app, &user.email).await;
+    let response = app.get_verify_email(&second).await;
+
+    assert_ne!(first, second);
+    assert_status(&response, 200, None);
+    assert_status(&app.post_login(&user.login_payload()).await, 200, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_email_should_return_401_for_an_invalid_token() {
+    let mut app  = TestApp::new().await;
+    let response = app.get_verify_email("not-a-token").await;
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verify_email_should_not_accept_an_auth_token() {
+    let mut app   = TestApp::new().await;
+    let user      = TestUser::new();
+    setup_registered_user(&app, &user).await;
+    let login     = app.post_login(&user.login_payload()).await;
+    let auth      = get_cookie_value(&login, JWT_COOKIE_NAME);
+
+    let response  = app.get_verify_email(&auth).await;
+
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn verification_token_should_not_be_accepted_as_an_auth_token() {
+    let mut app  = TestApp::new().await;
+    let user     = signup_unverified(&app).await;
+    let token    = last_verification_token(&app, &user.email).await;
+
+    let response = app.post_verify_token(&json!({"token": token})).await;
+
+    assert_status(&response, 401, None);
+    app.clean_up().await;
+}
@@ -1,9 +1,10 @@
-use crate::helpers_arrange::setup_logged_in_user;
+use crate::helpers_arrange::{get_token_claims, setup_logged_in_user};
 use crate::helpers_assert::assert_status;
 use crate::helpers_harness::TestApp;
 use crate::helpers_harness::get_random_email;
 use auth_service::domain::Email;
 use auth_service::utils::auth::generate_jwt_auth_token;
+use auth_service::utils::constants::TOKEN_POLICY;
 use secrecy::Secret;
 use serde_json::json;
 
@@ -58,9 +59,9 @@ async fn should_return_401_if_token_is_banned() {
     // Assert
     assert_status(&response, 401, None);
     {
-        let secret        = Secret::new(token.clone());
+        let claims        = get_token_claims(&token);
         let banned_tokens = app.banned_tokens.read().await;
-        assert!(banned_tokens.contains_token(&secret).await);
+        assert!(banned_tokens.contains_token(&claims.jti).await.unwrap());
     }
     app.clean_up().await;
 }
@@ -71,7 +72,7 @@ async fn should_return_401_if_banned_token_crt() {
     let email    = get_random_email();
     let email    = Secret::new(email);
     let email    = Email::parse(email).unwrap();
-    let token    = generate_jwt_auth_token(&email).unwrap();
+    let token    = generate_jwt_auth_token(&email, "session-1", TOKEN_POLICY.access_ttl).unwrap();
     println!("token: {}", token);
 
     // Now that we have a token, before we post it, let's add it to the
@@ -81,8 +82,8 @@ async fn should_return_401_if_banned_token_crt() {
     //
     {
         let mut tokens = app.banned_tokens.write().await;
-        let secret     = Secret::new(token.clone());
-        tokens.add_token(&secret).await.unwrap();
+        let claims     = get_token_claims(&token);
+        tokens.add_token(&claims.jti, claims.exp).await.unwrap();
         println!("token added");
     }
 